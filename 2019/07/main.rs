@@ -1,3 +1,4 @@
+use intcode::{Machine, Network, Route};
 use std::iter::*;
 
 type Parsed = Vec<i128>;
@@ -20,25 +21,21 @@ fn part2(numbers: &Parsed) -> i128 {
     let phases = vec![5, 6, 7, 8, 9];
     let mut max_power = 0;
     for permutation in permute::permutations_of(&phases) {
-        let mut machines: Vec<intcode::Machine> = permutation
-            .map(|x| intcode::Machine::with_input(numbers, &[*x]))
-            .collect();
-        machines[0].add_input(0);
-        let mut last_output = None;
-        let power = loop {
-            let mut out = None;
-            for i in 0..machines.len() {
-                if let Some(v) = machines[i].run_to_next_output() {
-                    machines[(i + 1) % 5].add_input(v);
-                    out = Some(v)
-                }
-            }
-            if let Some(v) = out {
-                last_output = Some(v);
-            } else {
-                break last_output.unwrap();
+        let mut network = Network::new();
+        for (i, x) in permutation.enumerate() {
+            network.add_machine(
+                Machine::with_input(numbers, &[*x]),
+                Route::Pipe((i + 1) % 5),
+            );
+        }
+        network.send(0, &[0]);
+        let mut power = 0;
+        network.run(|e| {
+            if e.from == 4 {
+                power = e.values[0];
             }
-        };
+            false
+        });
         max_power = std::cmp::max(max_power, power);
     }
     max_power
@@ -19,28 +19,23 @@ fn part1(layers: &Parsed) -> usize {
     ans
 }
 
-fn part2(layers: &Parsed) -> usize {
-    for y in 0..6 {
-        for x in 0..25 {
-            let mut pixel = 2u32;
+fn part2(layers: &Parsed) -> String {
+    let mut image = vec![vec![2u32; 25]; 6];
+    for (y, row) in image.iter_mut().enumerate() {
+        for (x, pixel) in row.iter_mut().enumerate() {
             for layer in layers.iter().rev() {
                 let v = layer[y * 25 + x];
                 match v {
-                    0 => pixel = 0,
-                    1 => pixel = 1,
+                    0 => *pixel = 0,
+                    1 => *pixel = 1,
                     2 => {}
                     _ => panic!("OH NOES"),
                 }
             }
-            match pixel {
-                0 => print!(" "),
-                1 => print!("█"),
-                _ => panic!("OH NOES"),
-            }
         }
-        println!();
     }
-    0
+    aoc::ocr::ocr(&image, |v| v == 1)
+        .unwrap_or_else(|e| panic!("could not read the message: {}", e))
 }
 
 fn parse(lines: &[String]) -> Parsed {
@@ -67,15 +62,31 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-    // use super::{part1, part2};
-
-    // #[test]
-    // fn test_part1() {
-    //     assert_eq!(part1(&vec![0]), 0);
-    // }
+    use super::*;
 
-    // #[test]
-    // fn test_part2() {
-    //     assert_eq!(part2(&vec![0]), 0);
-    // }
+    #[test]
+    fn test_part2() {
+        // The message on the bottom layer, mostly hidden by a transparent one
+        let message = [
+            "#..#.####.#....#.....##..",
+            "#..#.#....#....#....#..#.",
+            "####.###..#....#....#..#.",
+            "#..#.#....#....#....#..#.",
+            "#..#.#....#....#....#..#.",
+            "#..#.####.####.####..##..",
+        ];
+        let bottom: String = message
+            .concat()
+            .chars()
+            .map(|c| if c == '#' { '1' } else { '0' })
+            .collect();
+        let top: String = bottom
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if i % 7 == 0 { c } else { '2' })
+            .collect();
+        let layers = parse(&[top + &bottom]);
+        assert_eq!(layers.len(), 2);
+        assert_eq!(part2(&layers), "HELLO");
+    }
 }
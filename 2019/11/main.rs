@@ -99,28 +99,16 @@ fn part1(numbers: &Parsed) -> i128 {
     hull.len() as i128
 }
 
-fn print_hull(hull: &HashMap<(i128, i128), i128>) {
-    let min_x = hull.iter().map(|p| (p.0).0).min().unwrap();
-    let min_y = hull.iter().map(|p| (p.0).1).min().unwrap();
-    let max_x = hull.iter().map(|p| (p.0).0).max().unwrap();
-    let max_y = hull.iter().map(|p| (p.0).1).max().unwrap();
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let c = match hull.get(&(x, y)) {
-                Some(c) => *c,
-                None => 0,
-            };
-            if c == 0 {
-                print!(" ");
-            } else {
-                print!("█");
-            }
-        }
-        println!();
-    }
+// Reads the letters painted white
+fn read_hull(hull: &HashMap<(i128, i128), i128>) -> String {
+    let grid: HashMap<aoc::Point, i128> = hull
+        .iter()
+        .map(|((x, y), c)| ([*x as i64, *y as i64], *c))
+        .collect();
+    aoc::ocr::ocr(&grid, |c| c == 1).unwrap_or_else(|e| panic!("could not read the hull: {}", e))
 }
 
-fn part2(numbers: &Parsed) -> i128 {
+fn part2(numbers: &Parsed) -> String {
     let hull = if cfg!(feature = "vis") {
         let window = initscr();
         nl();
@@ -129,12 +117,13 @@ fn part2(numbers: &Parsed) -> i128 {
         window.keypad(true);
         window.scrollok(true);
         window.timeout(20);
-        paint(numbers, 1, Some(&window))
+        let hull = paint(numbers, 1, Some(&window));
+        endwin();
+        hull
     } else {
         paint(numbers, 1, None)
     };
-    print_hull(&hull);
-    0
+    read_hull(&hull)
 }
 
 /////// Disassembled input
@@ -160,7 +149,7 @@ impl Disassembled {
 
     fn input(&mut self) {}
 
-    pub fn run(&mut self) {
+    pub fn run(&mut self) -> HashMap<(i128, i128), i128> {
         self.out(0);
         self.out(1);
         self.f459(48092525312);
@@ -245,7 +234,7 @@ impl Disassembled {
                 Dir::Left => x -= 1,
             }
         }
-        print_hull(&hull);
+        hull
     }
 
     fn f459(&mut self, a1: i128) {
@@ -293,5 +282,23 @@ fn main() {
     aoc::run_main(intcode::parse_intcode, part1, part2);
 
     let mut d = Disassembled::new();
-    d.run();
+    println!("Disassembled: {}", read_hull(&d.run()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part2() {
+        // A robot program giving the disassembled outputs
+        let mut d = Disassembled::new();
+        assert_eq!(read_hull(&d.run()), "JKZLZJBH");
+        let mut program = vec![];
+        for pair in d.outs.chunks(2) {
+            program.extend([3, 0, 104, pair[0], 104, pair[1]]);
+        }
+        program.push(99);
+        assert_eq!(part2(&program), "JKZLZJBH");
+    }
 }
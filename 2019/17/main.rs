@@ -354,28 +354,14 @@ fn part2(program: &Parsed) -> i128 {
         inp.push('n');
     }
     inp.push('\n');
-    for i in &inp {
-        print!("{}", i);
-    }
-    for c in inp {
-        m.add_input(c as i128);
-    }
-    let mut res = 0;
-    loop {
-        let state = m.run_to_next_io();
-        if state != intcode::State::Output {
-            break;
-        }
-        let o = m.outputs();
-        for c in o {
-            if c > 255 {
-                res = c;
-                println!("{}", c);
-            } else {
-                print!("{}", to_char(c));
-            }
-        }
-    }
+    let input: String = inp.into_iter().collect();
+    print!("{}", input);
+    let mut m = intcode::AsciiMachine::from_machine(m);
+    m.send(&input);
+    let out = m.read_text();
+    print!("{}", out.text);
+    let res = out.values.last().copied().unwrap_or(0);
+    println!("{}", res);
     res
 }
 
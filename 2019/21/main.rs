@@ -1,5 +1,4 @@
 use intcode::*;
-use rustyline::Editor;
 use std::{cmp::Ordering, collections::VecDeque};
 
 type Parsed = Vec<i128>;
@@ -12,41 +11,8 @@ type Parsed = Vec<i128>;
 // >> AND D J
 // >> WALK
 fn part1(program: &Parsed) -> i128 {
-    let mut m = Machine::new(program);
-    let mut rl = Editor::<()>::new();
-    let mut state = m.run_to_next_io();
-    loop {
-        match state {
-            State::Output => {
-                for o in m.outputs() {
-                    if (0..256).contains(&o) {
-                        print!("{}", std::char::from_u32(o as u32).unwrap());
-                    } else {
-                        println!("value: {}", o);
-                    }
-                }
-                state = m.run_to_next_io();
-            }
-            State::Input => {
-                let readline = rl.readline(">> ");
-                match readline {
-                    Ok(s) => {
-                        let x = s.trim();
-                        for c in x.chars() {
-                            m.add_input(c as i128);
-                        }
-                        m.add_input(10);
-                        if x == "WALK" || x == "RUN" {
-                            state = m.run_to_next_io();
-                        }
-                    }
-                    Err(_) => break,
-                }
-            }
-            _ => break,
-        }
-    }
-    0
+    let values = AsciiMachine::new(program).interact();
+    values.last().copied().unwrap_or(0)
 }
 
 fn read(v: u32, ch: char) -> bool {
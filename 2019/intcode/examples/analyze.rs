@@ -0,0 +1,22 @@
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::Path;
+
+// analyze <file> [--dot]: pseudo-C for the program, or its graph as DOT
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let f = File::open(Path::new(&args[1])).unwrap();
+    let lines: Vec<_> = BufReader::new(f)
+        .lines()
+        .map_while(Result::ok)
+        .map(|x| x.trim().to_string())
+        .collect();
+    let cfg = intcode::Cfg::new(&intcode::parse_intcode(&lines));
+    if args.iter().any(|a| a == "--dot") {
+        print!("{}", cfg.to_dot());
+    } else {
+        print!("{}", cfg.to_pseudo_c());
+    }
+}
@@ -0,0 +1,27 @@
+use intcode::{assemble, disassemble, parse_intcode};
+use std::env;
+use std::fs::read_to_string;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    if args.len() != 3 || (args[1] != "-a" && args[1] != "-d") {
+        println!("usage: asm -a file.asm   (assemble to intcode)");
+        println!("       asm -d file.txt   (disassemble intcode)");
+        return;
+    }
+
+    let s = read_to_string(&args[2]).unwrap();
+    if args[1] == "-a" {
+        match assemble(&s) {
+            Ok(code) => {
+                let code: Vec<String> = code.iter().map(|v| v.to_string()).collect();
+                println!("{}", code.join(","));
+            }
+            Err(e) => println!("{}", e),
+        }
+    } else {
+        let lines: Vec<String> = s.lines().map(|l| l.trim().to_string()).collect();
+        print!("{}", disassemble(&parse_intcode(&lines)));
+    }
+}
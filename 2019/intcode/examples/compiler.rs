@@ -1,4 +1,4 @@
-use intcode::{parse, tokenize, ParseError};
+use intcode::{compile, parse, tokenize, ParseError};
 use std::env;
 use std::fs::read_to_string;
 use std::iter::*;
@@ -6,8 +6,8 @@ use std::iter::*;
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 2 {
-        println!("usage: compiler filename");
+    if args.len() < 2 || args.len() > 3 || (args.len() == 3 && args[2] != "--ast") {
+        println!("usage: compiler filename [--ast]");
         return;
     }
 
@@ -15,9 +15,17 @@ fn main() {
     let s = read_to_string(filename).unwrap();
     let lines: Vec<_> = s.split("\n").collect();
     match parse(&tokenize(&s)) {
-        Ok(program) => {
+        Ok(program) if args.len() == 3 => {
             println!("{:?}", program);
         }
+        // Intcode on one line, to run with the debug example
+        Ok(program) => match compile(&program) {
+            Ok(code) => {
+                let code: Vec<String> = code.iter().map(|v| v.to_string()).collect();
+                println!("{}", code.join(","));
+            }
+            Err(e) => println!("{}", e),
+        },
         Err(ParseError::SyntaxError(s, loc)) => {
             println!("{}", s);
             println!("{}", lines[loc.start.line]);
@@ -6,7 +6,13 @@ use std::iter::*;
 use std::path::Path;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // -x <file> runs the debugger commands in file instead of a terminal
+    let script = args.iter().position(|a| a == "-x").map(|i| {
+        let file = args.remove(i + 1);
+        args.remove(i);
+        file
+    });
 
     let (program, input) = if args.len() > 1 {
         let filename = &args[1];
@@ -16,7 +22,7 @@ fn main() {
             intcode::parse_intcode(
                 &buffered
                     .lines()
-                    .filter_map(Result::ok)
+                    .map_while(Result::ok)
                     .map(|x| x.trim().to_string())
                     .collect::<Vec<_>>(),
             ),
@@ -43,5 +49,11 @@ fn main() {
     let mut m = intcode::Machine::with_input(&program, &input);
     let mut debugger = intcode::Debugger::new(&mut m);
     debugger.analyze();
-    debugger.debug();
+    if let Some(file) = script {
+        let f = File::open(Path::new(&file)).unwrap();
+        let lines: Vec<_> = BufReader::new(f).lines().map_while(Result::ok).collect();
+        debugger.run_script(&lines);
+    } else {
+        debugger.debug();
+    }
 }
@@ -0,0 +1,53 @@
+use aoc::GridDrawer;
+use intcode::{heat_color, Counter, Profile};
+use std::env;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::path::Path;
+
+// Addresses per row of the heatmaps, and pixels per address
+const WIDTH: usize = 64;
+const SCALE: usize = 4;
+
+// profile <file> [inputs]: runs the program until it halts or needs more
+// input, then prints the report and saves heatmaps in profile/
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let f = File::open(Path::new(&args[1])).unwrap();
+    let lines: Vec<_> = BufReader::new(f)
+        .lines()
+        .map_while(Result::ok)
+        .map(|x| x.trim().to_string())
+        .collect();
+    let input: Vec<i128> = args
+        .get(2)
+        .map(|s| s.split(',').map(|v| v.trim().parse().unwrap()).collect())
+        .unwrap_or_default();
+    let mut m = intcode::Machine::with_input(&intcode::parse_intcode(&lines), &input);
+    m.set_stats(true);
+    println!("{:?}", m.run_to_next_input());
+    let profile = Profile::new(&m);
+    print!("{}", profile.report(10));
+    for (name, counter) in [
+        ("executes", Counter::Executes),
+        ("reads", Counter::Reads),
+        ("writes", Counter::Writes),
+    ] {
+        let heat: Vec<Vec<usize>> = profile
+            .heatmap(counter, WIDTH)
+            .iter()
+            .flat_map(|row| {
+                let row: Vec<usize> = row
+                    .iter()
+                    .flat_map(|c| std::iter::repeat_n(*c, SCALE))
+                    .collect();
+                std::iter::repeat_n(row, SCALE)
+            })
+            .collect();
+        let max = heat.iter().flatten().copied().max().unwrap_or(0);
+        let mut d =
+            aoc::BitmapGridDrawer::new(|c| heat_color(c, max), &format!("profile/{}", name));
+        d.draw(&heat);
+    }
+}
@@ -0,0 +1,549 @@
+//! Static analysis of Intcode programs
+//!
+//! Code is found by following the control flow from address 0, everything
+//! else is data. Jumps with an immediate target end basic blocks, and so do
+//! jumps through memory, which are taken to be returns. An unconditional
+//! jump is a call when the address after it was just stored somewhere, as a
+//! return address, which is how both the compiler and the puzzle programs do
+//! it. Call targets start functions.
+//!
+//! The graph can be written as DOT, or as pseudo-C like 2018/21 does for
+//! elfcode.
+
+use crate::machine::{Arg, Disassembly, Instruction, Machine, Op};
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+// How a basic block ends
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Exit {
+    // Falls through to the next block
+    Next(usize),
+    Jump(usize),
+    Branch { taken: usize, next: usize },
+    Call { target: usize, next: usize },
+    // A jump through memory, next is set when it is conditional
+    Return { next: Option<usize> },
+    Halt,
+    // Runs into something that isn't an instruction
+    Invalid,
+}
+
+impl Exit {
+    // Blocks control can continue in, not counting called functions
+    pub fn successors(&self) -> Vec<usize> {
+        match *self {
+            Exit::Next(n) | Exit::Jump(n) => vec![n],
+            Exit::Branch { taken, next } => vec![taken, next],
+            Exit::Call { next, .. } => vec![next],
+            Exit::Return { next } => next.into_iter().collect(),
+            Exit::Halt | Exit::Invalid => vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    // Addresses of the instructions
+    pub instructions: Vec<usize>,
+    pub exit: Exit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    pub entry: usize,
+    // Size of the stack frame, from an SP at the entry
+    pub frame: i128,
+    // Start of the blocks, in address order
+    pub blocks: Vec<usize>,
+}
+
+impl Function {
+    pub fn name(&self) -> String {
+        if self.entry == 0 {
+            "main".into()
+        } else {
+            format!("f_{:04}", self.entry)
+        }
+    }
+}
+
+pub struct Cfg {
+    machine: Machine,
+    // Length of each instruction found
+    instructions: BTreeMap<usize, usize>,
+    blocks: BTreeMap<usize, Block>,
+    functions: Vec<Function>,
+}
+
+// How far back a call looks for the return address being stored
+const CALL_WINDOW: usize = 8;
+
+impl Cfg {
+    pub fn new(program: &[i128]) -> Cfg {
+        let mut cfg = Cfg {
+            machine: Machine::new(program),
+            instructions: BTreeMap::new(),
+            blocks: BTreeMap::new(),
+            functions: vec![],
+        };
+        let leaders = cfg.discover();
+        cfg.build_blocks(&leaders);
+        cfg.build_functions();
+        cfg
+    }
+
+    fn instruction(&self, address: usize) -> Option<Instruction> {
+        if address >= self.machine.memory().len() {
+            return None;
+        }
+        match self.machine.get_disassembly(address) {
+            Disassembly::Instruction(ins) => Some(ins),
+            Disassembly::MemoryValue(_) => None,
+        }
+    }
+
+    // Whether an instruction shortly before address stores value
+    fn stores_before(&self, address: usize, value: i128) -> bool {
+        let mut a = address;
+        for _ in 0..CALL_WINDOW {
+            let prev = match self.instructions.range(..a).next_back() {
+                Some((p, len)) if p + len == a => *p,
+                _ => return false,
+            };
+            let ins = self.instruction(prev).unwrap();
+            if let [Arg::Immediate { value: x }, Arg::Immediate { value: y }] = ins.read() {
+                let v = match ins.op {
+                    Op::ADD => Some(x + y),
+                    Op::MUL => Some(x * y),
+                    _ => None,
+                };
+                if v == Some(value) {
+                    return true;
+                }
+            }
+            if matches!(ins.op, Op::JIT | Op::JIF | Op::HLT) {
+                return false;
+            }
+            a = prev;
+        }
+        false
+    }
+
+    // How the instruction at address changes the control flow, None when it
+    // continues with the next one
+    fn flow(&self, ins: &Instruction) -> Option<Exit> {
+        let next = ins.address + ins.increment();
+        match ins.op {
+            Op::HLT => Some(Exit::Halt),
+            Op::JIT | Op::JIF => {
+                let jumps = |v: i128| (v != 0) == (ins.op == Op::JIT);
+                let always = match ins.read()[0] {
+                    Arg::Immediate { value } => Some(jumps(value)),
+                    _ => None,
+                };
+                Some(match (always, ins.read()[1]) {
+                    (Some(false), _) => Exit::Next(next),
+                    (_, Arg::Immediate { value }) if value < 0 => Exit::Invalid,
+                    (Some(true), Arg::Immediate { value }) => {
+                        if self.stores_before(ins.address, next as i128) {
+                            Exit::Call {
+                                target: value as usize,
+                                next,
+                            }
+                        } else {
+                            Exit::Jump(value as usize)
+                        }
+                    }
+                    (None, Arg::Immediate { value }) => Exit::Branch {
+                        taken: value as usize,
+                        next,
+                    },
+                    (Some(true), _) => Exit::Return { next: None },
+                    (None, _) => Exit::Return { next: Some(next) },
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // Finds the instructions reachable from 0, returns the block starts
+    fn discover(&mut self) -> BTreeSet<usize> {
+        let mut leaders = BTreeSet::new();
+        leaders.insert(0);
+        let mut todo = vec![0];
+        while let Some(a) = todo.pop() {
+            if self.instructions.contains_key(&a) {
+                continue;
+            }
+            let ins = match self.instruction(a) {
+                Some(ins) => ins,
+                None => continue,
+            };
+            self.instructions.insert(a, ins.increment());
+            match self.flow(&ins) {
+                None => todo.push(a + ins.increment()),
+                Some(exit) => {
+                    if let Exit::Call { target, .. } = exit {
+                        leaders.insert(target);
+                        todo.push(target);
+                    }
+                    for s in exit.successors() {
+                        leaders.insert(s);
+                        todo.push(s);
+                    }
+                }
+            }
+        }
+        leaders
+    }
+
+    fn build_blocks(&mut self, leaders: &BTreeSet<usize>) {
+        for &start in leaders {
+            if !self.instructions.contains_key(&start) {
+                continue;
+            }
+            let mut block = Block {
+                start,
+                instructions: vec![],
+                exit: Exit::Invalid,
+            };
+            let mut a = start;
+            while let Some(len) = self.instructions.get(&a) {
+                block.instructions.push(a);
+                if let Some(exit) = self.flow(&self.instruction(a).unwrap()) {
+                    block.exit = exit;
+                    break;
+                }
+                a += len;
+                if leaders.contains(&a) {
+                    block.exit = Exit::Next(a);
+                    break;
+                }
+            }
+            self.blocks.insert(start, block);
+        }
+    }
+
+    fn build_functions(&mut self) {
+        let mut entries = BTreeSet::new();
+        entries.insert(0);
+        for b in self.blocks.values() {
+            if let Exit::Call { target, .. } = b.exit {
+                entries.insert(target);
+            }
+        }
+        for entry in entries {
+            let mut seen = BTreeSet::new();
+            let mut todo = vec![entry];
+            while let Some(b) = todo.pop() {
+                if let Some(block) = self.blocks.get(&b) {
+                    if seen.insert(b) {
+                        todo.extend(block.exit.successors());
+                    }
+                }
+            }
+            let frame = self
+                .blocks
+                .get(&entry)
+                .and_then(|b| {
+                    b.instructions.iter().find_map(|a| {
+                        let ins = self.instruction(*a)?;
+                        match (ins.op, ins.read()) {
+                            (Op::SP, [Arg::Immediate { value }]) if *value > 0 => Some(*value),
+                            _ => None,
+                        }
+                    })
+                })
+                .unwrap_or(0);
+            self.functions.push(Function {
+                entry,
+                frame,
+                blocks: seen.into_iter().collect(),
+            });
+        }
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = &Block> {
+        self.blocks.values()
+    }
+
+    pub fn block(&self, start: usize) -> Option<&Block> {
+        self.blocks.get(&start)
+    }
+
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    pub fn is_code(&self, address: usize) -> bool {
+        match self.instructions.range(..=address).next_back() {
+            Some((a, len)) => address < a + len,
+            None => false,
+        }
+    }
+
+    // Ranges of addresses that aren't code, end exclusive
+    pub fn data(&self) -> Vec<(usize, usize)> {
+        let mut ranges = vec![];
+        let mut a = 0;
+        for (start, len) in &self.instructions {
+            if *start > a {
+                ranges.push((a, *start));
+            }
+            a = a.max(start + len);
+        }
+        let end = self.machine.memory().len();
+        if a < end {
+            ranges.push((a, end));
+        }
+        ranges
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "digraph cfg {{").unwrap();
+        writeln!(s, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for f in &self.functions {
+            writeln!(s, "    b{} [xlabel=\"{}\"];", f.entry, f.name()).unwrap();
+        }
+        for b in self.blocks.values() {
+            let mut label = String::new();
+            for a in &b.instructions {
+                write!(label, "{}\\l", self.instruction(*a).unwrap()).unwrap();
+            }
+            writeln!(s, "    b{} [label=\"{}\"];", b.start, label).unwrap();
+            let edges = match b.exit {
+                Exit::Branch { taken, next } => vec![(taken, "label=\"T\""), (next, "label=\"F\"")],
+                Exit::Call { target, next } => vec![(target, "style=dashed"), (next, "")],
+                _ => b.exit.successors().into_iter().map(|n| (n, "")).collect(),
+            };
+            for (to, attributes) in edges {
+                if attributes.is_empty() {
+                    writeln!(s, "    b{} -> b{};", b.start, to).unwrap();
+                } else {
+                    writeln!(s, "    b{} -> b{} [{}];", b.start, to, attributes).unwrap();
+                }
+            }
+        }
+        writeln!(s, "}}").unwrap();
+        s
+    }
+
+    fn function_name(&self, entry: usize) -> String {
+        match self.functions.iter().find(|f| f.entry == entry) {
+            Some(f) => f.name(),
+            None => format!("f_{:04}", entry),
+        }
+    }
+
+    fn statement(&self, ins: &Instruction, exit: Option<Exit>) -> String {
+        let r = ins.read();
+        let w = ins.write();
+        let condition = || match ins.op {
+            Op::JIT => expression(&r[0]),
+            _ => format!("!{}", expression(&r[0])),
+        };
+        match (ins.op, exit) {
+            // Copies, as the compiler writes them
+            (Op::ADD, _) if matches!(r[1], Arg::Immediate { value: 0 }) => {
+                format!("{} = {};", expression(&w[0]), expression(&r[0]))
+            }
+            (Op::ADD, _) => format!(
+                "{} = {} + {};",
+                expression(&w[0]),
+                expression(&r[0]),
+                expression(&r[1])
+            ),
+            (Op::MUL, _) => format!(
+                "{} = {} * {};",
+                expression(&w[0]),
+                expression(&r[0]),
+                expression(&r[1])
+            ),
+            (Op::LTN, _) => format!(
+                "{} = {} < {};",
+                expression(&w[0]),
+                expression(&r[0]),
+                expression(&r[1])
+            ),
+            (Op::EQL, _) => format!(
+                "{} = {} == {};",
+                expression(&w[0]),
+                expression(&r[0]),
+                expression(&r[1])
+            ),
+            (Op::INP, _) => format!("{} = input();", expression(&w[0])),
+            (Op::OUT, _) => format!("output({});", expression(&r[0])),
+            (Op::SP, _) => format!("sp += {};", expression(&r[0])),
+            (Op::HLT, _) => "halt();".into(),
+            (_, Some(Exit::Jump(t))) => format!("goto L{:04};", t),
+            (_, Some(Exit::Branch { taken, .. })) => {
+                format!("if ({}) goto L{:04};", condition(), taken)
+            }
+            (_, Some(Exit::Call { target, .. })) => format!("{}();", self.function_name(target)),
+            (_, Some(Exit::Return { next: None })) => {
+                format!("return; /* to {} */", expression(&r[1]))
+            }
+            (_, Some(Exit::Return { .. })) => {
+                format!(
+                    "if ({}) return; /* to {} */",
+                    condition(),
+                    expression(&r[1])
+                )
+            }
+            (_, Some(Exit::Invalid)) => format!("goto {}; /* invalid */", expression(&r[1])),
+            _ => "/* never jumps */".into(),
+        }
+    }
+
+    // Pseudo-C for each function, with the address of each instruction
+    pub fn to_pseudo_c(&self) -> String {
+        let mut s = String::new();
+        for f in &self.functions {
+            if f.frame > 0 {
+                writeln!(s, "void {}() {{ // frame {}", f.name(), f.frame).unwrap();
+            } else {
+                writeln!(s, "void {}() {{", f.name()).unwrap();
+            }
+            for (i, start) in f.blocks.iter().enumerate() {
+                let b = &self.blocks[start];
+                writeln!(s, "L{:04}:", b.start).unwrap();
+                for (j, a) in b.instructions.iter().enumerate() {
+                    let ins = self.instruction(*a).unwrap();
+                    let last = j + 1 == b.instructions.len();
+                    let exit = if last { Some(b.exit) } else { None };
+                    writeln!(s, "    {:<40}// {:04}", self.statement(&ins, exit), a).unwrap();
+                }
+                // Control continuing somewhere else than the next block
+                let fall = match b.exit {
+                    Exit::Next(n) | Exit::Branch { next: n, .. } | Exit::Call { next: n, .. } => {
+                        Some(n)
+                    }
+                    Exit::Return { next } => next,
+                    _ => None,
+                };
+                if let Some(n) = fall {
+                    if f.blocks.get(i + 1) != Some(&n) {
+                        writeln!(s, "    goto L{:04};", n).unwrap();
+                    }
+                }
+            }
+            writeln!(s, "}}").unwrap();
+            writeln!(s).unwrap();
+        }
+        for (start, end) in self.data() {
+            let values: Vec<_> = self.machine.memory()[start..end]
+                .iter()
+                .map(|v| v.to_string())
+                .collect();
+            writeln!(s, "// data {:04}: {}", start, values.join(", ")).unwrap();
+        }
+        s
+    }
+}
+
+fn expression(arg: &Arg) -> String {
+    match arg {
+        Arg::Immediate { value } => value.to_string(),
+        Arg::Position { address } => format!("mem[{}]", address),
+        Arg::Relative { offset, .. } => format!("sp[{}]", offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, compile, parse, tokenize};
+
+    // main calls double twice, which keeps its argument on the stack
+    fn calls() -> Vec<i128> {
+        assemble(
+            "\
+        SP 100
+        INP [x]
+        ADD [x], 0 -> [SP+1]
+        ADD back1, 0 -> [SP+0]
+        JIT 1, double
+back1:  ADD back2, 0 -> [SP+0]
+        JIT 1, double
+back2:  OUT [SP+1]
+        HLT
+double: SP 2
+        MUL [SP-1], 2 -> [SP-1]
+        LTN [SP-1], 100 -> [SP+0]
+        JIF [SP+0], big
+        SP -2
+        JIT 1, [SP+0]
+big:    SP -2
+        JIT 1, [SP+0]
+x:      DATA 0
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_cfg() {
+        let program = calls();
+        let cfg = Cfg::new(&program);
+        let entries: Vec<_> = cfg.functions().iter().map(|f| f.entry).collect();
+        let double = entries[1];
+        assert_eq!(entries, vec![0, double]);
+        assert_eq!(cfg.functions()[1].frame, 2);
+        assert_eq!(cfg.functions()[1].blocks.len(), 3);
+        assert_eq!(cfg.functions()[0].blocks.len(), 3);
+        let exits: Vec<_> = cfg.blocks().map(|b| b.exit).collect();
+        assert_eq!(
+            exits
+                .iter()
+                .filter(|e| matches!(e, Exit::Call { target, .. } if *target == double))
+                .count(),
+            2
+        );
+        assert_eq!(
+            exits
+                .iter()
+                .filter(|e| **e == Exit::Return { next: None })
+                .count(),
+            2
+        );
+        assert_eq!(cfg.data(), vec![(program.len() - 1, program.len())]);
+        assert!(cfg.is_code(0) && cfg.is_code(1) && !cfg.is_code(program.len() - 1));
+
+        let c = cfg.to_pseudo_c();
+        assert!(c.contains(&format!("f_{:04}();", double)));
+        assert!(c.contains("void main() { // frame 100"));
+        assert!(c.contains("sp[-1] = sp[-1] * 2;"));
+        assert!(c.contains("sp[1] = mem["));
+        assert!(c.contains("if (!sp[0]) goto"));
+        assert!(c.contains("return; /* to sp[0] */"));
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains(&format!("-> b{} [style=dashed];", double)));
+        assert!(dot.contains("[label=\"T\"]"));
+    }
+
+    #[test]
+    fn test_compiled() {
+        // Division is a subroutine returning through a memory cell
+        let program = compile(
+            &parse(&tokenize(
+                "int main() { int a = 0; while (a < 10) { a = a + 1; } return a / 3; }",
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+        let cfg = Cfg::new(&program);
+        assert_eq!(cfg.functions().len(), 2);
+        assert!(cfg
+            .blocks()
+            .any(|b| matches!(b.exit, Exit::Return { next: None })));
+        assert!(cfg.blocks().any(|b| matches!(b.exit, Exit::Branch { .. })));
+        // The cells after the code are data
+        assert_eq!(cfg.data().last().unwrap().1, program.len());
+        assert!(cfg.blocks().all(|b| b.exit != Exit::Invalid));
+    }
+}
@@ -0,0 +1,215 @@
+//! Talking to ASCII programs, like the text adventure and the droids
+//!
+//! AsciiMachine sends lines of text as inputs and collects the outputs as
+//! text, keeping outputs outside ASCII (usually the answer at the end) as
+//! numbers. Everything sent and received is kept in a transcript.
+
+use crate::{Machine, State};
+use rustyline::Editor;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AsciiOutput {
+    pub text: String,
+    // Outputs that aren't ASCII, in order
+    pub values: Vec<i128>,
+}
+
+impl AsciiOutput {
+    fn push(&mut self, value: i128) {
+        if (0..128).contains(&value) {
+            self.text.push(value as u8 as char);
+        } else {
+            self.values.push(value);
+        }
+    }
+}
+
+// Splits outputs into text and other values
+pub fn to_ascii(outputs: &[i128]) -> AsciiOutput {
+    let mut out = AsciiOutput::default();
+    for v in outputs {
+        out.push(*v);
+    }
+    out
+}
+
+pub struct AsciiMachine {
+    machine: Machine,
+    state: State,
+    transcript: String,
+}
+
+impl AsciiMachine {
+    pub fn new(program: &[i128]) -> AsciiMachine {
+        AsciiMachine::from_machine(Machine::new(program))
+    }
+
+    pub fn from_machine(machine: Machine) -> AsciiMachine {
+        AsciiMachine {
+            machine,
+            state: State::Running,
+            transcript: String::new(),
+        }
+    }
+
+    pub fn machine(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    // Input when the machine waits for it, Output when a prompt was found
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_halted(&self) -> bool {
+        matches!(self.state, State::Halted | State::Fault(_))
+    }
+
+    // Everything read and sent so far
+    pub fn transcript(&self) -> &str {
+        &self.transcript
+    }
+
+    pub fn send(&mut self, text: &str) {
+        for c in text.chars() {
+            self.machine.add_input(c as i128);
+        }
+        self.transcript.push_str(text);
+    }
+
+    pub fn send_line(&mut self, line: &str) {
+        self.send(line);
+        self.send("\n");
+    }
+
+    fn read(&mut self, prompt: Option<&str>) -> AsciiOutput {
+        let mut out = AsciiOutput::default();
+        loop {
+            self.state = self.machine.step();
+            match self.state {
+                State::Running => {}
+                State::Output => {
+                    for v in self.machine.outputs() {
+                        out.push(v);
+                    }
+                    if prompt.is_some_and(|p| out.text.ends_with(p)) {
+                        break;
+                    }
+                }
+                State::Input | State::Halted | State::Fault(_) => break,
+            }
+        }
+        self.transcript.push_str(&out.text);
+        out
+    }
+
+    // Runs until the machine waits for input or halts
+    pub fn read_text(&mut self) -> AsciiOutput {
+        self.read(None)
+    }
+
+    // Runs until the text ends with prompt, or the machine waits for input
+    // or halts
+    pub fn read_until_prompt(&mut self, prompt: &str) -> AsciiOutput {
+        self.read(Some(prompt))
+    }
+
+    // Sends each command when the machine waits for input, until they are
+    // all sent or it halts. Returns the session as it would look in a
+    // terminal, with the commands echoed.
+    pub fn run_script<S: AsRef<str>>(&mut self, commands: &[S]) -> AsciiOutput {
+        let start = self.transcript.len();
+        let mut values = self.read_text().values;
+        for command in commands {
+            if self.state != State::Input {
+                break;
+            }
+            self.send_line(command.as_ref());
+            values.extend(self.read_text().values);
+        }
+        AsciiOutput {
+            text: self.transcript[start..].to_string(),
+            values,
+        }
+    }
+
+    // Reads commands from the terminal until the machine halts or input
+    // ends, returns the values that weren't ASCII
+    pub fn interact(&mut self) -> Vec<i128> {
+        let mut rl = Editor::<()>::new();
+        let mut values = vec![];
+        loop {
+            let out = self.read_text();
+            print!("{}", out.text);
+            for v in &out.values {
+                println!("value: {}", v);
+            }
+            values.extend(out.values);
+            if self.state != State::Input {
+                break;
+            }
+            match rl.readline(">> ") {
+                Ok(s) => self.send_line(s.trim()),
+                Err(_) => break,
+            }
+        }
+        values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // Echoes two lines after a prompt, then outputs two numbers
+    fn echo() -> Vec<i128> {
+        assemble(
+            "\
+prompt: OUT 62
+        OUT 32
+loop:   INP [c]
+        EQL [c], 10 -> [t]
+        JIT [t], eol
+        OUT [c]
+        JIT 1, loop
+eol:    OUT 10
+        ADD [n], -1 -> [n]
+        JIT [n], prompt
+        OUT 1000
+        OUT 2000
+        HLT
+n:      DATA 2
+c:      DATA 0
+t:      DATA 0
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_and_send() {
+        let mut m = AsciiMachine::new(&echo());
+        assert_eq!(m.read_until_prompt("> ").text, "> ");
+        assert_eq!(m.state(), State::Output);
+        assert_eq!(m.read_text(), AsciiOutput::default());
+        assert_eq!(m.state(), State::Input);
+        m.send_line("hi");
+        assert_eq!(m.read_text().text, "hi\n> ");
+        m.send_line("yo");
+        let out = m.read_text();
+        assert_eq!(out.text, "yo\n");
+        assert_eq!(out.values, vec![1000, 2000]);
+        assert!(m.is_halted());
+        assert_eq!(to_ascii(&[104, 105, -1]).values, vec![-1]);
+    }
+
+    #[test]
+    fn test_script() {
+        let mut m = AsciiMachine::new(&echo());
+        let out = m.run_script(&["a", "b", "never sent"]);
+        assert_eq!(out.text, "> a\na\n> b\nb\n");
+        assert_eq!(out.values, vec![1000, 2000]);
+        assert_eq!(m.transcript(), out.text);
+    }
+}
@@ -0,0 +1,495 @@
+//! Intcode assembly language
+//!
+//! One instruction per line, using the names from Op::definition, with the
+//! values read before `->` and the ones written after it:
+//!
+//! ```text
+//! ; comments start with a semicolon
+//!         SP stack                ; the address of a label as a value
+//! loop:   ADD [x], 5 -> [SP+0]    ; position and relative mode
+//!         JIF [SP+0], loop
+//!         OUT [x+1]
+//!         HLT
+//! x:      DATA 7, -2, loop
+//! stack:
+//! ```
+//!
+//! disassemble writes the same syntax, with labels for jump targets and
+//! addresses referenced inside the program, so assemble gives back the
+//! exact same memory.
+
+use crate::machine::Op;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+const OPCODES: [i128; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 99];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AsmError {
+    SyntaxError(String, usize),
+    UndefinedLabel(String, usize),
+    DuplicateLabel(String, usize),
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::SyntaxError(s, line) => write!(f, "SyntaxError: {} --> line {}", s, line),
+            AsmError::UndefinedLabel(s, line) => {
+                write!(f, "Undefined label '{}' --> line {}", s, line)
+            }
+            AsmError::DuplicateLabel(s, line) => {
+                write!(f, "Duplicate label '{}' --> line {}", s, line)
+            }
+        }
+    }
+}
+
+// A number, or a label plus an offset
+#[derive(Debug, Clone)]
+struct Value {
+    label: Option<String>,
+    offset: i128,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Position(Value),
+    Immediate(Value),
+    Relative(i128),
+}
+
+enum Item {
+    Instruction(Op, Vec<Operand>),
+    Data(Vec<Value>),
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_number(s: &str, line: usize) -> Result<i128, AsmError> {
+    s.trim()
+        .replace(' ', "")
+        .parse::<i128>()
+        .map_err(|_| AsmError::SyntaxError(format!("Expected number, got '{}'", s.trim()), line))
+}
+
+fn parse_value(s: &str, line: usize) -> Result<Value, AsmError> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(AsmError::SyntaxError("Expected value".into(), line));
+    }
+    // label, label+n or label-n
+    let split = s
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| matches!(c, '+' | '-'))
+        .map_or(s.len(), |(i, _)| i);
+    let (name, rest) = s.split_at(split);
+    let name = name.trim();
+    if is_label(name) {
+        let offset = if rest.is_empty() {
+            0
+        } else {
+            parse_number(rest, line)?
+        };
+        Ok(Value {
+            label: Some(name.to_string()),
+            offset,
+        })
+    } else {
+        Ok(Value {
+            label: None,
+            offset: parse_number(s, line)?,
+        })
+    }
+}
+
+fn parse_operand(s: &str, line: usize) -> Result<Operand, AsmError> {
+    let s = s.trim();
+    if let Some(inner) = s.strip_prefix('[') {
+        let inner = inner
+            .strip_suffix(']')
+            .ok_or_else(|| AsmError::SyntaxError("Expected ]".into(), line))?
+            .trim();
+        if let Some(offset) = inner.strip_prefix("SP") {
+            let offset = offset.trim();
+            if offset.is_empty() {
+                Ok(Operand::Relative(0))
+            } else if offset.starts_with(['+', '-']) {
+                Ok(Operand::Relative(parse_number(offset, line)?))
+            } else {
+                Ok(Operand::Position(parse_value(inner, line)?))
+            }
+        } else {
+            Ok(Operand::Position(parse_value(inner, line)?))
+        }
+    } else {
+        Ok(Operand::Immediate(parse_value(s, line)?))
+    }
+}
+
+// Splits on commas, allowing a trailing one like in older listings
+fn split_list(s: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = s.split(',').map(|p| p.trim()).collect();
+    if parts.last() == Some(&"") {
+        parts.pop();
+    }
+    parts
+}
+
+fn op_from_name(name: &str) -> Option<Op> {
+    let name = name.to_ascii_uppercase();
+    OPCODES
+        .iter()
+        .filter_map(|code| Op::from_i128(*code))
+        .find(|op| op.definition().0 == name)
+}
+
+fn parse_item(s: &str, line: usize) -> Result<Item, AsmError> {
+    let (name, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+    if name.eq_ignore_ascii_case("DATA") {
+        let values = split_list(rest)
+            .iter()
+            .map(|v| parse_value(v, line))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(Item::Data(values));
+    }
+    let op = op_from_name(name)
+        .ok_or_else(|| AsmError::SyntaxError(format!("Unknown instruction '{}'", name), line))?;
+    let (_, reads, writes) = op.definition();
+    let (read_part, write_part) = rest.split_once("->").unwrap_or((rest, ""));
+    let mut operands = vec![];
+    for part in split_list(read_part)
+        .into_iter()
+        .chain(split_list(write_part))
+    {
+        operands.push(parse_operand(part, line)?);
+    }
+    let expected = if rest.contains("->") || writes == 0 {
+        split_list(read_part).len() == reads && split_list(write_part).len() == writes
+    } else {
+        operands.len() == reads + writes
+    };
+    if !expected {
+        return Err(AsmError::SyntaxError(
+            format!(
+                "{} takes {} -> {} operands",
+                op.definition().0,
+                reads,
+                writes
+            ),
+            line,
+        ));
+    }
+    for operand in &operands[reads..] {
+        if let Operand::Immediate(_) = operand {
+            return Err(AsmError::SyntaxError(
+                "Can't write to an immediate value".into(),
+                line,
+            ));
+        }
+    }
+    Ok(Item::Instruction(op, operands))
+}
+
+fn resolve(value: &Value, labels: &HashMap<String, usize>, line: usize) -> Result<i128, AsmError> {
+    match &value.label {
+        Some(name) => labels
+            .get(name)
+            .map(|address| *address as i128 + value.offset)
+            .ok_or_else(|| AsmError::UndefinedLabel(name.clone(), line)),
+        None => Ok(value.offset),
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<i128>, AsmError> {
+    // First pass: parse and find the label addresses
+    let mut items = vec![];
+    let mut labels = HashMap::new();
+    let mut address = 0;
+    for (line_ix, text) in source.lines().enumerate() {
+        let line = line_ix + 1;
+        let mut s = text.split(';').next().unwrap().trim();
+        while let Some((label, rest)) = s.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(AsmError::SyntaxError(
+                    format!("Invalid label '{}'", label),
+                    line,
+                ));
+            }
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(AsmError::DuplicateLabel(label.to_string(), line));
+            }
+            s = rest.trim();
+        }
+        if s.is_empty() {
+            continue;
+        }
+        let item = parse_item(s, line)?;
+        address += match &item {
+            Item::Instruction(_, operands) => 1 + operands.len(),
+            Item::Data(values) => values.len(),
+        };
+        items.push((item, line));
+    }
+    // Second pass: encode
+    let mut memory = Vec::with_capacity(address);
+    for (item, line) in items {
+        match item {
+            Item::Instruction(op, operands) => {
+                let mut value = op.code();
+                let mut factor = 100;
+                for operand in &operands {
+                    value += factor
+                        * match operand {
+                            Operand::Position(_) => 0,
+                            Operand::Immediate(_) => 1,
+                            Operand::Relative(_) => 2,
+                        };
+                    factor *= 10;
+                }
+                memory.push(value);
+                for operand in &operands {
+                    memory.push(match operand {
+                        Operand::Position(v) | Operand::Immediate(v) => resolve(v, &labels, line)?,
+                        Operand::Relative(offset) => *offset,
+                    });
+                }
+            }
+            Item::Data(values) => {
+                for v in &values {
+                    memory.push(resolve(v, &labels, line)?);
+                }
+            }
+        }
+    }
+    Ok(memory)
+}
+
+enum Line {
+    // The op, then (mode, value) of each operand
+    Instruction(Op, Vec<(i128, i128)>),
+    Data(Vec<i128>),
+}
+
+impl Line {
+    fn len(&self) -> usize {
+        match self {
+            Line::Instruction(_, args) => 1 + args.len(),
+            Line::Data(values) => values.len(),
+        }
+    }
+}
+
+// Only what assembles back to the same values is an instruction
+fn decode(memory: &[i128], address: usize) -> Option<Line> {
+    let value = memory[address];
+    if value < 0 {
+        return None;
+    }
+    let op = Op::from_i128(value)?;
+    let (_, reads, writes) = op.definition();
+    let n = reads + writes;
+    if address + n >= memory.len() || value / (100 * 10i128.pow(n as u32)) != 0 {
+        return None;
+    }
+    let mut args = vec![];
+    let mut div = 100;
+    for i in 0..n {
+        let mode = (value / div) % 10;
+        if mode > 2 || (i >= reads && mode == 1) {
+            return None;
+        }
+        args.push((mode, memory[address + 1 + i]));
+        div *= 10;
+    }
+    Some(Line::Instruction(op, args))
+}
+
+// Data is gathered into lines of at most this many values
+const DATA_PER_LINE: usize = 8;
+
+pub fn disassemble(memory: &[i128]) -> String {
+    let mut lines: BTreeMap<usize, Line> = BTreeMap::new();
+    let mut address = 0;
+    let mut data_start = None;
+    while address < memory.len() {
+        if let Some(line) = decode(memory, address) {
+            data_start = None;
+            address += line.len();
+            lines.insert(address - line.len(), line);
+        } else {
+            match data_start.and_then(|start| lines.get_mut(&start)) {
+                Some(Line::Data(values)) if values.len() < DATA_PER_LINE => {
+                    values.push(memory[address])
+                }
+                _ => {
+                    data_start = Some(address);
+                    lines.insert(address, Line::Data(vec![memory[address]]));
+                }
+            }
+            address += 1;
+        }
+    }
+    // Jump targets and positions inside the program get labels, at the start
+    // of the line containing them
+    let line_of = |address: usize| lines.range(..=address).next_back().map(|(a, _)| *a);
+    let mut targets = BTreeSet::new();
+    for line in lines.values() {
+        if let Line::Instruction(op, args) = line {
+            for (i, (mode, value)) in args.iter().enumerate() {
+                let is_jump = matches!(op, Op::JIT | Op::JIF) && i == 1 && *mode == 1;
+                if (*mode == 0 || is_jump) && *value >= 0 && (*value as usize) < memory.len() {
+                    targets.insert(*value as usize);
+                }
+            }
+        }
+    }
+    let labels: BTreeSet<usize> = targets.iter().filter_map(|t| line_of(*t)).collect();
+    let name = |value: i128| {
+        let start = line_of(value as usize).unwrap();
+        let offset = value as usize - start;
+        if offset == 0 {
+            format!("L{:04}", start)
+        } else {
+            format!("L{:04}+{}", start, offset)
+        }
+    };
+    let mut s = String::new();
+    for (address, line) in &lines {
+        let label = if labels.contains(address) {
+            format!("L{:04}:", address)
+        } else {
+            String::new()
+        };
+        s += &format!("{:8}", label);
+        match line {
+            Line::Instruction(op, args) => {
+                let (op_name, reads, _) = op.definition();
+                s += op_name;
+                for (i, (mode, value)) in args.iter().enumerate() {
+                    s += match i {
+                        0 => " ",
+                        _ if i == reads => " -> ",
+                        _ => ", ",
+                    };
+                    let is_target = targets.contains(&(*value as usize)) && *value >= 0;
+                    let is_jump = matches!(op, Op::JIT | Op::JIF) && i == 1;
+                    s += &match mode {
+                        0 if is_target => format!("[{}]", name(*value)),
+                        0 => format!("[{}]", value),
+                        1 if is_jump && is_target => name(*value),
+                        1 => format!("{}", value),
+                        _ => format!("[SP{:+}]", value),
+                    };
+                }
+            }
+            Line::Data(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                s += &format!("DATA {}", values.join(", "));
+            }
+        }
+        s.push('\n');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{compile, parse, tokenize, Machine};
+
+    #[test]
+    fn test_assemble() {
+        let source = "\
+; counts down from the input
+        SP stack
+        INP [SP+0]
+loop:   OUT [SP]
+        ADD [SP+0], -1 -> [SP+0]
+        JIT [SP+0], loop
+        OUT [x+1]
+        HLT
+x:      DATA 7, -2, loop
+stack:
+";
+        let memory = assemble(source).unwrap();
+        assert_eq!(&memory[..7], &[109, 19, 203, 0, 204, 0, 21201]);
+        assert_eq!(&memory[16..], &[7, -2, 4]);
+        let mut m = Machine::with_input(&memory, &[3]);
+        m.run();
+        assert_eq!(m.outputs(), vec![3, 2, 1, -2]);
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("ADD 1, 2 -> 3"),
+            Err(AsmError::SyntaxError(
+                "Can't write to an immediate value".into(),
+                1
+            ))
+        );
+        assert_eq!(
+            assemble("\nJIT 1, nowhere"),
+            Err(AsmError::UndefinedLabel("nowhere".into(), 2))
+        );
+        assert_eq!(
+            assemble("a: HLT\na: HLT"),
+            Err(AsmError::DuplicateLabel("a".into(), 2))
+        );
+        assert!(matches!(
+            assemble("MUL 1, 2"),
+            Err(AsmError::SyntaxError(_, 1))
+        ));
+        assert!(matches!(assemble("FOO"), Err(AsmError::SyntaxError(_, 1))));
+        assert!(matches!(
+            assemble("DATA é"),
+            Err(AsmError::SyntaxError(_, 1))
+        ));
+        assert!(matches!(
+            assemble("DATA é+1"),
+            Err(AsmError::SyntaxError(_, 1))
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let programs = vec![
+            vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50],
+            vec![
+                3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36,
+                98, 0, 0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000,
+                1, 20, 4, 20, 1105, 1, 46, 98, 99,
+            ],
+            vec![
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ],
+            vec![1, 2, 3, 104, 42, 10005, 99, -1, 1101, 1, 2],
+            compile(&parse(&tokenize("int main() { int a = 7; return a / 2; }")).unwrap()).unwrap(),
+        ];
+        for program in programs {
+            let listing = disassemble(&program);
+            assert_eq!(assemble(&listing).unwrap(), program, "{}", listing);
+        }
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let listing = disassemble(&[1, 9, 10, 3, 1105, 1, 0, 99, 21101, 30, 40, 50]);
+        assert_eq!(
+            listing,
+            "\
+L0000:  ADD [L0008+1], [L0008+2] -> [L0000+3]
+        JIT 1, L0000
+        HLT
+L0008:  ADD 30, 40 -> [SP+50]
+"
+        );
+    }
+}
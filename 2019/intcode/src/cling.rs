@@ -1297,7 +1297,7 @@ fn parse_statement(tokens: &[TokenWithLocation]) -> Result<(Statement, usize), P
                             .next()
                             .ok_or(ParseError::UnexpectedEOF)?
                         {
-                            return Ok((Statement::While(exp, Box::new(statement)), offset));
+                            return Ok((Statement::Do(Box::new(statement), exp), offset + 1));
                         }
                     }
                 }
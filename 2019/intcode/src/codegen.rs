@@ -0,0 +1,677 @@
+//! Code generation from the cling AST to Intcode
+//!
+//! The relative base is set to the start of a stack after the code with
+//! Op::SP, and every local and temporary value is a slot relative to it.
+//! Intcode has no division, so / and % call a subroutine doing long division
+//! which is only included when used. `return` outputs the value and halts,
+//! falling off the end of main returns 0.
+
+use crate::cling::*;
+use crate::machine::Op;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CompileError {
+    UndeclaredVariable(String),
+    Redeclaration(String),
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::UndeclaredVariable(name) => write!(f, "Undeclared variable '{}'", name),
+            CompileError::Redeclaration(name) => write!(f, "Redeclaration of '{}'", name),
+            CompileError::BreakOutsideLoop => write!(f, "'break' outside of loop"),
+            CompileError::ContinueOutsideLoop => write!(f, "'continue' outside of loop"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Operand {
+    Immediate(i128),
+    // Stack slot, relative to SP
+    Slot(i128),
+    // The address of a label as an immediate value
+    Label(usize),
+    // The memory at a label
+    Memory(usize),
+}
+
+impl Operand {
+    fn mode(&self) -> i128 {
+        match self {
+            Operand::Memory(_) => 0,
+            Operand::Immediate(_) | Operand::Label(_) => 1,
+            Operand::Slot(_) => 2,
+        }
+    }
+}
+
+struct Loop {
+    continue_label: usize,
+    break_label: usize,
+}
+
+// Labels of the division subroutine and the cells it uses to pass values
+#[derive(Copy, Clone)]
+struct DivMod {
+    entry: usize,
+    a: usize,
+    b: usize,
+    quotient: usize,
+    remainder: usize,
+    ret: usize,
+}
+
+struct CodeGen {
+    code: Vec<i128>,
+    labels: Vec<Option<usize>>,
+    // Code positions to be patched with label addresses
+    fixups: Vec<(usize, usize)>,
+    // Labels of single memory cells placed after the code
+    cells: Vec<usize>,
+    scopes: Vec<HashMap<String, i128>>,
+    loops: Vec<Loop>,
+    // First free stack slot
+    depth: i128,
+    divmod: Option<DivMod>,
+}
+
+impl CodeGen {
+    fn new() -> CodeGen {
+        CodeGen {
+            code: vec![],
+            labels: vec![],
+            fixups: vec![],
+            cells: vec![],
+            scopes: vec![],
+            loops: vec![],
+            depth: 0,
+            divmod: None,
+        }
+    }
+
+    fn label(&mut self) -> usize {
+        self.labels.push(None);
+        self.labels.len() - 1
+    }
+
+    fn place(&mut self, label: usize) {
+        self.labels[label] = Some(self.code.len());
+    }
+
+    fn cell(&mut self) -> usize {
+        let label = self.label();
+        self.cells.push(label);
+        label
+    }
+
+    fn temp(&mut self) -> i128 {
+        self.depth += 1;
+        self.depth - 1
+    }
+
+    fn emit(&mut self, op: Op, args: &[Operand]) {
+        let (_, reads, writes) = op.definition();
+        assert_eq!(args.len(), reads + writes);
+        let mut value = op.code();
+        let mut factor = 100;
+        for arg in args {
+            value += arg.mode() * factor;
+            factor *= 10;
+        }
+        self.code.push(value);
+        for arg in args {
+            match arg {
+                Operand::Immediate(v) | Operand::Slot(v) => self.code.push(*v),
+                Operand::Label(label) | Operand::Memory(label) => {
+                    self.fixups.push((self.code.len(), *label));
+                    self.code.push(0);
+                }
+            }
+        }
+    }
+
+    fn jump(&mut self, label: usize) {
+        self.emit(Op::JIT, &[Operand::Immediate(1), Operand::Label(label)]);
+    }
+
+    fn copy(&mut self, from: Operand, to: Operand) {
+        if from != to {
+            self.emit(Op::ADD, &[from, Operand::Immediate(0), to]);
+        }
+    }
+
+    // Turns any non-zero value into 1
+    fn normalize(&mut self, from: Operand, to: Operand) {
+        self.emit(Op::EQL, &[from, Operand::Immediate(0), to]);
+        self.emit(Op::EQL, &[to, Operand::Immediate(0), to]);
+    }
+
+    fn lookup(&self, name: &str) -> Result<i128, CompileError> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+            .ok_or_else(|| CompileError::UndeclaredVariable(name.into()))
+    }
+
+    // Constants and variables are used as they are, anything else is
+    // evaluated into a temporary slot
+    fn operand(&mut self, e: &Expression) -> Result<Operand, CompileError> {
+        match e {
+            Expression::Constant(v) => Ok(Operand::Immediate(*v)),
+            Expression::VariableReference(name) => Ok(Operand::Slot(self.lookup(name)?)),
+            Expression::Assignment(name, e) => {
+                let slot = self.lookup(name)?;
+                self.expression(e, slot)?;
+                Ok(Operand::Slot(slot))
+            }
+            _ => {
+                let t = self.temp();
+                self.expression(e, t)?;
+                Ok(Operand::Slot(t))
+            }
+        }
+    }
+
+    // Evaluates e into the stack slot dst
+    fn expression(&mut self, e: &Expression, dst: i128) -> Result<(), CompileError> {
+        let depth = self.depth;
+        let d = Operand::Slot(dst);
+        match e {
+            Expression::Constant(_) | Expression::VariableReference(_) => {
+                let v = self.operand(e)?;
+                self.copy(v, d);
+            }
+            Expression::Assignment(name, e) => {
+                let slot = self.lookup(name)?;
+                self.expression(e, slot)?;
+                self.copy(Operand::Slot(slot), d);
+            }
+            Expression::UnaryOperator(op, e) => {
+                let v = self.operand(e)?;
+                match op {
+                    UnaryOperator::Negation => {
+                        self.emit(Op::MUL, &[v, Operand::Immediate(-1), d]);
+                    }
+                    UnaryOperator::LogicalNegation => {
+                        self.emit(Op::EQL, &[v, Operand::Immediate(0), d]);
+                    }
+                }
+            }
+            // dst may be a variable read by the right hand side, so it is
+            // only written once both sides are done
+            Expression::BinaryOperator(BinaryOperator::And, l, r) => {
+                let is_false = self.label();
+                let end = self.label();
+                let a = self.operand(l)?;
+                self.emit(Op::JIF, &[a, Operand::Label(is_false)]);
+                let b = self.operand(r)?;
+                self.normalize(b, d);
+                self.jump(end);
+                self.place(is_false);
+                self.copy(Operand::Immediate(0), d);
+                self.place(end);
+            }
+            Expression::BinaryOperator(BinaryOperator::Or, l, r) => {
+                let is_true = self.label();
+                let end = self.label();
+                let a = self.operand(l)?;
+                self.emit(Op::JIT, &[a, Operand::Label(is_true)]);
+                let b = self.operand(r)?;
+                self.normalize(b, d);
+                self.jump(end);
+                self.place(is_true);
+                self.copy(Operand::Immediate(1), d);
+                self.place(end);
+            }
+            Expression::BinaryOperator(op, l, r) => {
+                let a = self.operand(l)?;
+                let b = self.operand(r)?;
+                self.binary_operator(op, a, b, d);
+            }
+            Expression::Conditional(c, x, y) => {
+                let otherwise = self.label();
+                let end = self.label();
+                let v = self.operand(c)?;
+                self.emit(Op::JIF, &[v, Operand::Label(otherwise)]);
+                self.expression(x, dst)?;
+                self.jump(end);
+                self.place(otherwise);
+                self.expression(y, dst)?;
+                self.place(end);
+            }
+        }
+        self.depth = depth;
+        Ok(())
+    }
+
+    fn binary_operator(&mut self, op: &BinaryOperator, a: Operand, b: Operand, d: Operand) {
+        match op {
+            BinaryOperator::Addition => self.emit(Op::ADD, &[a, b, d]),
+            BinaryOperator::Subtraction => {
+                if let Operand::Immediate(v) = b {
+                    self.emit(Op::ADD, &[a, Operand::Immediate(-v), d]);
+                } else {
+                    let t = Operand::Slot(self.temp());
+                    self.emit(Op::MUL, &[b, Operand::Immediate(-1), t]);
+                    self.emit(Op::ADD, &[a, t, d]);
+                }
+            }
+            BinaryOperator::Multiplication => self.emit(Op::MUL, &[a, b, d]),
+            BinaryOperator::Division | BinaryOperator::Modulo => {
+                let dm = self.divmod();
+                let ret = self.label();
+                self.copy(a, Operand::Memory(dm.a));
+                self.copy(b, Operand::Memory(dm.b));
+                self.copy(Operand::Label(ret), Operand::Memory(dm.ret));
+                self.jump(dm.entry);
+                self.place(ret);
+                let result = if *op == BinaryOperator::Division {
+                    dm.quotient
+                } else {
+                    dm.remainder
+                };
+                self.copy(Operand::Memory(result), d);
+            }
+            BinaryOperator::Equal => self.emit(Op::EQL, &[a, b, d]),
+            BinaryOperator::NotEqual => {
+                self.emit(Op::EQL, &[a, b, d]);
+                self.emit(Op::EQL, &[d, Operand::Immediate(0), d]);
+            }
+            BinaryOperator::LessThan => self.emit(Op::LTN, &[a, b, d]),
+            BinaryOperator::GreaterThan => self.emit(Op::LTN, &[b, a, d]),
+            BinaryOperator::LessThanOrEqual => {
+                self.emit(Op::LTN, &[b, a, d]);
+                self.emit(Op::EQL, &[d, Operand::Immediate(0), d]);
+            }
+            BinaryOperator::GreaterThanOrEqual => {
+                self.emit(Op::LTN, &[a, b, d]);
+                self.emit(Op::EQL, &[d, Operand::Immediate(0), d]);
+            }
+            BinaryOperator::And | BinaryOperator::Or => unreachable!("short-circuit"),
+        }
+    }
+
+    fn divmod(&mut self) -> DivMod {
+        if let Some(dm) = self.divmod {
+            return dm;
+        }
+        let dm = DivMod {
+            entry: self.label(),
+            a: self.cell(),
+            b: self.cell(),
+            quotient: self.cell(),
+            remainder: self.cell(),
+            ret: self.cell(),
+        };
+        self.divmod = Some(dm);
+        dm
+    }
+
+    // Truncating division like C, with the sign of the remainder following
+    // a. Doubles b to subtract as much as possible each round, so it takes
+    // O(log^2) steps. Division by zero halts.
+    fn emit_divmod(&mut self, dm: DivMod) {
+        use Operand::{Immediate, Label, Memory};
+        let (neg_a, neg_b, d, m, t) = (
+            self.cell(),
+            self.cell(),
+            self.cell(),
+            self.cell(),
+            self.cell(),
+        );
+        let (a, b, q, r) = (
+            Memory(dm.a),
+            Memory(dm.b),
+            Memory(dm.quotient),
+            Memory(dm.remainder),
+        );
+        let (neg_a, neg_b, d, m, t) = (
+            Memory(neg_a),
+            Memory(neg_b),
+            Memory(d),
+            Memory(m),
+            Memory(t),
+        );
+        let labels: Vec<usize> = (0..7).map(|_| self.label()).collect();
+        let [non_zero, a_pos, b_pos, outer, inner, subtract, done] = labels[..] else {
+            unreachable!()
+        };
+        let (q_pos, r_pos) = (self.label(), self.label());
+
+        self.place(dm.entry);
+        self.emit(Op::JIT, &[b, Label(non_zero)]);
+        self.emit(Op::HLT, &[]);
+        self.place(non_zero);
+        self.emit(Op::LTN, &[a, Immediate(0), neg_a]);
+        self.emit(Op::JIF, &[neg_a, Label(a_pos)]);
+        self.emit(Op::MUL, &[a, Immediate(-1), a]);
+        self.place(a_pos);
+        self.emit(Op::LTN, &[b, Immediate(0), neg_b]);
+        self.emit(Op::JIF, &[neg_b, Label(b_pos)]);
+        self.emit(Op::MUL, &[b, Immediate(-1), b]);
+        self.place(b_pos);
+        self.copy(Immediate(0), q);
+        self.copy(a, r);
+
+        self.place(outer);
+        self.emit(Op::LTN, &[r, b, t]);
+        self.emit(Op::JIT, &[t, Label(done)]);
+        self.copy(b, d);
+        self.copy(Immediate(1), m);
+        self.place(inner);
+        self.emit(Op::ADD, &[d, d, t]);
+        self.emit(Op::LTN, &[r, t, t]);
+        self.emit(Op::JIT, &[t, Label(subtract)]);
+        self.emit(Op::ADD, &[d, d, d]);
+        self.emit(Op::ADD, &[m, m, m]);
+        self.jump(inner);
+        self.place(subtract);
+        self.emit(Op::MUL, &[d, Immediate(-1), t]);
+        self.emit(Op::ADD, &[r, t, r]);
+        self.emit(Op::ADD, &[q, m, q]);
+        self.jump(outer);
+
+        self.place(done);
+        self.emit(Op::EQL, &[neg_a, neg_b, t]);
+        self.emit(Op::JIT, &[t, Label(q_pos)]);
+        self.emit(Op::MUL, &[q, Immediate(-1), q]);
+        self.place(q_pos);
+        self.emit(Op::JIF, &[neg_a, Label(r_pos)]);
+        self.emit(Op::MUL, &[r, Immediate(-1), r]);
+        self.place(r_pos);
+        self.emit(Op::JIT, &[Immediate(1), Memory(dm.ret)]);
+    }
+
+    fn jump_if_false(&mut self, c: &Expression, label: usize) -> Result<(), CompileError> {
+        let depth = self.depth;
+        let v = self.operand(c)?;
+        self.emit(Op::JIF, &[v, Operand::Label(label)]);
+        self.depth = depth;
+        Ok(())
+    }
+
+    fn declaration(&mut self, d: &Declaration) -> Result<(), CompileError> {
+        let Declaration::Variable(name, init) = d;
+        if self.scopes.last().unwrap().contains_key(name) {
+            return Err(CompileError::Redeclaration(name.clone()));
+        }
+        let slot = self.temp();
+        match init {
+            Some(e) => self.expression(e, slot)?,
+            None => self.copy(Operand::Immediate(0), Operand::Slot(slot)),
+        }
+        self.scopes.last_mut().unwrap().insert(name.clone(), slot);
+        Ok(())
+    }
+
+    fn block(&mut self, items: &[BlockItem]) -> Result<(), CompileError> {
+        let depth = self.depth;
+        self.scopes.push(HashMap::new());
+        for item in items {
+            match item {
+                BlockItem::Statement(s) => self.statement(s)?,
+                BlockItem::Declaration(d) => self.declaration(d)?,
+            }
+        }
+        self.scopes.pop();
+        self.depth = depth;
+        Ok(())
+    }
+
+    fn loop_body(&mut self, body: &Statement, cont: usize, brk: usize) -> Result<(), CompileError> {
+        self.loops.push(Loop {
+            continue_label: cont,
+            break_label: brk,
+        });
+        self.statement(body)?;
+        self.loops.pop();
+        Ok(())
+    }
+
+    fn for_loop(
+        &mut self,
+        cond: &Expression,
+        post: &Option<Expression>,
+        body: &Statement,
+    ) -> Result<(), CompileError> {
+        let start = self.label();
+        let cont = self.label();
+        let end = self.label();
+        self.place(start);
+        self.jump_if_false(cond, end)?;
+        self.loop_body(body, cont, end)?;
+        self.place(cont);
+        if let Some(post) = post {
+            self.operand(post)?;
+        }
+        self.jump(start);
+        self.place(end);
+        Ok(())
+    }
+
+    fn statement(&mut self, s: &Statement) -> Result<(), CompileError> {
+        let depth = self.depth;
+        match s {
+            Statement::Return(e) => {
+                let v = self.operand(e)?;
+                self.emit(Op::OUT, &[v]);
+                self.emit(Op::HLT, &[]);
+            }
+            Statement::Expression(Some(e)) => {
+                self.operand(e)?;
+            }
+            Statement::Expression(None) => {}
+            Statement::Conditional(c, then, otherwise) => {
+                let else_label = self.label();
+                self.jump_if_false(c, else_label)?;
+                self.statement(then)?;
+                if let Some(otherwise) = otherwise {
+                    let end = self.label();
+                    self.jump(end);
+                    self.place(else_label);
+                    self.statement(otherwise)?;
+                    self.place(end);
+                } else {
+                    self.place(else_label);
+                }
+            }
+            Statement::Compound(items) => self.block(items)?,
+            Statement::For(init, cond, post, body) => {
+                if let Some(init) = init {
+                    self.operand(init)?;
+                    self.depth = depth;
+                }
+                self.for_loop(cond, post, body)?;
+            }
+            Statement::ForDeclaration(decl, cond, post, body) => {
+                self.scopes.push(HashMap::new());
+                self.declaration(decl)?;
+                self.for_loop(cond, post, body)?;
+                self.scopes.pop();
+            }
+            Statement::While(cond, body) => {
+                let cont = self.label();
+                let end = self.label();
+                self.place(cont);
+                self.jump_if_false(cond, end)?;
+                self.loop_body(body, cont, end)?;
+                self.jump(cont);
+                self.place(end);
+            }
+            Statement::Do(body, cond) => {
+                let start = self.label();
+                let cont = self.label();
+                let end = self.label();
+                self.place(start);
+                self.loop_body(body, cont, end)?;
+                self.place(cont);
+                let v = self.operand(cond)?;
+                self.emit(Op::JIT, &[v, Operand::Label(start)]);
+                self.place(end);
+            }
+            Statement::Break => {
+                let label = self
+                    .loops
+                    .last()
+                    .ok_or(CompileError::BreakOutsideLoop)?
+                    .break_label;
+                self.jump(label);
+            }
+            Statement::Continue => {
+                let label = self
+                    .loops
+                    .last()
+                    .ok_or(CompileError::ContinueOutsideLoop)?
+                    .continue_label;
+                self.jump(label);
+            }
+        }
+        self.depth = depth;
+        Ok(())
+    }
+
+    fn program(mut self, program: &Program) -> Result<Vec<i128>, CompileError> {
+        let Program::Program(Function::Function(_, items)) = program;
+        let stack = self.label();
+        self.emit(Op::SP, &[Operand::Label(stack)]);
+        self.block(items)?;
+        self.emit(Op::OUT, &[Operand::Immediate(0)]);
+        self.emit(Op::HLT, &[]);
+        if let Some(dm) = self.divmod {
+            self.emit_divmod(dm);
+        }
+        for label in std::mem::take(&mut self.cells) {
+            self.place(label);
+            self.code.push(0);
+        }
+        self.place(stack);
+        for (pos, label) in &self.fixups {
+            self.code[*pos] = self.labels[*label].expect("label not placed") as i128;
+        }
+        Ok(self.code)
+    }
+}
+
+// Compiles a parsed program to Intcode memory to run with Machine::new
+pub fn compile(program: &Program) -> Result<Vec<i128>, CompileError> {
+    CodeGen::new().program(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Machine;
+
+    fn run(source: &str) -> i128 {
+        let program = parse(&tokenize(source)).unwrap();
+        let mut m = Machine::new(&compile(&program).unwrap());
+        m.run();
+        let outputs = m.outputs();
+        assert_eq!(outputs.len(), 1);
+        outputs[0]
+    }
+
+    fn compile_error(source: &str) -> CompileError {
+        compile(&parse(&tokenize(source)).unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(run("int main() { return 2 + 3 * 4 - -1; }"), 15);
+        assert_eq!(
+            run("int main() { int a = 10; int b = 4; return a - b; }"),
+            6
+        );
+        assert_eq!(
+            run("int main() { return 100 / 7 * 1000 + 1000000 % 37; }"),
+            14001
+        );
+        assert_eq!(
+            run("int main() { return (7 - 10) / 2 * 100 + -7 % 3; }"),
+            -101
+        );
+        assert_eq!(run("int main() { return 7 / -2 + 7 % -2 * 10; }"), 7);
+        assert_eq!(
+            run("int main() { return (3 <= 3) + (4 >= 5) * 10 + (1 != 2) * 100; }"),
+            101
+        );
+    }
+
+    #[test]
+    fn test_loops() {
+        let source = "\
+int main() {
+    int s = 0;
+    for (int i = 0; i < 100; i = i + 1) {
+        if (i % 2)
+            continue;
+        if (i > 8)
+            break;
+        s = s + i;
+    }
+    int f = 1;
+    int n = 5;
+    while (n) {
+        f = f * n;
+        n = n - 1;
+    }
+    int k = 0;
+    do k = k + 1; while (0);
+    for (;;) {
+        break;
+    }
+    return s * 1000 + f + k * 10000;
+}
+";
+        assert_eq!(run(source), 30120);
+    }
+
+    #[test]
+    fn test_short_circuit() {
+        let source = "\
+int main() {
+    int a = 0;
+    int b = 0;
+    if (a && (b = 1))
+        a = 5;
+    int c = 1 || (b = 2);
+    a = 2 && a;
+    return b * 1000 + c * 100 + a * 10 + (2 && 3);
+}
+";
+        assert_eq!(run(source), 101);
+        assert_eq!(
+            run("int main() { int b = 0; return b ? 1 : b < 3 ? 20 : 30; }"),
+            20
+        );
+    }
+
+    #[test]
+    fn test_scopes() {
+        assert_eq!(
+            run("int main() { int a = 1; { int a = 2; a = 3; } return a; }"),
+            1
+        );
+        assert_eq!(run("int main() { int a; a = 4; }"), 0);
+        assert_eq!(
+            compile_error("int main() { return x; }"),
+            CompileError::UndeclaredVariable("x".into())
+        );
+        assert_eq!(
+            compile_error("int main() { int a; int a; return 0; }"),
+            CompileError::Redeclaration("a".into())
+        );
+        assert_eq!(
+            compile_error("int main() { break; }"),
+            CompileError::BreakOutsideLoop
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Conditions for debugger breakpoints
+//!
+//! A condition compares two values, like `mem[1234] > 5`, `input_len == 0`
+//! or `mem[sp+2] != ip`. Values are numbers, `ip`, `sp`, `input_len` and
+//! memory at a fixed address or relative to `sp`.
+
+use crate::machine::Machine;
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    Number(i128),
+    Ip,
+    Sp,
+    InputLen,
+    Memory { relative: bool, offset: i128 },
+}
+
+impl Value {
+    fn parse(s: &str) -> Option<Value> {
+        let s = s.trim();
+        Some(match s {
+            "ip" => Value::Ip,
+            "sp" => Value::Sp,
+            "input_len" => Value::InputLen,
+            _ => {
+                if let Some(inner) = s.strip_prefix("mem[").and_then(|x| x.strip_suffix(']')) {
+                    let inner: String = inner.chars().filter(|c| !c.is_whitespace()).collect();
+                    if let Some(offset) = inner.strip_prefix("sp") {
+                        let offset = match offset {
+                            "" => 0,
+                            _ => offset.strip_prefix('+').unwrap_or(offset).parse().ok()?,
+                        };
+                        Value::Memory {
+                            relative: true,
+                            offset,
+                        }
+                    } else {
+                        Value::Memory {
+                            relative: false,
+                            offset: inner.parse().ok()?,
+                        }
+                    }
+                } else {
+                    Value::Number(s.parse().ok()?)
+                }
+            }
+        })
+    }
+
+    fn eval(&self, machine: &Machine) -> i128 {
+        match self {
+            Value::Number(n) => *n,
+            Value::Ip => machine.ip() as i128,
+            Value::Sp => machine.sp() as i128,
+            Value::InputLen => machine.input_len() as i128,
+            Value::Memory { relative, offset } => {
+                let base = if *relative { machine.sp() as i128 } else { 0 };
+                usize::try_from(base + offset)
+                    .ok()
+                    .and_then(|a| machine.memory().get(a))
+                    .copied()
+                    .unwrap_or(0)
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// Longer operators first so `<=` isn't read as `<`
+const OPERATORS: [(&str, Compare); 6] = [
+    ("==", Compare::Eq),
+    ("!=", Compare::Ne),
+    ("<=", Compare::Le),
+    (">=", Compare::Ge),
+    ("<", Compare::Lt),
+    (">", Compare::Gt),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    text: String,
+    left: Value,
+    compare: Compare,
+    right: Value,
+}
+
+impl Condition {
+    pub fn parse(s: &str) -> Option<Condition> {
+        let (pos, op, compare) = OPERATORS
+            .iter()
+            .find_map(|(op, c)| s.find(op).map(|pos| (pos, op, *c)))?;
+        Some(Condition {
+            text: s.trim().to_string(),
+            left: Value::parse(&s[..pos])?,
+            compare,
+            right: Value::parse(&s[pos + op.len()..])?,
+        })
+    }
+
+    pub fn eval(&self, machine: &Machine) -> bool {
+        let a = self.left.eval(machine);
+        let b = self.right.eval(machine);
+        match self.compare {
+            Compare::Eq => a == b,
+            Compare::Ne => a != b,
+            Compare::Lt => a < b,
+            Compare::Le => a <= b,
+            Compare::Gt => a > b,
+            Compare::Ge => a >= b,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_condition() {
+        let mut m = Machine::with_input(&[109, 3, 99, 7, 8], &[1]);
+        let eval = |s: &str, m: &Machine| Condition::parse(s).unwrap().eval(m);
+        assert!(eval("mem[3] == 7", &m));
+        assert!(eval("mem[4]>=8", &m));
+        assert!(!eval("mem[4] < 8", &m));
+        assert!(eval("input_len != 0", &m));
+        assert!(eval("mem[-1] == 0", &m));
+        m.step();
+        assert!(eval("ip == 2", &m));
+        assert!(eval("mem[sp] == 7", &m));
+        assert!(eval("mem[sp + 1] > mem[sp]", &m));
+        assert!(eval("mem[sp-1] == 99", &m));
+        assert_eq!(Condition::parse("mem[3] 7"), None);
+        assert_eq!(Condition::parse("mem[x] == 7"), None);
+        assert_eq!(Condition::parse(" sp > 1 ").unwrap().to_string(), "sp > 1");
+    }
+}
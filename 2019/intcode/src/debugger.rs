@@ -1,18 +1,47 @@
+use crate::condition::Condition;
 use crate::machine::*;
+use crate::to_ascii;
+use crate::Cfg;
+use crate::History;
+use crate::Profile;
 use std::collections::HashMap;
-use std::collections::HashSet;
+use std::fmt;
 
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
 use ansi_term::Colour;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "r"),
+            Access::Write => write!(f, "w"),
+            Access::ReadWrite => write!(f, "rw"),
+        }
+    }
+}
+
 pub struct Debugger<'a> {
     machine: &'a mut Machine,
-    breakpoints: HashSet<usize>,
+    breakpoints: HashMap<usize, Option<Condition>>,
     watches: HashMap<String, usize>,
+    watchpoints: HashMap<usize, Access>,
+    history: History,
 }
 
+// Steps between copies of the machine kept for going back
+const SNAPSHOT_INTERVAL: usize = 1000;
+// Copies kept, going back is limited to the last million steps or so
+const MAX_SNAPSHOTS: usize = 1000;
+
 fn config() {
     #[cfg(target_os = "windows")]
     let _ = ansi_term::enable_ansi_support();
@@ -21,52 +50,30 @@ fn config() {
 impl Debugger<'_> {
     pub fn new(machine: &mut Machine) -> Debugger<'_> {
         config();
+        // For the dump command
+        machine.set_stats(true);
         Debugger {
             machine,
-            breakpoints: HashSet::new(),
+            breakpoints: HashMap::new(),
             watches: HashMap::new(),
+            watchpoints: HashMap::new(),
+            history: History::new(SNAPSHOT_INTERVAL, MAX_SNAPSHOTS),
         }
     }
 
     pub fn analyze(&self) {
-        let mut a = 0;
-        let mut sp = 0;
-        let mut sp_addr = 0;
-        loop {
-            a = match self.machine.get_disassembly(a) {
-                Disassembly::Instruction(x) => {
-                    match x.op {
-                        // Find function
-                        Op::SP => {
-                            if let Some(Arg::Immediate { value }) = &x.read().first() {
-                                if value.is_positive() {
-                                    sp = *value;
-                                    sp_addr = a;
-                                } else if *value == -sp {
-                                    sp = 0;
-                                }
-                            }
-                        }
-                        Op::JIT => {
-                            if let Some(Arg::Immediate { value: 1 }) = &x.read().first() {
-                                if let Some(Arg::Relative { base: _, offset: 0 }) = &x.read().get(1)
-                                {
-                                    println!("found function {} -> {}", sp_addr, a);
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                    a + x.increment()
-                }
-                Disassembly::MemoryValue(_x) => {
-                    println!("variable at {}", a);
-                    a + 1
-                }
-            };
-            if a >= self.machine.memory().len() {
-                break;
-            }
+        let cfg = Cfg::new(self.machine.memory());
+        for f in cfg.functions() {
+            println!(
+                "found function {} at {} ({} blocks, frame {})",
+                f.name(),
+                f.entry,
+                f.blocks.len(),
+                f.frame
+            );
+        }
+        for (start, end) in cfg.data() {
+            println!("data at {}..{}", start, end);
         }
     }
 
@@ -75,7 +82,7 @@ impl Debugger<'_> {
             Disassembly::Instruction(x) => {
                 print!(
                     "{} SP:{}, IP:",
-                    if self.breakpoints.contains(&a) {
+                    if self.breakpoints.contains_key(&a) {
                         "*"
                     } else {
                         " "
@@ -133,30 +140,55 @@ impl Debugger<'_> {
         self.print_memory(self.machine.sp(), 16);
     }
 
-    fn set_breakpoint(&mut self, address: usize) {
-        if self.breakpoints.insert(address) {
-            println!("Breakpoint set on address {}", address);
+    fn set_breakpoint(&mut self, address: usize, condition: Option<Condition>) {
+        let text = match &condition {
+            Some(c) => format!(" if {}", c),
+            None => String::new(),
+        };
+        if self.breakpoints.insert(address, condition).is_none() {
+            println!("Breakpoint set on address {}{}", address, text);
         } else {
-            println!("Breakpoint already set on address {}", address);
+            println!("Breakpoint changed on address {}{}", address, text);
         }
     }
 
     fn list_breakpoints(&self) {
         let mut bp: Vec<_> = self.breakpoints.iter().collect();
-        bp.sort();
-        for addr in bp {
-            println!("b {}", addr);
+        bp.sort_by_key(|b| b.0);
+        for (addr, condition) in bp {
+            match condition {
+                Some(c) => println!("b {} if {}", addr, c),
+                None => println!("b {}", addr),
+            }
+        }
+        let mut wp: Vec<_> = self.watchpoints.iter().collect();
+        wp.sort_by_key(|w| w.0);
+        for (addr, access) in wp {
+            println!("wp {} {}", addr, access);
         }
     }
 
     fn clear_breakpoint(&mut self, address: usize) {
-        if self.breakpoints.remove(&address) {
+        if self.breakpoints.remove(&address).is_some() {
             println!("Breakpoint removed on address {}", address);
         } else {
             println!("No breakpoint on address {}", address);
         }
     }
 
+    fn set_watchpoint(&mut self, address: usize, access: Access) {
+        self.watchpoints.insert(address, access);
+        println!("Watchpoint set on address {} ({})", address, access);
+    }
+
+    fn clear_watchpoint(&mut self, address: usize) {
+        if self.watchpoints.remove(&address).is_some() {
+            println!("Watchpoint removed on address {}", address);
+        } else {
+            println!("No watchpoint on address {}", address);
+        }
+    }
+
     fn set_watch(&mut self, name: &str, address: usize) {
         if let Some(v) = self.watches.insert(name.into(), address) {
             println!(
@@ -184,21 +216,50 @@ impl Debugger<'_> {
     }
 
     fn print_output(&mut self) {
-        for o in self.machine.outputs() {
-            if (0..256).contains(&o) {
-                print!("{}", std::char::from_u32(o as u32).unwrap());
-            } else {
-                println!("value: {}", o);
+        let out = to_ascii(&self.machine.outputs());
+        print!("{}", out.text);
+        for v in out.values {
+            println!("value: {}", v);
+        }
+    }
+
+    // Goes back to the given step, outputs after it were already printed
+    fn travel(&mut self, time: usize) {
+        self.history.goto(self.machine, time);
+        let _ = self.machine.outputs();
+    }
+
+    // Conditions are not checked, they would need the machine at each step
+    fn reverse_continue(&mut self) {
+        let breakpoints = &self.breakpoints;
+        match self.history.rfind(|s| breakpoints.contains_key(&s.ip)) {
+            Some(t) => {
+                self.travel(t);
+                println!("Breakpoint reached at step {}", t);
+            }
+            None => {
+                self.travel(self.history.start());
+                println!("Oldest recorded step reached");
             }
         }
     }
 
-    fn handle_io(&mut self, rl: &mut Editor<()>, state: State) {
+    fn last_write(&mut self, address: usize) {
+        if let Some(t) = self.history.last_write(address) {
+            self.travel(t);
+            println!("Last write to {} at step {}", address, t);
+        } else {
+            println!("No write to {} recorded", address);
+        }
+    }
+
+    // Prints the outputs, or reads a line with read_line and adds it as input
+    fn handle_io(&mut self, read_line: &mut dyn FnMut(&str) -> Option<String>, state: State) {
         if state == State::Output {
             self.print_output();
         }
         if state == State::Input {
-            if let Ok(s) = rl.readline("INP >> ") {
+            if let Some(s) = read_line("INP >> ") {
                 let x = s.trim();
                 for c in x.chars() {
                     self.machine.add_input(c as i128);
@@ -208,6 +269,248 @@ impl Debugger<'_> {
         }
     }
 
+    // Addresses read and written by the instruction at ip
+    fn accesses(&self) -> (Vec<usize>, Option<usize>) {
+        let mut reads = vec![];
+        if let Disassembly::Instruction(x) = self.machine.get_disassembly(self.machine.ip()) {
+            for r in x.read() {
+                let address = match r {
+                    Arg::Immediate { .. } => continue,
+                    Arg::Position { address } => *address,
+                    Arg::Relative { base, offset } => base + offset,
+                };
+                if address >= 0 {
+                    reads.push(address as usize);
+                }
+            }
+        }
+        (reads, self.machine.write_address())
+    }
+
+    // The watchpoint hit by the instruction at ip, if any
+    fn watchpoint_hit(&self) -> Option<(usize, Access)> {
+        if self.watchpoints.is_empty() {
+            return None;
+        }
+        let (reads, write) = self.accesses();
+        if let Some(a) = write {
+            if let Some(Access::Write | Access::ReadWrite) = self.watchpoints.get(&a) {
+                return Some((a, Access::Write));
+            }
+        }
+        reads
+            .into_iter()
+            .find_map(|a| match self.watchpoints.get(&a) {
+                Some(Access::Read | Access::ReadWrite) => Some((a, Access::Read)),
+                _ => None,
+            })
+    }
+
+    fn breakpoint_hit(&self) -> bool {
+        match self.breakpoints.get(&self.machine.ip()) {
+            Some(Some(c)) => c.eval(self.machine),
+            Some(None) => true,
+            None => false,
+        }
+    }
+
+    fn continue_run(&mut self, read_line: &mut dyn FnMut(&str) -> Option<String>) {
+        loop {
+            let watchpoint = self.watchpoint_hit();
+            let old = watchpoint.map(|(a, _)| *self.machine.memory().get(a).unwrap_or(&0));
+            let state = self.history.step(self.machine);
+            self.handle_io(read_line, state);
+            // The machine stays at the faulting instruction
+            if let State::Fault(f) = state {
+                println!("Fault: {}", f);
+                break;
+            }
+            if state != State::Input {
+                if let (Some((a, access)), Some(old)) = (watchpoint, old) {
+                    let new = *self.machine.memory().get(a).unwrap_or(&0);
+                    match access {
+                        Access::Write => println!("Watchpoint: write {} -> {} at {}", old, new, a),
+                        _ => println!("Watchpoint: read {} at {}", old, a),
+                    }
+                    break;
+                }
+            }
+            if self.breakpoint_hit() {
+                println!("Breakpoint reached");
+                break;
+            }
+            if state == State::Halted {
+                println!("Program halted");
+                break;
+            }
+            if state == State::Input && self.machine.input_len() == 0 {
+                println!("No input");
+                break;
+            }
+        }
+        let _ = self.print_instruction(self.machine.ip(), true);
+    }
+
+    // Runs one debugger command, lines for INP come from read_line. Returns
+    // false when the session should end.
+    pub fn command(
+        &mut self,
+        line: &str,
+        read_line: &mut dyn FnMut(&str) -> Option<String>,
+    ) -> bool {
+        if line == "s" {
+            let state = self.history.step(self.machine);
+            self.handle_io(read_line, state);
+            if let State::Fault(f) = state {
+                println!("Fault: {}", f);
+            }
+            if state == State::Halted {
+                println!("Program halted");
+            } else {
+                let _ = self.print_instruction(self.machine.ip(), true);
+            }
+        } else if line == "q" {
+            return false;
+        } else if line == "rs" {
+            if self.history.step_back(self.machine) {
+                let _ = self.machine.outputs();
+            } else {
+                println!("Oldest recorded step reached");
+            }
+            let _ = self.print_instruction(self.machine.ip(), true);
+        } else if line == "rc" {
+            self.reverse_continue();
+            let _ = self.print_instruction(self.machine.ip(), true);
+        } else if let Some(stripped) = line.strip_prefix("lw ") {
+            if let Ok(addr) = stripped.trim().parse::<usize>() {
+                self.last_write(addr);
+                let _ = self.print_instruction(self.machine.ip(), true);
+            } else {
+                println!("Invalid address!");
+            }
+        } else if line.starts_with('b') {
+            if line == "bl" {
+                self.list_breakpoints();
+            } else if let Some(stripped) = line.strip_prefix("bc") {
+                if let Ok(addr) = stripped.trim().parse::<usize>() {
+                    self.clear_breakpoint(addr);
+                } else {
+                    self.clear_breakpoint(self.machine.ip());
+                }
+            } else if let Some(stripped) = line.strip_prefix('b') {
+                let (addr, condition) = match stripped.split_once(" if ") {
+                    Some((addr, c)) => (addr, Some(c)),
+                    None => (stripped, None),
+                };
+                let addr = addr.trim().parse::<usize>().unwrap_or(self.machine.ip());
+                match condition.map(Condition::parse) {
+                    Some(None) => println!("Invalid condition: {}", condition.unwrap()),
+                    Some(c) => self.set_breakpoint(addr, c),
+                    None => self.set_breakpoint(addr, None),
+                }
+            }
+        } else if let Some(stripped) = line.strip_prefix("wp") {
+            let parts: Vec<_> = stripped.split_whitespace().collect();
+            if parts.first() == Some(&"c") {
+                match parts.get(1).and_then(|a| a.parse::<usize>().ok()) {
+                    Some(addr) => self.clear_watchpoint(addr),
+                    None => println!("Invalid address!"),
+                }
+            } else {
+                let addr = parts.first().and_then(|a| a.parse::<usize>().ok());
+                let access = match parts.get(1) {
+                    None | Some(&"w") => Some(Access::Write),
+                    Some(&"r") => Some(Access::Read),
+                    Some(&"rw") => Some(Access::ReadWrite),
+                    _ => None,
+                };
+                match (addr, access) {
+                    (Some(addr), Some(access)) => self.set_watchpoint(addr, access),
+                    _ => println!("Invalid watchpoint: {}", line),
+                }
+            }
+        } else if line.starts_with('w') {
+            if let Some(stripped) = line.strip_prefix("wr ") {
+                let parts: Vec<_> = stripped.split_whitespace().collect();
+                let addr = parts.first().and_then(|a| a.parse::<usize>().ok());
+                let val = parts.get(1).and_then(|v| v.parse::<i128>().ok());
+                match (addr, val) {
+                    (Some(addr), Some(val)) => {
+                        if let Some(x) = self.machine.memory_mut().get_mut(addr) {
+                            *x = val;
+                            // Going back past here undoes the write
+                            self.history.snapshot(self.machine);
+                        } else {
+                            println!("Invalid address!");
+                        }
+                    }
+                    _ => println!("Invalid command: {}", line),
+                }
+            } else if line == "wl" {
+                self.list_watches();
+            } else if let Some(stripped) = line.strip_prefix("wc") {
+                self.clear_watch(stripped);
+            } else {
+                let parts: Vec<_> = line.split_whitespace().collect();
+                match (
+                    parts.get(1),
+                    parts.get(2).and_then(|a| a.parse::<usize>().ok()),
+                ) {
+                    (Some(name), Some(addr)) if parts[0] == "w" => self.set_watch(name, addr),
+                    _ => println!("Invalid command: {}", line),
+                }
+            }
+        } else if line == "c" {
+            self.continue_run(read_line);
+        } else if line == "pr" {
+            print!("{}", Profile::new(self.machine).report(10));
+        } else if let Some(stripped) = line.strip_prefix('p') {
+            if let Ok(addr) = stripped.trim().parse::<usize>() {
+                self.print_memory(addr, 8);
+            } else {
+                self.print_memory(self.machine.ip(), 8);
+            }
+        } else if line == "m" {
+            self.print_memory(self.machine.ip(), 8);
+        } else if line == "ds" {
+            self.machine.dump(5);
+        } else if let Some(stripped) = line.strip_prefix('l') {
+            if let Ok(lines) = stripped.trim().parse::<usize>() {
+                self.print_instructions(self.machine.ip(), lines);
+            } else {
+                self.print_instructions(self.machine.ip(), 8);
+            }
+        } else {
+            println!("Invalid command: {}", line);
+        }
+        true
+    }
+
+    // Runs the commands without a terminal. When the machine waits for
+    // input the next line is sent to it instead. Empty lines and lines
+    // starting with # are skipped.
+    pub fn run_script<S: AsRef<str>>(&mut self, script: &[S]) {
+        let mut lines = script
+            .iter()
+            .map(|l| l.as_ref().trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string());
+        let _ = self.print_instruction(self.machine.ip(), false);
+        while let Some(line) = lines.next() {
+            println!(">> {}", line);
+            let mut read_line = |prompt: &str| {
+                let l = lines.next();
+                if let Some(l) = &l {
+                    println!("{}{}", prompt, l);
+                }
+                l
+            };
+            if !self.command(&line, &mut read_line) {
+                break;
+            }
+        }
+    }
+
     pub fn debug(&mut self) {
         // `()` can be used when no completer is required
         let mut rl = Editor::<()>::new();
@@ -221,87 +524,13 @@ impl Debugger<'_> {
             match readline {
                 Ok(l) => {
                     rl.add_history_entry(l.as_str());
-                    let mut line = l;
+                    let mut line = l.trim().to_string();
                     #[allow(clippy::unnecessary_unwrap)]
                     if line.is_empty() && last.is_some() {
                         line = last.unwrap();
                     }
-                    if line == "s" {
-                        let state = self.machine.step();
-                        self.handle_io(&mut rl, state);
-                        if state == State::Halted {
-                            println!("Program halted");
-                        } else {
-                            let _ = self.print_instruction(self.machine.ip(), true);
-                        }
-                    } else if line.starts_with('b') {
-                        if line == "bl" {
-                            self.list_breakpoints();
-                        } else if let Some(stripped) = line.strip_prefix("bc") {
-                            if let Ok(addr) = stripped.trim().parse::<usize>() {
-                                self.clear_breakpoint(addr);
-                            } else {
-                                self.clear_breakpoint(self.machine.ip());
-                            }
-                        } else if let Some(stripped) = line.strip_prefix('b') {
-                            if let Ok(addr) = stripped.trim().parse::<usize>() {
-                                self.set_breakpoint(addr);
-                            } else {
-                                self.set_breakpoint(self.machine.ip());
-                            }
-                        }
-                    } else if line.starts_with('w') {
-                        if line.starts_with("wr ") {
-                            let parts: Vec<_> = line.split(' ').map(|x| x.trim()).collect();
-                            let addr = parts[1].parse::<usize>().unwrap();
-                            let val = parts[2].parse::<i128>().unwrap();
-                            if let Some(x) = self.machine.memory_mut().get_mut(addr) {
-                                *x = val;
-                            } else {
-                                println!("Invalid address!");
-                            }
-                        } else if line == "wl" {
-                            self.list_watches();
-                        } else if let Some(stripped) = line.strip_prefix("wc") {
-                            self.clear_watch(stripped);
-                        } else {
-                            let parts: Vec<_> = line.split(' ').map(|x| x.trim()).collect();
-                            let name = parts[1];
-                            let addr = parts[2].parse::<usize>().unwrap();
-                            self.set_watch(name, addr);
-                        }
-                    } else if line == "c" {
-                        loop {
-                            let state = self.machine.step();
-                            self.handle_io(&mut rl, state);
-                            if self.breakpoints.contains(&self.machine.ip()) {
-                                println!("Breakpoint reached");
-                                break;
-                            }
-                            if state == State::Halted {
-                                println!("Program halted");
-                                break;
-                            }
-                        }
-                        let _ = self.print_instruction(self.machine.ip(), true);
-                    } else if let Some(stripped) = line.strip_prefix('p') {
-                        if let Ok(addr) = stripped.trim().parse::<usize>() {
-                            self.print_memory(addr, 8);
-                        } else {
-                            self.print_memory(self.machine.ip(), 8);
-                        }
-                    } else if line == "m" {
-                        self.print_memory(self.machine.ip(), 8);
-                    } else if line == "ds" {
-                        self.machine.dump(5);
-                    } else if let Some(stripped) = line.strip_prefix('l') {
-                        if let Ok(lines) = stripped.trim().parse::<usize>() {
-                            self.print_instructions(self.machine.ip(), lines);
-                        } else {
-                            self.print_instructions(self.machine.ip(), 8);
-                        }
-                    } else {
-                        println!("Invalid command: {}", line);
+                    if !self.command(&line, &mut |prompt| rl.readline(prompt).ok()) {
+                        break;
                     }
                     last = Some(line);
                 }
@@ -322,3 +551,77 @@ impl Debugger<'_> {
         rl.save_history("history.txt").unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // Sums the inputs until a 0 is read
+    fn sum() -> Vec<i128> {
+        assemble(
+            "\
+loop:   INP [x]
+        ADD [s], [x] -> [s]
+        JIT [x], loop
+        OUT [s]
+        HLT
+x:      DATA 0
+s:      DATA 0
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        let program = sum();
+        let s = program.len() - 1;
+        let mut m = Machine::with_input(&program, &[1, 2, 3, 0]);
+        let mut d = Debugger::new(&mut m);
+        d.run_script(&[format!("b 2 if mem[{}] >= 3", s), "c".into()]);
+        assert_eq!((m.ip(), m.memory()[s]), (2, 3));
+        let mut m = Machine::with_input(&program, &[1, 2, 3, 0]);
+        let mut d = Debugger::new(&mut m);
+        d.run_script(&["b 9 if input_len == 0", "c", "s", "q", "s"]);
+        assert_eq!(m.ip(), 11);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let program = sum();
+        let s = program.len() - 1;
+        let x = s - 1;
+        let mut m = Machine::with_input(&program, &[1, 2, 3, 0]);
+        let mut d = Debugger::new(&mut m);
+        d.run_script(&[format!("wp {}", s), "c".into()]);
+        assert_eq!(d.machine.ip(), 6);
+        assert_eq!(d.machine.memory()[s], 1);
+        d.run_script(&[format!("wp {} r", x), format!("wpc {}", s), "c".into()]);
+        assert_eq!(d.machine.ip(), 0);
+        assert_eq!(d.machine.memory()[s], 1);
+        d.run_script(&["c", "c"]);
+        assert_eq!(d.machine.memory()[s], 3);
+    }
+
+    #[test]
+    fn test_invalid_commands() {
+        let program = sum();
+        let mut m = Machine::new(&program);
+        let mut d = Debugger::new(&mut m);
+        d.run_script(&["wr", "wr 5", "wr x 1", "wr 5 y", "w", "w x", "w x y"]);
+        assert!(d.watches.is_empty());
+        d.run_script(&["wr 0 2", "w x 0"]);
+        assert_eq!(d.watches.get("x"), Some(&0));
+        assert_eq!(m.memory()[0], 2);
+    }
+
+    #[test]
+    fn test_script_input() {
+        let mut m = Machine::new(&sum());
+        let mut d = Debugger::new(&mut m);
+        d.run_script(&["# sends a and a newline", "c", "", "a"]);
+        assert_eq!(m.ip(), 0);
+        assert_eq!(m.memory().last(), Some(&107));
+    }
+}
@@ -0,0 +1,231 @@
+//! Recorded execution for going back in time
+//!
+//! Every executed instruction is logged with the address it wrote, and a
+//! copy of the machine is kept every `interval` steps. Going back restores
+//! the closest earlier copy and replays the steps after it, which gives the
+//! same machine as the inputs are kept.
+//!
+//! At most `max_snapshots` copies are kept. When there are more, the oldest
+//! copy and the steps before the next one are dropped, so going back stops
+//! at the oldest kept copy, see `start`.
+
+use crate::machine::{Machine, State};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub ip: usize,
+    pub write: Option<usize>,
+}
+
+pub struct History {
+    interval: usize,
+    max_snapshots: usize,
+    // Time of the first kept step
+    start: usize,
+    // The machine before the step at each time
+    snapshots: Vec<(usize, Machine)>,
+    steps: Vec<Step>,
+}
+
+impl History {
+    pub fn new(interval: usize, max_snapshots: usize) -> History {
+        History {
+            interval: interval.max(1),
+            max_snapshots: max_snapshots.max(1),
+            start: 0,
+            snapshots: vec![],
+            steps: vec![],
+        }
+    }
+
+    // Number of steps executed, which is the current time
+    pub fn len(&self) -> usize {
+        self.start + self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Earliest time that can be gone back to
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    // The kept steps, the first one at time start
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    // Keeps a copy of the machine at the current time, needed when it was
+    // changed outside of step
+    pub fn snapshot(&mut self, machine: &Machine) {
+        let time = self.len();
+        if let Some((t, _)) = self.snapshots.last() {
+            if *t == time {
+                self.snapshots.pop();
+            }
+        }
+        self.snapshots.push((time, machine.clone()));
+        if self.snapshots.len() > self.max_snapshots {
+            self.snapshots.remove(0);
+            let oldest = self.snapshots[0].0;
+            self.steps.drain(..oldest - self.start);
+            self.start = oldest;
+        }
+    }
+
+    // Steps the machine, recording the step if an instruction was executed
+    pub fn step(&mut self, machine: &mut Machine) -> State {
+        let time = self.len();
+        if time.is_multiple_of(self.interval) && self.snapshots.last().map(|s| s.0) != Some(time) {
+            self.snapshot(machine);
+        }
+        let ip = machine.ip();
+        let write = machine.write_address();
+        let state = machine.step();
+        if let State::Running | State::Output | State::Halted = state {
+            self.steps.push(Step { ip, write });
+        }
+        state
+    }
+
+    // Takes the machine back to an earlier time, forgetting what came after.
+    // Times before start go to start.
+    pub fn goto(&mut self, machine: &mut Machine, time: usize) {
+        if time >= self.len() {
+            return;
+        }
+        let time = time.max(self.start);
+        while self.snapshots.last().is_some_and(|s| s.0 > time) {
+            self.snapshots.pop();
+        }
+        let (start, snapshot) = self.snapshots.last().unwrap();
+        machine.restore(snapshot);
+        for _ in *start..time {
+            machine.step();
+        }
+        self.steps.truncate(time - self.start);
+    }
+
+    // Goes back one step, false at start
+    pub fn step_back(&mut self, machine: &mut Machine) -> bool {
+        if self.len() == self.start {
+            return false;
+        }
+        self.goto(machine, self.len() - 1);
+        true
+    }
+
+    // Time of the last step matching f
+    pub fn rfind(&self, f: impl Fn(&Step) -> bool) -> Option<usize> {
+        self.steps.iter().rposition(f).map(|i| self.start + i)
+    }
+
+    // Time of the last step that wrote to address
+    pub fn last_write(&self, address: usize) -> Option<usize> {
+        self.rfind(|s| s.write == Some(address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // Sums the inputs until a 0 is read
+    fn sum() -> Vec<i128> {
+        assemble(
+            "\
+loop:   INP [x]
+        ADD [s], [x] -> [s]
+        JIT [x], loop
+        OUT [s]
+        HLT
+x:      DATA 0
+s:      DATA 0
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_goto() {
+        let program = sum();
+        let mut m = Machine::with_input(&program, &[1, 2]);
+        let mut h = History::new(2, 100);
+        while h.step(&mut m) != State::Input {}
+        assert_eq!(h.len(), 6);
+        m.add_inputs(&[3, 0]);
+        while h.step(&mut m) != State::Halted {}
+        assert_eq!(m.outputs(), vec![6]);
+        let end = h.len();
+        let s = program.len() - 1;
+        assert_eq!(h.last_write(s), Some(end - 4));
+        assert_eq!(h.steps()[end - 4].ip, 2);
+
+        h.goto(&mut m, 7);
+        let mut fresh = Machine::with_input(&program, &[1, 2, 3, 0]);
+        for _ in 0..7 {
+            fresh.step();
+        }
+        assert_eq!(m.ip(), fresh.ip());
+        assert_eq!(m.memory(), fresh.memory());
+        assert_eq!(h.len(), 7);
+        assert!(h.step_back(&mut m));
+        assert_eq!(h.len(), 6);
+        assert_eq!(m.memory()[s], 3);
+
+        // Running again reads the same inputs
+        while h.step(&mut m) != State::Halted {}
+        assert_eq!(h.len(), end);
+        assert_eq!(m.outputs(), vec![6]);
+        h.goto(&mut m, 0);
+        assert_eq!(m.memory(), program.as_slice());
+        assert!(!h.step_back(&mut m));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let program = sum();
+        let mut m = Machine::with_input(&program, &[5, 0]);
+        let mut h = History::new(100, 100);
+        h.step(&mut m);
+        h.step(&mut m);
+        let s = program.len() - 1;
+        m.memory_mut()[s] = 10;
+        h.snapshot(&m);
+        while h.step(&mut m) != State::Halted {}
+        assert_eq!(m.outputs(), vec![10]);
+        h.goto(&mut m, 3);
+        assert_eq!(m.memory()[s], 10);
+        h.goto(&mut m, 1);
+        assert_eq!(m.memory()[s], 0);
+    }
+
+    #[test]
+    fn test_max_snapshots() {
+        let program = sum();
+        let mut m = Machine::with_input(&program, &[1, 2, 3, 0]);
+        let mut h = History::new(2, 3);
+        while h.step(&mut m) != State::Halted {}
+        let end = h.len();
+        assert_eq!(m.outputs(), vec![6]);
+        // Snapshots at 8, 10 and 12 are kept
+        assert_eq!((end, h.start()), (14, 8));
+        assert_eq!(h.steps().len(), 6);
+        assert_eq!(h.last_write(program.len() - 1), Some(end - 4));
+        // Only the last input is still recorded
+        assert_eq!(h.steps().iter().filter(|s| s.ip == 0).count(), 1);
+
+        h.goto(&mut m, 3);
+        assert_eq!(h.len(), 8);
+        let mut fresh = Machine::with_input(&program, &[1, 2, 3, 0]);
+        for _ in 0..8 {
+            fresh.step();
+        }
+        assert_eq!(m.ip(), fresh.ip());
+        assert_eq!(m.memory(), fresh.memory());
+        assert!(!h.step_back(&mut m));
+    }
+}
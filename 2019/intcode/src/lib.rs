@@ -1,11 +1,27 @@
+mod analysis;
+mod ascii;
+mod asm;
 mod cling;
+mod codegen;
+mod condition;
 mod debugger;
+mod history;
 mod machine;
+mod network;
+mod profile;
 
+pub use analysis::{Block, Cfg, Exit, Function};
+pub use ascii::*;
+pub use asm::{assemble, disassemble, AsmError};
 pub use cling::*;
+pub use codegen::{compile, CompileError};
 pub use debugger::Debugger;
+pub use history::{History, Step};
 pub use machine::Machine;
 pub use machine::State;
+pub use machine::{Fault, FaultReason};
+pub use network::*;
+pub use profile::{heat_color, Counter, Loop, Profile};
 
 pub fn parse_intcode(lines: &[String]) -> Vec<i128> {
     let result: Vec<i128> = lines[0]
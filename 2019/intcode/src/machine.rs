@@ -1,7 +1,9 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fmt;
 
 #[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Op {
     ADD,
     MUL,
@@ -16,7 +18,7 @@ pub enum Op {
 }
 
 impl Op {
-    fn from_i128(value: i128) -> Option<Op> {
+    pub(crate) fn from_i128(value: i128) -> Option<Op> {
         let v = value % 100;
         match v {
             1 => Some(Op::ADD),
@@ -33,6 +35,21 @@ impl Op {
         }
     }
 
+    pub fn code(&self) -> i128 {
+        match self {
+            Op::ADD => 1,
+            Op::MUL => 2,
+            Op::INP => 3,
+            Op::OUT => 4,
+            Op::JIT => 5,
+            Op::JIF => 6,
+            Op::LTN => 7,
+            Op::EQL => 8,
+            Op::SP => 9,
+            Op::HLT => 99,
+        }
+    }
+
     pub fn definition(&self) -> (&'static str, usize, usize) {
         match self {
             Op::ADD => ("ADD", 2, 1),
@@ -49,27 +66,6 @@ impl Op {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-enum Mode {
-    Position,
-    Immediate,
-    Relative,
-}
-
-fn mode(value: i128, pos: usize) -> Option<Mode> {
-    let mut div = 100;
-    for _ in 1..pos {
-        div *= 10;
-    }
-    let m = (value / div) % 10;
-    match m {
-        0 => Some(Mode::Position),
-        1 => Some(Mode::Immediate),
-        2 => Some(Mode::Relative),
-        _ => None,
-    }
-}
-
 #[derive(Debug)]
 pub struct MemoryValue {
     address: usize,
@@ -85,7 +81,7 @@ impl fmt::Display for MemoryValue {
 #[derive(Copy, Clone)]
 pub enum Arg {
     Immediate { value: i128 },
-    Position { address: usize },
+    Position { address: i128 },
     Relative { base: i128, offset: i128 },
 }
 
@@ -136,35 +132,28 @@ impl Instruction {
     }
 }
 
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Arg::Immediate { value } => write!(f, "{}", value),
+            Arg::Position { address } => write!(f, "[{}]", address),
+            Arg::Relative { base: _, offset } => write!(f, "[SP{:+}]", offset),
+        }
+    }
+}
+
+// Same syntax as the assembler, after the address
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:>04} {} ", self.address, self.name())?;
-        for r in self.read() {
-            match r {
-                Arg::Immediate { value } => write!(f, "{}, ", value)?,
-                Arg::Position { address } => write!(f, "[{}], ", address)?,
-                Arg::Relative { base: _, offset } => write!(
-                    f,
-                    "[SP{}{}], ",
-                    if offset.is_positive() { "+" } else { "" },
-                    offset
-                )?,
-            }
+        write!(f, "{:>04} {}", self.address, self.name())?;
+        for (i, r) in self.read().iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, r)?;
         }
         if !self.write().is_empty() {
-            write!(f, "-> ")?;
-        }
-        for w in self.write() {
-            match w {
-                Arg::Immediate { value } => write!(f, "{}, ", value)?,
-                Arg::Position { address } => write!(f, "[{}], ", address)?,
-                Arg::Relative { base: _, offset } => write!(
-                    f,
-                    "[SP{}{}], ",
-                    if offset.is_positive() { "+" } else { "" },
-                    offset
-                )?,
-            }
+            write!(f, " ->")?;
+        }
+        for (i, w) in self.write().iter().enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, w)?;
         }
         Ok(())
     }
@@ -175,13 +164,89 @@ pub enum Disassembly {
     MemoryValue(MemoryValue),
 }
 
+// An opcode with its parameter modes, decoded once per address
+#[derive(Copy, Clone)]
+struct Decoded {
+    op: Op,
+    modes: [u8; 3],
+    len: u8,
+}
+
+impl Decoded {
+    fn new(value: i128) -> Result<Decoded, FaultReason> {
+        if value < 0 {
+            return Err(FaultReason::UnknownOpcode);
+        }
+        let op = Op::from_i128(value).ok_or(FaultReason::UnknownOpcode)?;
+        let (_, reads, writes) = op.definition();
+        let mut modes = [0; 3];
+        let mut div = 100;
+        for (i, m) in modes.iter_mut().enumerate().take(reads + writes) {
+            *m = ((value / div) % 10) as u8;
+            if *m > 2 {
+                return Err(FaultReason::InvalidMode);
+            }
+            if i >= reads && *m == 1 {
+                return Err(FaultReason::ImmediateWrite);
+            }
+            div *= 10;
+        }
+        Ok(Decoded {
+            op,
+            modes,
+            len: (1 + reads + writes) as u8,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaultReason {
+    UnknownOpcode,
+    InvalidMode,
+    ImmediateWrite,
+    NegativeAddress,
+}
+
+impl fmt::Display for FaultReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FaultReason::UnknownOpcode => write!(f, "unknown opcode"),
+            FaultReason::InvalidMode => write!(f, "invalid parameter mode"),
+            FaultReason::ImmediateWrite => write!(f, "write in immediate mode"),
+            FaultReason::NegativeAddress => write!(f, "negative address"),
+        }
+    }
+}
+
+// Why the instruction at ip can't be executed. The machine stays at it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Fault {
+    pub ip: usize,
+    pub opcode: i128,
+    pub reason: FaultReason,
+    // The offending address for NegativeAddress
+    pub address: Option<i128>,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {} (opcode {})", self.reason, self.ip, self.opcode)?;
+        if let Some(address) = self.address {
+            write!(f, ", address {}", address)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Fault {}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum State {
     Running,
     Input,
     Output,
     Halted,
-    Invalid,
+    Fault(Fault),
 }
 
 #[derive(Clone)]
@@ -195,7 +260,9 @@ pub struct Machine {
     reads: HashMap<usize, usize>,
     writes: HashMap<usize, usize>,
     relative_base: i128,
+    // Only collected with set_stats, as it slows down execution
     stats: bool,
+    decoded: Vec<Option<Decoded>>,
 }
 
 impl Machine {
@@ -211,6 +278,7 @@ impl Machine {
             writes: HashMap::new(),
             relative_base: 0,
             stats: false,
+            decoded: vec![None; memory.len()],
         }
     }
 
@@ -218,6 +286,19 @@ impl Machine {
         self.stats = s;
     }
 
+    // Times each address was executed, read and written, with set_stats
+    pub fn executes(&self) -> &HashMap<usize, usize> {
+        &self.executes
+    }
+
+    pub fn reads(&self) -> &HashMap<usize, usize> {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &HashMap<usize, usize> {
+        &self.writes
+    }
+
     pub fn new(memory: &[i128]) -> Machine {
         Machine::with_input(memory, &[])
     }
@@ -233,6 +314,7 @@ impl Machine {
     }
 
     pub fn memory_mut(&mut self) -> &mut [i128] {
+        self.decoded.fill(None);
         self.memory.as_mut_slice()
     }
 
@@ -247,188 +329,236 @@ impl Machine {
     pub fn read_arg(&self, arg: &Arg) -> i128 {
         match arg {
             Arg::Immediate { value } => *value,
-            Arg::Position { address } => *self.memory.get(*address).unwrap_or(&0),
-            Arg::Relative { base, offset } => {
-                *self.memory.get((base + offset) as usize).unwrap_or(&0)
-            }
+            Arg::Position { address } => usize::try_from(*address)
+                .ok()
+                .and_then(|a| self.memory.get(a))
+                .copied()
+                .unwrap_or(0),
+            Arg::Relative { base, offset } => usize::try_from(base + offset)
+                .ok()
+                .and_then(|a| self.memory.get(a))
+                .copied()
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn add_input(&mut self, input: i128) {
+        self.inputs.push(input);
+    }
+
+    pub fn add_inputs(&mut self, inputs: &[i128]) {
+        self.inputs.extend(inputs);
+    }
+
+    pub fn input_len(&self) -> usize {
+        self.inputs.len() - self.curr_input
+    }
+
+    // Address the instruction at ip writes to, if it writes and doesn't fault
+    pub fn write_address(&self) -> Option<usize> {
+        let d = Decoded::new(*self.memory.get(self.ip).unwrap_or(&0)).ok()?;
+        let (_, reads, writes) = d.op.definition();
+        if writes == 0 {
+            return None;
+        }
+        self.param_address(&d, reads).ok()
+    }
+
+    // Goes back to an earlier copy of this machine, keeping the inputs added
+    // since so they are read again
+    pub(crate) fn restore(&mut self, snapshot: &Machine) {
+        let inputs = std::mem::take(&mut self.inputs);
+        *self = snapshot.clone();
+        self.inputs = inputs;
+    }
+
+    fn fault(&self, reason: FaultReason, address: Option<i128>) -> Fault {
+        Fault {
+            ip: self.ip,
+            opcode: *self.memory.get(self.ip).unwrap_or(&0),
+            reason,
+            address,
+        }
+    }
+
+    // The decoded opcode at address, cached until the address is written
+    fn decode(&mut self, address: usize) -> Result<Decoded, Fault> {
+        if let Some(Some(d)) = self.decoded.get(address) {
+            return Ok(*d);
+        }
+        let d = Decoded::new(*self.memory.get(address).unwrap_or(&0))
+            .map_err(|reason| self.fault(reason, None))?;
+        if address < self.decoded.len() {
+            self.decoded[address] = Some(d);
         }
+        Ok(d)
     }
 
-    fn write_arg(&mut self, arg: &Arg, value: i128) {
-        let address = match arg {
-            Arg::Position { address } => *address,
-            Arg::Relative { base, offset } => (base + offset) as usize,
-            _ => panic!("can't write immediate"),
+    // Address of parameter i of the current instruction, which must not be
+    // in immediate mode
+    #[inline]
+    fn param_address(&self, d: &Decoded, i: usize) -> Result<usize, Fault> {
+        let v = *self.memory.get(self.ip + 1 + i).unwrap_or(&0);
+        let address = match d.modes[i] {
+            0 => v,
+            _ => self.relative_base + v,
         };
-        if address >= self.memory.len() {
-            self.memory.resize(address + 1, 0);
+        if address < 0 {
+            return Err(self.fault(FaultReason::NegativeAddress, Some(address)));
         }
-        self.memory[address] = value;
+        Ok(address as usize)
     }
 
-    fn input(&mut self) -> Option<i128> {
-        if self.curr_input < self.inputs.len() {
-            let res = self.inputs[self.curr_input];
-            self.curr_input += 1;
-            Some(res)
+    #[inline]
+    fn load(&self, d: &Decoded, i: usize) -> Result<i128, Fault> {
+        if d.modes[i] == 1 {
+            Ok(*self.memory.get(self.ip + 1 + i).unwrap_or(&0))
         } else {
-            None
+            Ok(*self.memory.get(self.param_address(d, i)?).unwrap_or(&0))
         }
     }
 
-    pub fn add_input(&mut self, input: i128) {
-        self.inputs.push(input);
+    #[inline]
+    fn jump_target(&self, d: &Decoded) -> Result<usize, Fault> {
+        let target = self.load(d, 1)?;
+        if target < 0 {
+            return Err(self.fault(FaultReason::NegativeAddress, Some(target)));
+        }
+        Ok(target as usize)
     }
 
-    pub fn add_inputs(&mut self, inputs: &[i128]) {
-        self.inputs.extend(inputs);
+    #[inline]
+    fn store(&mut self, address: usize, value: i128) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1, 0);
+            self.decoded.resize(address + 1, None);
+        }
+        self.memory[address] = value;
+        self.decoded[address] = None;
+    }
+
+    // With the relative base from before the instruction
+    fn update_stats(&mut self, d: &Decoded) {
+        let (_, reads, writes) = d.op.definition();
+        for i in 0..(reads + writes) {
+            if d.modes[i] != 1 {
+                if let Ok(address) = self.param_address(d, i) {
+                    let map = if i < reads {
+                        &mut self.reads
+                    } else {
+                        &mut self.writes
+                    };
+                    *map.entry(address).or_insert(0) += 1;
+                }
+            }
+        }
+        *self.executes.entry(self.ip).or_insert(0) += 1;
     }
 
-    pub fn input_len(&self) -> usize {
-        self.inputs.len() - self.curr_input
+    pub fn step(&mut self) -> State {
+        match self.execute() {
+            Ok(state) => state,
+            Err(fault) => State::Fault(fault),
+        }
     }
 
-    pub fn step(&mut self) -> State {
-        match self.get_disassembly(self.ip) {
-            Disassembly::Instruction(x) => {
-                // println!("{}", x);
-                let mut next_pos = self.ip + x.increment();
-                let mut state = State::Running;
-                let v = match x.op {
-                    Op::ADD => Some(self.read_arg(&x.read()[0]) + self.read_arg(&x.read()[1])),
-                    Op::MUL => Some(self.read_arg(&x.read()[0]) * self.read_arg(&x.read()[1])),
-                    Op::INP => {
-                        if let Some(x) = self.input() {
-                            Some(x)
-                        } else {
-                            return State::Input;
-                        }
-                    }
-                    Op::OUT => {
-                        state = State::Output;
-                        self.outputs.push(self.read_arg(&x.read()[0]));
-                        // println!("OUT: {}", self.read_arg(x.read[0]));
-                        None
-                    }
-                    Op::JIT => {
-                        if self.read_arg(&x.read()[0]) != 0 {
-                            next_pos = self.read_arg(&x.read()[1]) as usize;
-                        }
-                        None
-                    }
-                    Op::JIF => {
-                        if self.read_arg(&x.read()[0]) == 0 {
-                            next_pos = self.read_arg(&x.read()[1]) as usize;
-                        }
-                        None
-                    }
-                    Op::LTN => Some(i128::from(
-                        self.read_arg(&x.read()[0]) < self.read_arg(&x.read()[1]),
-                    )),
-                    Op::EQL => Some(i128::from(
-                        self.read_arg(&x.read()[0]) == self.read_arg(&x.read()[1]),
-                    )),
-                    Op::SP => {
-                        self.relative_base += self.read_arg(&x.read()[0]);
-                        None
-                    }
-                    Op::HLT => {
-                        state = State::Halted;
-                        None
-                    }
-                };
-                if let Some(out) = v {
-                    assert!(x.op.definition().2 == 1);
-                    self.write_arg(&x.write()[0], out);
-                } else if x.op.definition().2 != 0 {
-                    println!("{:?}", x.op.definition());
-                    assert!(x.op.definition().2 == 0);
-                }
-                // Update stats
-                for r in x.read() {
-                    match r {
-                        Arg::Position { address } => {
-                            *self.reads.entry(*address).or_insert(0) += 1;
-                        }
-                        Arg::Relative { base, offset } => {
-                            *self.reads.entry((base + offset) as usize).or_insert(0) += 1;
-                        }
-                        _ => {}
-                    }
+    // Nothing is changed when the instruction faults
+    fn execute(&mut self) -> Result<State, Fault> {
+        let d = self.decode(self.ip)?;
+        if d.op == Op::INP && self.curr_input >= self.inputs.len() {
+            return Ok(State::Input);
+        }
+        let stats = self.stats.then_some(self.relative_base);
+        let mut next_pos = self.ip + d.len as usize;
+        let mut state = State::Running;
+        match d.op {
+            Op::ADD => {
+                let v = self.load(&d, 0)? + self.load(&d, 1)?;
+                self.store(self.param_address(&d, 2)?, v);
+            }
+            Op::MUL => {
+                let v = self.load(&d, 0)? * self.load(&d, 1)?;
+                self.store(self.param_address(&d, 2)?, v);
+            }
+            Op::INP => {
+                let address = self.param_address(&d, 0)?;
+                let v = self.inputs[self.curr_input];
+                self.curr_input += 1;
+                self.store(address, v);
+            }
+            Op::OUT => {
+                state = State::Output;
+                let v = self.load(&d, 0)?;
+                self.outputs.push(v);
+            }
+            Op::JIT => {
+                if self.load(&d, 0)? != 0 {
+                    next_pos = self.jump_target(&d)?;
                 }
-                for w in x.write() {
-                    match w {
-                        Arg::Position { address } => {
-                            *self.writes.entry(*address).or_insert(0) += 1;
-                        }
-                        Arg::Relative { base, offset } => {
-                            *self.writes.entry((base + offset) as usize).or_insert(0) += 1;
-                        }
-                        _ => {}
-                    }
+            }
+            Op::JIF => {
+                if self.load(&d, 0)? == 0 {
+                    next_pos = self.jump_target(&d)?;
                 }
-                *self.executes.entry(self.ip).or_insert(0) += 1;
-                // Update ip
-                self.ip = next_pos;
-                state
             }
-            Disassembly::MemoryValue(_) => State::Invalid,
+            Op::LTN => {
+                let v = i128::from(self.load(&d, 0)? < self.load(&d, 1)?);
+                self.store(self.param_address(&d, 2)?, v);
+            }
+            Op::EQL => {
+                let v = i128::from(self.load(&d, 0)? == self.load(&d, 1)?);
+                self.store(self.param_address(&d, 2)?, v);
+            }
+            Op::SP => {
+                self.relative_base += self.load(&d, 0)?;
+            }
+            Op::HLT => {
+                state = State::Halted;
+            }
+        }
+        if let Some(base) = stats {
+            let new_base = self.relative_base;
+            self.relative_base = base;
+            self.update_stats(&d);
+            self.relative_base = new_base;
         }
+        self.ip = next_pos;
+        Ok(state)
     }
 
+    // Values that can't be executed are shown as memory values
     pub fn get_disassembly(&self, address: usize) -> Disassembly {
         let val = *self.memory.get(address).unwrap_or(&0);
-        if let Some(op) = Op::from_i128(val) {
-            let def = op.definition();
-            let mut ins = Instruction::new(address, op);
-            for r in 0..def.1 {
-                let m = mode(val, 1 + r);
-                let v = self.memory.get(address + 1 + r).unwrap_or(&0);
-                match &m {
-                    Some(Mode::Immediate) => {
-                        ins.add_read(Arg::Immediate { value: *v });
-                    }
-                    Some(Mode::Position) => {
-                        ins.add_read(Arg::Position {
-                            address: *v as usize,
-                        });
-                    }
-                    Some(Mode::Relative) => {
-                        ins.add_read(Arg::Relative {
-                            base: self.relative_base,
-                            offset: *v,
-                        });
-                    }
-                    _ => (),
-                }
-            }
-            for w in 0..def.2 {
-                let m = mode(val, 1 + def.1 + w);
-                let v = self.memory.get(address + 1 + def.1 + w).unwrap_or(&0);
-                match &m {
-                    Some(Mode::Position) => {
-                        ins.add_write(Arg::Position {
-                            address: *v as usize,
-                        });
-                    }
-                    Some(Mode::Relative) => {
-                        ins.add_write(Arg::Relative {
+        match Decoded::new(val) {
+            Ok(d) => {
+                let (_, reads, writes) = d.op.definition();
+                let mut ins = Instruction::new(address, d.op);
+                for i in 0..(reads + writes) {
+                    let v = *self.memory.get(address + 1 + i).unwrap_or(&0);
+                    let arg = match d.modes[i] {
+                        0 => Arg::Position { address: v },
+                        1 => Arg::Immediate { value: v },
+                        _ => Arg::Relative {
                             base: self.relative_base,
-                            offset: *v,
-                        });
+                            offset: v,
+                        },
+                    };
+                    if i < reads {
+                        ins.add_read(arg);
+                    } else {
+                        ins.add_write(arg);
                     }
-                    _ => (),
                 }
+                Disassembly::Instruction(ins)
             }
-            Disassembly::Instruction(ins)
-        } else {
-            Disassembly::MemoryValue(MemoryValue {
+            Err(_) => Disassembly::MemoryValue(MemoryValue {
                 address,
                 value: val,
-            })
+            }),
         }
     }
 
+    // None when the machine halts or faults
     pub fn run_to_next_output(&mut self) -> Option<i128> {
         let res = loop {
             let cont = self.step();
@@ -436,7 +566,7 @@ impl Machine {
                 assert!(cont == State::Output);
                 break Some(*v);
             }
-            if cont == State::Halted {
+            if let State::Halted | State::Fault(_) = cont {
                 break None;
             }
         };
@@ -458,8 +588,7 @@ impl Machine {
         loop {
             let s = self.step();
             match s {
-                State::Halted => break s,
-                State::Input => break s,
+                State::Halted | State::Input | State::Fault(_) => break s,
                 _ => {}
             }
         }
@@ -469,16 +598,20 @@ impl Machine {
         loop {
             let s = self.step();
             match s {
-                State::Halted => break s,
-                State::Input => break s,
-                State::Output => break s,
+                State::Halted | State::Input | State::Output | State::Fault(_) => break s,
                 _ => {}
             }
         }
     }
 
-    pub fn run(&mut self) {
-        while self.step() != State::Halted {}
+    // Returns Halted or the fault
+    pub fn run(&mut self) -> State {
+        loop {
+            let s = self.step();
+            if let State::Halted | State::Fault(_) = s {
+                break s;
+            }
+        }
     }
 
     pub fn dump(&self, n: usize) {
@@ -508,7 +641,7 @@ impl Machine {
 
 #[cfg(test)]
 mod tests {
-    use super::Machine;
+    use super::{Arg, Fault, FaultReason, Machine, State};
 
     #[test]
     fn test() {
@@ -628,4 +761,118 @@ mod tests {
         m.run();
         assert_eq!(m.outputs[0], 1125899906842624);
     }
+
+    #[test]
+    fn test_read_relative_out_of_range() {
+        let m = Machine::new(&[204, -1, 99]);
+        // Neither may wrap around onto memory[0]
+        assert_eq!(
+            m.read_arg(&Arg::Relative {
+                base: 0,
+                offset: -1
+            }),
+            0
+        );
+        let huge = Arg::Relative {
+            base: 1 << 64,
+            offset: 0,
+        };
+        assert_eq!(m.read_arg(&huge), 0);
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // Overwrites the cached JIT at patch with HLT on the third round
+        let program = crate::assemble(
+            "\
+loop:   OUT [n]
+        ADD [n], 1 -> [n]
+        LTN [n], 3 -> [c]
+        JIT [c], patch
+        ADD 99, 0 -> [patch]
+patch:  JIT 1, loop
+        OUT 42
+n:      DATA 0
+c:      DATA 0
+",
+        )
+        .unwrap();
+        let mut m = Machine::new(&program);
+        let mut steps = 0;
+        while m.step() != State::Halted {
+            steps += 1;
+            assert!(steps < 100);
+        }
+        assert_eq!(m.outputs(), vec![0, 1, 2]);
+        m.memory_mut()[0] = 99;
+        let mut m2 = m.clone();
+        m2.ip = 0;
+        assert_eq!(m2.step(), State::Halted);
+        m2.ip = 1;
+        match m2.step() {
+            State::Fault(f) => assert_eq!((f.ip, f.reason), (1, FaultReason::UnknownOpcode)),
+            s => panic!("{:?}", s),
+        }
+    }
+
+    fn fault(program: &[i128]) -> Fault {
+        let mut m = Machine::new(program);
+        match m.run() {
+            State::Fault(f) => {
+                assert_eq!(m.ip(), f.ip);
+                assert_eq!(m.step(), State::Fault(f));
+                f
+            }
+            s => panic!("{:?}", s),
+        }
+    }
+
+    #[test]
+    fn test_faults() {
+        let f = fault(&[1101, 1, 2, 5, 77, 0]);
+        assert_eq!(
+            (f.ip, f.opcode, f.reason),
+            (4, 77, FaultReason::UnknownOpcode)
+        );
+        assert_eq!(fault(&[-1]).reason, FaultReason::UnknownOpcode);
+        assert_eq!(fault(&[301, 0, 0, 0]).reason, FaultReason::InvalidMode);
+        let f = fault(&[11101, 1, 2, 3]);
+        assert_eq!((f.opcode, f.reason), (11101, FaultReason::ImmediateWrite));
+        let f = fault(&[1, -1, 0, 0, 99]);
+        assert_eq!(
+            (f.reason, f.address),
+            (FaultReason::NegativeAddress, Some(-1))
+        );
+        let f = fault(&[109, 2, 204, -5, 99]);
+        assert_eq!((f.ip, f.address), (2, Some(-3)));
+        let f = fault(&[1105, 1, -5]);
+        assert_eq!(
+            (f.reason, f.address),
+            (FaultReason::NegativeAddress, Some(-5))
+        );
+        assert_eq!(
+            f.to_string(),
+            "negative address at 0 (opcode 1105), address -5"
+        );
+        // Nothing was written before the fault
+        let mut m = Machine::new(&[21101, 1, 2, -1, 99]);
+        m.set_stats(true);
+        assert!(matches!(m.run(), State::Fault(_)));
+        assert_eq!(m.memory(), &[21101, 1, 2, -1, 99]);
+        assert!(m.executes.is_empty());
+    }
+
+    #[test]
+    fn test_stats() {
+        let program = vec![1101, 1, 2, 5, 99, 0];
+        let mut m = Machine::new(&program);
+        m.run();
+        assert!(m.executes.is_empty() && m.writes.is_empty());
+        let mut m = Machine::new(&program);
+        m.set_stats(true);
+        m.run();
+        assert_eq!(m.executes.len(), 2);
+        assert_eq!(m.writes.get(&5), Some(&1));
+        assert_eq!(m.memory()[5], 3);
+    }
 }
@@ -0,0 +1,480 @@
+//! Several machines talking to each other
+//!
+//! A Network runs its nodes round-robin in the order they were added. Each
+//! machine runs until it waits for input it doesn't have, or halts, and its
+//! outputs are then sent on by its Route. Other nodes, like the Nat, only
+//! react to what is sent to them and to the network going idle. Everything
+//! sent is reported as an Event to the stop condition given to run.
+
+use crate::{Fault, Machine, State};
+use std::collections::{HashMap, VecDeque};
+
+pub type NodeId = usize;
+
+// From, to and the values
+type Message = (NodeId, Option<NodeId>, Vec<i128>);
+
+// Where the outputs of a machine go
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Route {
+    // Each value to the input of another node
+    Pipe(NodeId),
+    // Each value to all of the nodes
+    Broadcast(Vec<NodeId>),
+    // Packets of this many values, the first being the address
+    Packets(usize),
+    // Nowhere, the values are only reported
+    Out,
+}
+
+// Something sent, to is None when it left the network
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    pub round: usize,
+    pub from: NodeId,
+    pub to: Option<NodeId>,
+    pub values: Vec<i128>,
+}
+
+// How run ended
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetworkState {
+    // The stop condition returned true for this event
+    Stopped(Event),
+    // All machines have halted
+    Halted,
+    // Every machine waits for input and nothing woke the network up
+    Deadlocked,
+    // A machine faulted, it stays at the faulting instruction
+    Fault(NodeId, Fault),
+}
+
+pub trait Node {
+    // Values sent to this node, returns values to send on
+    fn receive(&mut self, from: NodeId, values: &[i128]) -> Vec<(NodeId, Vec<i128>)>;
+    // The network is idle, returns values to wake it up with
+    fn idle(&mut self) -> Vec<(NodeId, Vec<i128>)> {
+        vec![]
+    }
+}
+
+// Keeps the last packet sent to it and sends it to target when the
+// network is idle, like the NAT in 2019/23
+pub struct Nat {
+    target: NodeId,
+    last: Option<Vec<i128>>,
+}
+
+impl Nat {
+    pub fn new(target: NodeId) -> Nat {
+        Nat { target, last: None }
+    }
+}
+
+impl Node for Nat {
+    fn receive(&mut self, _from: NodeId, values: &[i128]) -> Vec<(NodeId, Vec<i128>)> {
+        self.last = Some(values.to_vec());
+        vec![]
+    }
+
+    fn idle(&mut self) -> Vec<(NodeId, Vec<i128>)> {
+        self.last
+            .iter()
+            .map(|values| (self.target, values.clone()))
+            .collect()
+    }
+}
+
+// Prints what is sent to it and passes it on unchanged, to put in the
+// middle of a pipe
+pub struct Monitor {
+    name: String,
+    forward: Option<NodeId>,
+}
+
+impl Monitor {
+    pub fn new(name: &str, forward: Option<NodeId>) -> Monitor {
+        Monitor {
+            name: name.to_string(),
+            forward,
+        }
+    }
+}
+
+impl Node for Monitor {
+    fn receive(&mut self, from: NodeId, values: &[i128]) -> Vec<(NodeId, Vec<i128>)> {
+        println!("{}: {} -> {:?}", self.name, from, values);
+        self.forward
+            .iter()
+            .map(|to| (*to, values.to_vec()))
+            .collect()
+    }
+}
+
+enum NodeKind {
+    Machine {
+        machine: Box<Machine>,
+        route: Route,
+        // Outputs of an unfinished packet
+        pending: Vec<i128>,
+        halted: bool,
+    },
+    Custom(Box<dyn Node>),
+}
+
+pub struct Network {
+    nodes: Vec<NodeKind>,
+    addresses: HashMap<i128, NodeId>,
+    empty_input: Option<i128>,
+    round: usize,
+    log: Option<Vec<Event>>,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Network::new()
+    }
+}
+
+impl Network {
+    pub fn new() -> Network {
+        Network {
+            nodes: vec![],
+            addresses: HashMap::new(),
+            empty_input: None,
+            round: 0,
+            log: None,
+        }
+    }
+
+    pub fn add_machine(&mut self, machine: Machine, route: Route) -> NodeId {
+        self.nodes.push(NodeKind::Machine {
+            machine: Box::new(machine),
+            route,
+            pending: vec![],
+            halted: false,
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn add_node(&mut self, node: Box<dyn Node>) -> NodeId {
+        self.nodes.push(NodeKind::Custom(node));
+        self.nodes.len() - 1
+    }
+
+    pub fn set_route(&mut self, id: NodeId, new_route: Route) {
+        if let NodeKind::Machine { route, .. } = &mut self.nodes[id] {
+            *route = new_route;
+        }
+    }
+
+    // Packets to address go to the node. Addresses without a node are
+    // taken as node ids.
+    pub fn set_address(&mut self, address: i128, id: NodeId) {
+        self.addresses.insert(address, id);
+    }
+
+    // Given to machines waiting for input when there is none, instead of
+    // waiting. The network is still idle when they all got it.
+    pub fn set_empty_input(&mut self, value: Option<i128>) {
+        self.empty_input = value;
+    }
+
+    // Keeps all events, see events
+    pub fn set_log(&mut self, log: bool) {
+        self.log = if log { Some(vec![]) } else { None };
+    }
+
+    pub fn events(&self) -> &[Event] {
+        self.log.as_deref().unwrap_or(&[])
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn machine(&self, id: NodeId) -> Option<&Machine> {
+        match self.nodes.get(id) {
+            Some(NodeKind::Machine { machine, .. }) => Some(machine),
+            _ => None,
+        }
+    }
+
+    pub fn machine_mut(&mut self, id: NodeId) -> Option<&mut Machine> {
+        match self.nodes.get_mut(id) {
+            Some(NodeKind::Machine { machine, .. }) => Some(machine),
+            _ => None,
+        }
+    }
+
+    // Input from outside the network, not reported as an event
+    pub fn send(&mut self, to: NodeId, values: &[i128]) {
+        if let Some(machine) = self.machine_mut(to) {
+            machine.add_inputs(values);
+        }
+    }
+
+    fn resolve(&self, address: i128) -> Option<NodeId> {
+        match self.addresses.get(&address) {
+            Some(id) => Some(*id),
+            None if address >= 0 && (address as usize) < self.nodes.len() => Some(address as usize),
+            None => None,
+        }
+    }
+
+    // Delivers values and everything sent on because of them. Returns the
+    // event the stop condition returned true for.
+    fn deliver(
+        &mut self,
+        messages: Vec<Message>,
+        stop: &mut dyn FnMut(&Event) -> bool,
+    ) -> Option<Event> {
+        let mut queue: VecDeque<_> = messages.into();
+        while let Some((from, to, values)) = queue.pop_front() {
+            match to.and_then(|to| self.nodes.get_mut(to)) {
+                Some(NodeKind::Machine {
+                    machine,
+                    halted: false,
+                    ..
+                }) => machine.add_inputs(&values),
+                Some(NodeKind::Custom(node)) => {
+                    let sent = node.receive(from, &values);
+                    queue.extend(sent.into_iter().map(|(t, v)| (to.unwrap(), Some(t), v)));
+                }
+                _ => {}
+            }
+            let event = Event {
+                round: self.round,
+                from,
+                to,
+                values,
+            };
+            if let Some(log) = &mut self.log {
+                log.push(event.clone());
+            }
+            if stop(&event) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    // Runs machine id until it waits for input, halts or faults, returns
+    // where its outputs go, whether it is waiting and the fault
+    fn turn(&mut self, id: NodeId) -> (Vec<Message>, bool, Option<Fault>) {
+        let empty_input = self.empty_input;
+        let NodeKind::Machine {
+            machine,
+            route,
+            pending,
+            halted,
+        } = &mut self.nodes[id]
+        else {
+            return (vec![], true, None);
+        };
+        if *halted {
+            return (vec![], true, None);
+        }
+        let mut outputs = vec![];
+        let mut fault = None;
+        let waiting = loop {
+            match machine.step() {
+                State::Running => {}
+                State::Output => outputs.extend(machine.outputs()),
+                State::Input => {
+                    if let Some(v) = empty_input {
+                        machine.add_input(v);
+                    }
+                    break true;
+                }
+                State::Halted => {
+                    *halted = true;
+                    break false;
+                }
+                State::Fault(f) => {
+                    fault = Some(f);
+                    break false;
+                }
+            }
+        };
+        let mut packets = vec![];
+        let mut sent = vec![];
+        for v in outputs {
+            match route {
+                Route::Pipe(to) => sent.push((id, Some(*to), vec![v])),
+                Route::Broadcast(to) => sent.extend(to.iter().map(|t| (id, Some(*t), vec![v]))),
+                Route::Packets(size) => {
+                    pending.push(v);
+                    if pending.len() == *size {
+                        packets.push(std::mem::take(pending));
+                    }
+                }
+                Route::Out => sent.push((id, None, vec![v])),
+            }
+        }
+        for packet in packets {
+            let to = self.resolve(packet[0]);
+            sent.push((id, to, packet[1..].to_vec()));
+        }
+        (sent, waiting, fault)
+    }
+
+    fn all_halted(&self) -> bool {
+        self.nodes.iter().all(|node| match node {
+            NodeKind::Machine { halted, .. } => *halted,
+            NodeKind::Custom(_) => true,
+        })
+    }
+
+    // Runs until stop returns true for an event, all machines halt, one
+    // faults, or the network deadlocks. With set_empty_input the machines are never
+    // deadlocked, so stop has to end it.
+    pub fn run<F>(&mut self, mut stop: F) -> NetworkState
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        loop {
+            let mut idle = true;
+            for id in 0..self.nodes.len() {
+                let (sent, waiting, fault) = self.turn(id);
+                idle &= sent.is_empty() && (waiting || self.all_halted());
+                if let Some(event) = self.deliver(sent, &mut stop) {
+                    return NetworkState::Stopped(event);
+                }
+                if let Some(fault) = fault {
+                    return NetworkState::Fault(id, fault);
+                }
+            }
+            self.round += 1;
+            if self.all_halted() {
+                return NetworkState::Halted;
+            }
+            if idle {
+                let mut woken = vec![];
+                for (id, node) in self.nodes.iter_mut().enumerate() {
+                    if let NodeKind::Custom(node) = node {
+                        woken.extend(node.idle().into_iter().map(|(t, v)| (id, Some(t), v)));
+                    }
+                }
+                if woken.is_empty() && self.empty_input.is_none() {
+                    return NetworkState::Deadlocked;
+                }
+                if let Some(event) = self.deliver(woken, &mut stop) {
+                    return NetworkState::Stopped(event);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // Reads a value and outputs it plus one, until it reads 0 which is
+    // passed on before halting
+    fn incrementer() -> Vec<i128> {
+        assemble(
+            "\
+loop:   INP [x]
+        JIF [x], end
+        ADD [x], 1 -> [x]
+        OUT [x]
+        JIT 1, loop
+end:    OUT 0
+        HLT
+x:      DATA 0
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pipes() {
+        let program = incrementer();
+        let mut network = Network::new();
+        let a = network.add_machine(Machine::new(&program), Route::Pipe(1));
+        let b = network.add_machine(Machine::new(&program), Route::Pipe(2));
+        let out = network.add_machine(Machine::new(&program), Route::Out);
+        network.set_log(true);
+        network.send(a, &[1, 10]);
+        assert_eq!(network.run(|_| false), NetworkState::Deadlocked);
+        let outputs: Vec<i128> = network
+            .events()
+            .iter()
+            .filter(|e| e.from == out)
+            .flat_map(|e| e.values.clone())
+            .collect();
+        assert_eq!(outputs, vec![4, 13]);
+        network.send(a, &[0]);
+        assert_eq!(network.run(|_| false), NetworkState::Halted);
+        assert_eq!(network.events().iter().filter(|e| e.from == b).count(), 3);
+        let last = network.events().last().unwrap();
+        assert_eq!(
+            (last.from, last.to, last.values.clone()),
+            (out, None, vec![0])
+        );
+    }
+
+    #[test]
+    fn test_fault() {
+        let program = incrementer();
+        let mut network = Network::new();
+        let a = network.add_machine(Machine::new(&program), Route::Pipe(1));
+        // Outputs what it reads, then runs into an unknown opcode
+        let bad = network.add_machine(Machine::new(&[3, 5, 4, 5, 77, 0]), Route::Out);
+        network.set_log(true);
+        network.send(a, &[1]);
+        match network.run(|_| false) {
+            NetworkState::Fault(id, f) => {
+                assert_eq!(id, bad);
+                assert_eq!(
+                    (f.ip, f.opcode, f.reason),
+                    (4, 77, crate::FaultReason::UnknownOpcode)
+                );
+            }
+            s => panic!("{:?}", s),
+        }
+        assert_eq!(network.events().last().unwrap().values, vec![2]);
+        assert!(matches!(network.run(|_| false), NetworkState::Fault(1, _)));
+    }
+
+    #[test]
+    fn test_packets_and_nat() {
+        // Sends (1, x, x) once to address 1 and then reads -1 forever
+        let program = assemble(
+            "\
+        INP [x]
+        OUT 1
+        OUT [x]
+        OUT [x]
+loop:   INP [y]
+        JIT 1, loop
+x:      DATA 0
+y:      DATA 0
+",
+        )
+        .unwrap();
+        let mut network = Network::new();
+        network.set_empty_input(Some(-1));
+        network.add_machine(Machine::with_input(&program, &[5]), Route::Packets(3));
+        network.add_machine(Machine::with_input(&program, &[7]), Route::Packets(3));
+        let nat = network.add_node(Box::new(Nat::new(0)));
+        network.set_address(1, nat);
+        let mut nat_sent = vec![];
+        let state = network.run(|e| {
+            if e.from == nat {
+                nat_sent.push(e.values.clone());
+            }
+            nat_sent.len() == 2
+        });
+        // Both packets went to the NAT, which sent the last one to 0 each
+        // time the network was idle
+        assert_eq!(nat_sent, vec![vec![7, 7], vec![7, 7]]);
+        match state {
+            NetworkState::Stopped(e) => assert_eq!((e.from, e.to), (nat, Some(0))),
+            _ => panic!("{:?}", state),
+        }
+    }
+}
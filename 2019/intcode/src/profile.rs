@@ -0,0 +1,225 @@
+//! Profiling reports from the counters a Machine keeps with set_stats
+//!
+//! Instructions are looked up in memory as it is when profiling, so self
+//! modifying code is reported as it ended up. Loops are backward jumps to an
+//! immediate address; their iterations are the executions of the jump target.
+
+use crate::machine::{Arg, Disassembly, Machine, Op};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Counter {
+    Executes,
+    Reads,
+    Writes,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub start: usize,
+    // Address of the jump back
+    pub end: usize,
+    pub iterations: usize,
+    // Instructions executed between start and end
+    pub executed: usize,
+}
+
+pub struct Profile<'a> {
+    machine: &'a Machine,
+}
+
+// Sorted with the highest count first, then by address
+fn top(counts: &HashMap<usize, usize>, n: usize) -> Vec<(usize, usize)> {
+    let mut v: Vec<_> = counts.iter().map(|(a, c)| (*a, *c)).collect();
+    v.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    v.truncate(n);
+    v
+}
+
+impl Profile<'_> {
+    pub fn new(machine: &Machine) -> Profile<'_> {
+        Profile { machine }
+    }
+
+    fn counts(&self, counter: Counter) -> &HashMap<usize, usize> {
+        match counter {
+            Counter::Executes => self.machine.executes(),
+            Counter::Reads => self.machine.reads(),
+            Counter::Writes => self.machine.writes(),
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.machine.executes().values().sum()
+    }
+
+    // Addresses with the highest counts
+    pub fn top(&self, counter: Counter, n: usize) -> Vec<(usize, usize)> {
+        top(self.counts(counter), n)
+    }
+
+    // Executions per instruction name, most used first
+    pub fn mix(&self) -> Vec<(&'static str, usize)> {
+        let mut mix: HashMap<&'static str, usize> = HashMap::new();
+        for (a, c) in self.machine.executes() {
+            let name = match self.machine.get_disassembly(*a) {
+                Disassembly::Instruction(ins) => ins.op.definition().0,
+                Disassembly::MemoryValue(_) => "???",
+            };
+            *mix.entry(name).or_insert(0) += c;
+        }
+        let mut mix: Vec<_> = mix.into_iter().collect();
+        mix.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        mix
+    }
+
+    // Loops that ran, with the most instructions executed first
+    pub fn loops(&self, n: usize) -> Vec<Loop> {
+        let executes = self.machine.executes();
+        let mut loops = vec![];
+        for end in executes.keys() {
+            if let Disassembly::Instruction(ins) = self.machine.get_disassembly(*end) {
+                if let (Op::JIT | Op::JIF, [_, Arg::Immediate { value }]) = (ins.op, ins.read()) {
+                    if *value >= 0 && *value as usize <= *end {
+                        let start = *value as usize;
+                        let executed = (start..=*end).filter_map(|a| executes.get(&a)).sum();
+                        loops.push(Loop {
+                            start,
+                            end: *end,
+                            iterations: *executes.get(&start).unwrap_or(&0),
+                            executed,
+                        });
+                    }
+                }
+            }
+        }
+        loops.sort_by(|a, b| b.executed.cmp(&a.executed).then(a.start.cmp(&b.start)));
+        loops.truncate(n);
+        loops
+    }
+
+    // Counts per address in rows of width, for aoc's bitmap drawers
+    pub fn heatmap(&self, counter: Counter, width: usize) -> Vec<Vec<usize>> {
+        let counts = self.counts(counter);
+        let len = self.machine.memory().len().max(1);
+        (0..len)
+            .step_by(width)
+            .map(|row| {
+                (row..row + width)
+                    .map(|a| *counts.get(&a).unwrap_or(&0))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The n top entries of everything
+    pub fn report(&self, n: usize) -> String {
+        let mut s = String::new();
+        let total = self.total();
+        writeln!(s, "Executed {} instructions", total).unwrap();
+        writeln!(s, "Instruction mix:").unwrap();
+        for (name, c) in self.mix() {
+            let percent = 100.0 * c as f64 / total.max(1) as f64;
+            writeln!(s, "  {:<4} {:>10} {:>5.1}%", name, c, percent).unwrap();
+        }
+        writeln!(s, "Hottest instructions:").unwrap();
+        for (a, c) in self.top(Counter::Executes, n) {
+            let ins = match self.machine.get_disassembly(a) {
+                Disassembly::Instruction(ins) => ins.to_string(),
+                Disassembly::MemoryValue(v) => v.to_string(),
+            };
+            writeln!(s, "  {:<40} {:>10}", ins, c).unwrap();
+        }
+        writeln!(s, "Hottest loops:").unwrap();
+        for l in self.loops(n) {
+            writeln!(
+                s,
+                "  {:04}..{:04} {:>10} iterations {:>10} executed",
+                l.start, l.end, l.iterations, l.executed
+            )
+            .unwrap();
+        }
+        for (title, counter) in [
+            ("Most read:", Counter::Reads),
+            ("Most written:", Counter::Writes),
+        ] {
+            writeln!(s, "{}", title).unwrap();
+            for (a, c) in self.top(counter, n) {
+                writeln!(s, "  {:04} {:>10}", a, c).unwrap();
+            }
+        }
+        s
+    }
+}
+
+// Dark for unused, then through red and yellow to white on a log scale
+pub fn heat_color(count: usize, max: usize) -> [u8; 3] {
+    if count == 0 {
+        return [32, 32, 32];
+    }
+    let t = ((count as f64).ln_1p() / (max.max(1) as f64).ln_1p()).min(1.0);
+    let channel = |from: f64| (((t * 3.0 - from).clamp(0.0, 1.0)) * 255.0) as u8;
+    [channel(0.0), channel(1.0), channel(2.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // Counts n down from 10, adding n to s each time
+    fn countdown() -> Vec<i128> {
+        assemble(
+            "\
+loop:   ADD [s], [n] -> [s]
+        ADD [n], -1 -> [n]
+        JIT [n], loop
+        OUT [s]
+        HLT
+n:      DATA 10
+s:      DATA 0
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_profile() {
+        let program = countdown();
+        let (n, s) = (program.len() - 2, program.len() - 1);
+        let mut m = Machine::new(&program);
+        m.set_stats(true);
+        m.run();
+        assert_eq!(m.outputs(), vec![55]);
+        let p = Profile::new(&m);
+        assert_eq!(p.total(), 32);
+        assert_eq!(
+            p.mix(),
+            vec![("ADD", 20), ("JIT", 10), ("HLT", 1), ("OUT", 1)]
+        );
+        assert_eq!(p.top(Counter::Executes, 2), vec![(0, 10), (4, 10)]);
+        assert_eq!(p.top(Counter::Writes, 1), vec![(n, 10)]);
+        assert_eq!(p.top(Counter::Reads, 2), vec![(n, 30), (s, 11)]);
+        assert_eq!(
+            p.loops(5),
+            vec![Loop {
+                start: 0,
+                end: 8,
+                iterations: 10,
+                executed: 30
+            }]
+        );
+        let report = p.report(3);
+        assert!(report.starts_with("Executed 32 instructions\n"));
+        assert!(report.contains("0000..0008"));
+
+        let heat = p.heatmap(Counter::Executes, 8);
+        assert_eq!(heat.len(), program.len().div_ceil(8));
+        assert_eq!(heat[0][0], 10);
+        assert_eq!(heat[1][3], 1);
+        assert_eq!(heat_color(0, 10), [32, 32, 32]);
+        assert_eq!(heat_color(10, 10), [255, 255, 255]);
+        assert_eq!(heat_color(1, 10)[2], 0);
+    }
+}
@@ -100,13 +100,16 @@ fn step(g: &Parsed, d: &[Vec3]) -> Parsed {
     newg
 }
 
-#[allow(dead_code)]
 fn part1(input: &Parsed) -> Answer {
     let d = dirs();
     let mut i = 0;
     let mut g = input.clone();
+    #[cfg(feature = "vis3d")]
+    let mut scenes = vec![];
     print_slices(&g);
     loop {
+        #[cfg(feature = "vis3d")]
+        scenes.push(scene(&g));
         let newg = step(&g, &d);
         println!("After {} cycle:", i);
         print_slices(&newg);
@@ -116,6 +119,8 @@ fn part1(input: &Parsed) -> Answer {
         i += 1;
         g = newg.clone();
     }
+    #[cfg(feature = "vis3d")]
+    draw(&scenes, 1);
     g.iter().filter(|(_p, v)| **v == '#').count()
 }
 
@@ -147,7 +152,6 @@ fn step4(g: &HashMap<Vec4, char>, d: &[Vec4]) -> HashMap<Vec4, char> {
     newg
 }
 
-#[allow(dead_code)]
 fn part2(input: &Parsed) -> Answer {
     let d = dirs4();
     let mut i = 0;
@@ -155,7 +159,11 @@ fn part2(input: &Parsed) -> Answer {
     for ([x, y, z], v) in input {
         g.insert([*x, *y, *z, 0], *v);
     }
+    #[cfg(feature = "vis3d")]
+    let mut scenes = vec![];
     loop {
+        #[cfg(feature = "vis3d")]
+        scenes.push(scene4(&g));
         let newg = step4(&g, &d);
         if i == 6 {
             break;
@@ -163,6 +171,8 @@ fn part2(input: &Parsed) -> Answer {
         i += 1;
         g = newg.clone();
     }
+    #[cfg(feature = "vis3d")]
+    draw(&scenes, 2);
     g.iter().filter(|(_p, v)| **v == '#').count()
 }
 
@@ -176,100 +186,39 @@ fn parse(lines: &[String]) -> Parsed {
     g
 }
 
-#[cfg(feature = "vis")]
-fn draw1(parsed: &Parsed) -> Answer {
-    let mut window = kiss3d::window::Window::new_with_size("Day 17", 1280, 720);
-
-    window.set_light(kiss3d::light::Light::StickToCamera);
-
-    let d = dirs();
-    let mut g = parsed.clone();
-    let mut cubes = vec![];
-    let eye = kiss3d::nalgebra::Point3::new(40.0f32, 15.0, 20.0);
-    let at = kiss3d::nalgebra::Point3::origin();
-    let mut camera = kiss3d::camera::ArcBall::new(eye, at);
-    let mut frame = 0;
-    while window.render_with_camera(&mut camera) {
-        if frame % 20 == 0 {
-            for mut c in cubes {
-                window.remove_node(&mut c);
-            }
-            let mut new_cubes = vec![];
-            for (p, v) in &g {
-                if *v == '#' {
-                    let mut c = window.add_cube(1.0, 1.0, 1.0);
-                    c.append_translation(&kiss3d::nalgebra::Translation3::new(
-                        p[0] as f32,
-                        p[1] as f32,
-                        p[2] as f32,
-                    ));
-                    c.set_color(0.0, 1.0, 0.0);
-                    new_cubes.push(c);
-                }
-            }
-            g = step(&g, &d);
-            cubes = new_cubes;
-        }
-        // rotate the arc-ball camera.
-        let curr_yaw = camera.yaw();
-        camera.set_yaw(curr_yaw + 0.05);
-        frame += 1;
-    }
-    0
+#[cfg(feature = "vis3d")]
+fn scene(g: &Parsed) -> aoc::VoxelScene {
+    let active: std::collections::HashSet<Vec3> = g
+        .iter()
+        .filter(|(_p, v)| **v == '#')
+        .map(|(p, _v)| *p)
+        .collect();
+    let mut scene = aoc::VoxelScene::from_set(&active, [0, 255, 0]);
+    scene.set_size(1280, 720);
+    scene
 }
 
-#[cfg(feature = "vis")]
-fn draw2(parsed: &Parsed) -> Answer {
-    let mut window = kiss3d::window::Window::new_with_size("Day 17", 1280, 720);
-
-    window.set_light(kiss3d::light::Light::StickToCamera);
-
-    let d = dirs4();
-    let mut g = HashMap::new();
-    for ([x, y, z], v) in parsed {
-        g.insert([*x, *y, *z, 0], *v);
-    }
-    let mut cubes = vec![];
-    let eye = kiss3d::nalgebra::Point3::new(40.0f32, 15.0, 20.0);
-    let at = kiss3d::nalgebra::Point3::origin();
-    let mut camera = kiss3d::camera::ArcBall::new(eye, at);
-    let mut frame = 0;
-    while window.render_with_camera(&mut camera) {
-        if frame % 20 == 0 {
-            for mut c in cubes {
-                window.remove_node(&mut c);
-            }
-            let mut new_cubes = vec![];
-            for (p, v) in &g {
-                if *v == '#' {
-                    let mut c = window.add_cube(1.0, 1.0, 1.0);
-                    c.append_translation(&kiss3d::nalgebra::Translation3::new(
-                        p[0] as f32,
-                        p[1] as f32,
-                        p[2] as f32,
-                    ));
-                    let col = p[3] as f32 / (frame / 20 + 1) as f32;
-                    c.set_color(0.0, col, 0.0);
-                    new_cubes.push(c);
-                }
-            }
-            g = step4(&g, &d);
-            cubes = new_cubes;
+// Seen along the w axis, brighter where more of the w slices are active
+#[cfg(feature = "vis3d")]
+fn scene4(g: &HashMap<Vec4, char>) -> aoc::VoxelScene {
+    let mut counts: HashMap<Vec3, usize> = HashMap::new();
+    for (p, v) in g {
+        if *v == '#' {
+            *counts.entry([p[0], p[1], p[2]]).or_insert(0) += 1;
         }
-        // rotate the arc-ball camera.
-        let curr_yaw = camera.yaw();
-        camera.set_yaw(curr_yaw + 0.05);
-        frame += 1;
     }
-    0
+    let max = counts.values().copied().max().unwrap_or(1);
+    let mut scene = aoc::VoxelScene::from_map(&counts, |n| [0, (80 + 175 * n / max) as u8, 0]);
+    scene.set_size(1280, 720);
+    scene
 }
 
-#[cfg(feature = "vis")]
-fn main() {
-    aoc::run_main(parse, draw1, draw2);
+#[cfg(feature = "vis3d")]
+fn draw(scenes: &[aoc::VoxelScene], part: usize) {
+    aoc::save_scenes(scenes, &format!("vis/17/part{}", part), 0.2);
+    aoc::show_scenes(scenes, "Day 17", 20);
 }
 
-#[cfg(not(feature = "vis"))]
 fn main() {
     aoc::run_main(parse, part1, part2);
 }
@@ -1,4 +1,4 @@
-use aoc::{Grid, GridDrawer, Point};
+use aoc::{Grid, Point};
 
 use std::{collections::HashMap, iter::*};
 
@@ -27,14 +27,12 @@ fn part1(manual: &Parsed) -> Answer {
     g.len() as Answer
 }
 
-fn part2(manual: &Parsed) -> Answer {
-    let mut d = aoc::PrintGridDrawer::new(|c| if c == '#' { '\u{2588}' } else { ' ' });
+fn part2(manual: &Parsed) -> String {
     let mut g = manual.grid.clone();
     for fold in &manual.folds {
         g = fold_grid(&g, *fold);
     }
-    d.draw(&g);
-    0
+    aoc::ocr::ocr(&g, |c| c == '#').unwrap_or_else(|e| panic!("could not read the code: {}", e))
 }
 
 fn parse(lines: &[String]) -> Parsed {
@@ -97,15 +95,40 @@ mod tests {
     #[test]
     fn test_part2() {
         let manual = parse(&example());
-        let mut d = aoc::PrintGridDrawer::new(|c| c);
         let mut g = manual.grid.clone();
-        d.draw(&g);
-        println!();
         for fold in &manual.folds {
             g = fold_grid(&g, *fold);
-            d.draw(&g);
-            println!();
         }
         assert_eq!(g.len(), 16);
     }
+
+    #[test]
+    fn test_part2_code() {
+        let code = [
+            "###..#..#.####",
+            "#..#.#..#....#",
+            "#..#.####...#.",
+            "###..#..#..#..",
+            "#.#..#..#.#...",
+            "#..#.#..#.####",
+        ];
+        // Some dots are mirrored below y=7, some also right of x=20
+        let mut lines = vec![];
+        for (y, row) in code.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    let (x, y) = match (x + y) % 3 {
+                        0 => (x, y),
+                        1 => (x, 14 - y),
+                        _ => (40 - x, 14 - y),
+                    };
+                    lines.push(format!("{},{}", x, y));
+                }
+            }
+        }
+        lines.push("".into());
+        lines.push("fold along y=7".into());
+        lines.push("fold along x=20".into());
+        assert_eq!(part2(&parse(&lines)), "RHZ");
+    }
 }
@@ -207,108 +207,46 @@ fn split(cbi: &Cuboid, cbj: &Cuboid) -> Vec<Cuboid> {
     merge(&fromi)
 }
 
-#[cfg(any(test, not(feature = "vis")))]
+#[cfg(any(test, not(feature = "vis3d")))]
 fn draw(_: &[Cuboid], _: &[Cuboid]) {}
 
-#[cfg(not(any(test, not(feature = "vis"))))]
-fn draw(cuboids_a: &[Cuboid], cuboids_b: &[Cuboid]) {
-    let mut window = kiss3d::window::Window::new_with_size("Day 22", 800, 1000);
-    window.set_light(kiss3d::light::Light::StickToCamera);
-    let eye = kiss3d::nalgebra::Point3::new(40.0f32, 0.0, 80.0);
-    let at = kiss3d::nalgebra::Point3::origin();
-    let mut camera = kiss3d::camera::ArcBall::new(eye, at);
-    println!("add cubes");
-    let mut ix = 0;
-    for cb in cuboids_a {
-        println!("a: {}", cb);
-        let cminx = cb.minx as f32 / 10000.0;
-        let cmaxx = (cb.maxx + 1) as f32 / 10000.0;
-        let cminy = cb.miny as f32 / 10000.0;
-        let cmaxy = (cb.maxy + 1) as f32 / 10000.0;
-        let cminz = cb.minz as f32 / 10000.0;
-        let cmaxz = (cb.maxz + 1) as f32 / 10000.0;
-        let mut c = window.add_cube(cmaxx - cminx, cmaxy - cminy, cmaxz - cminz);
-        c.append_translation(&kiss3d::nalgebra::Translation3::new(
-            cminx + (cmaxx - cminx) / 2.0,
-            cminy + (cmaxy - cminy) / 2.0 - 15.0,
-            cminz + (cmaxz - cminz) / 2.0,
-        ));
-        let colors1 = [
-            [0.0, 0.0, 1.0],
-            [0.0, 1.0, 1.0],
-            [1.0, 0.0, 1.0],
-            [1.0, 1.0, 1.0],
-        ];
-        let colors2 = [
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 1.0],
-            [1.0, 0.0, 1.0],
-            [1.0, 1.0, 0.0],
-        ];
-        if cb.state == State::On {
-            c.set_color(
-                colors1[ix % colors1.len()][0],
-                colors1[ix % colors1.len()][1],
-                colors1[ix % colors1.len()][2],
-            );
-        } else {
-            c.set_color(
-                colors2[ix % colors2.len()][0],
-                colors2[ix % colors2.len()][1],
-                colors2[ix % colors2.len()][2],
-            );
-        }
-    }
-    ix = 0;
-    for cb in cuboids_b {
-        println!("b: {}", cb);
-        let cminx = cb.minx as f32 / 10000.0;
-        let cmaxx = (cb.maxx + 1) as f32 / 10000.0;
-        let cminy = cb.miny as f32 / 10000.0;
-        let cmaxy = (cb.maxy + 1) as f32 / 10000.0;
-        let cminz = cb.minz as f32 / 10000.0;
-        let cmaxz = (cb.maxz + 1) as f32 / 10000.0;
-        let mut c = window.add_cube(cmaxx - cminx, cmaxy - cminy, cmaxz - cminz);
-        c.append_translation(&kiss3d::nalgebra::Translation3::new(
-            cminx + (cmaxx - cminx) / 2.0,
-            cminy + (cmaxy - cminy) / 2.0 + 15.0,
-            cminz + (cmaxz - cminz) / 2.0,
-        ));
-        let colors1 = [
-            [0.0, 0.0, 1.0],
-            [0.0, 1.0, 1.0],
-            [1.0, 0.0, 1.0],
-            [1.0, 1.0, 1.0],
-        ];
-        let colors2 = [
-            [0.0, 1.0, 0.0],
-            [0.0, 1.0, 1.0],
-            [1.0, 0.0, 1.0],
-            [1.0, 1.0, 0.0],
-        ];
-        if cb.state == State::On {
-            c.set_color(
-                colors1[ix % colors1.len()][0],
-                colors1[ix % colors1.len()][1],
-                colors1[ix % colors1.len()][2],
-            );
+// Voxels are this wide
+#[cfg(not(any(test, not(feature = "vis3d"))))]
+const VOXEL: i64 = 5000;
+
+// Adds the voxels whose centers are in the cuboids, moved dy voxels up
+#[cfg(not(any(test, not(feature = "vis3d"))))]
+fn add_cuboids(scene: &mut aoc::VoxelScene, cuboids: &[Cuboid], dy: i64) {
+    let on = [[0, 0, 255], [0, 255, 255], [255, 0, 255], [255, 255, 255]];
+    let off = [[0, 255, 0], [0, 255, 255], [255, 0, 255], [255, 255, 0]];
+    let cells = |min: i64, max: i64| -((-min).div_euclid(VOXEL))..=max.div_euclid(VOXEL);
+    for (ix, cb) in cuboids.iter().enumerate() {
+        let color = if cb.state == State::On {
+            on[ix % on.len()]
         } else {
-            c.set_color(
-                colors2[ix % colors2.len()][0],
-                colors2[ix % colors2.len()][1],
-                colors2[ix % colors2.len()][2],
-            );
+            off[ix % off.len()]
+        };
+        for x in cells(cb.minx, cb.maxx) {
+            for y in cells(cb.miny, cb.maxy) {
+                for z in cells(cb.minz, cb.maxz) {
+                    scene.insert([x, y + dy, z], color);
+                }
+            }
         }
-        ix += 1;
-    }
-    println!("start rendering");
-    while window.render_with_camera(&mut camera) {
-        // rotate the arc-ball camera.
-        let curr_yaw = camera.yaw();
-        camera.set_yaw(curr_yaw + 0.05);
     }
 }
 
+// The input cuboids below, the non-overlapping ones they are split into above
+#[cfg(not(any(test, not(feature = "vis3d"))))]
+fn draw(cuboids_a: &[Cuboid], cuboids_b: &[Cuboid]) {
+    let mut scene = aoc::VoxelScene::new();
+    scene.set_size(800, 1000);
+    add_cuboids(&mut scene, cuboids_a, -25);
+    add_cuboids(&mut scene, cuboids_b, 25);
+    scene.save_orbit("vis/22/part2", 120);
+    scene.show("Day 22");
+}
+
 #[allow(clippy::needless_range_loop)]
 fn solve(cuboids: &[ParsedItem], d: bool) -> Answer {
     // Split to non-overlapping cuboids
@@ -347,7 +285,7 @@ fn solve(cuboids: &[ParsedItem], d: bool) -> Answer {
 }
 
 fn part2(cuboids: &Parsed) -> Answer {
-    solve(cuboids, false)
+    solve(cuboids, cfg!(feature = "vis3d"))
 }
 
 fn parse(lines: &[String]) -> Parsed {
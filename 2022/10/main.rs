@@ -1,6 +1,6 @@
 use std::iter::*;
 
-use aoc::{Grid, GridDrawer};
+use aoc::Grid;
 
 #[derive(parse_display::Display, parse_display::FromStr, Debug, Clone, PartialEq, Eq, Hash)]
 #[display(style = "snake_case")]
@@ -14,7 +14,7 @@ type ParsedItem = Instruction;
 type Parsed = Vec<ParsedItem>;
 type Answer = i64;
 
-fn part1(data: &Parsed) -> Answer {
+fn run(data: &Parsed) -> (Answer, Vec<Vec<char>>) {
     let mut cycle = 1;
     let mut pc = 0;
     let mut ins_cycle = 0;
@@ -46,13 +46,16 @@ fn part1(data: &Parsed) -> Answer {
         }
         cycle += 1;
     }
-    let mut gd = aoc::PrintGridDrawer::new(|c| c);
-    gd.draw(&crt);
-    signal_strength
+    (signal_strength, crt)
+}
+
+fn part1(data: &Parsed) -> Answer {
+    run(data).0
 }
 
-fn part2(_: &Parsed) -> Answer {
-    0
+fn part2(data: &Parsed) -> String {
+    let (_, crt) = run(data);
+    aoc::ocr::ocr(&crt, |c| c == '#').unwrap_or_else(|e| panic!("could not read the CRT: {}", e))
 }
 
 fn parse(lines: &[String]) -> Parsed {
@@ -66,6 +69,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     fn example() -> Vec<String> {
         vec![
@@ -222,4 +226,58 @@ mod tests {
     fn test_part1() {
         assert_eq!(part1(&parse(&example())), 13140);
     }
+
+    // A program drawing screen, found by trying noop and every addx at each
+    // cycle, remembering the dead ends
+    fn program_for(screen: &[&str]) -> Vec<String> {
+        let lit: Vec<bool> = screen.concat().chars().map(|c| c == '#').collect();
+        let ok = |cycle: usize, x: i64| ((x - (cycle % 40) as i64).abs() <= 1) == lit[cycle];
+        fn search(
+            cycle: usize,
+            x: i64,
+            ok: &dyn Fn(usize, i64) -> bool,
+            dead: &mut HashSet<(usize, i64)>,
+            program: &mut Vec<String>,
+        ) -> bool {
+            if cycle == 240 {
+                return true;
+            }
+            if dead.contains(&(cycle, x)) || !ok(cycle, x) {
+                return false;
+            }
+            program.push("noop".into());
+            if search(cycle + 1, x, ok, dead, program) {
+                return true;
+            }
+            program.pop();
+            if cycle + 1 < 240 && ok(cycle + 1, x) {
+                for nx in -2..=41 {
+                    program.push(format!("addx {}", nx - x));
+                    if search(cycle + 2, nx, ok, dead, program) {
+                        return true;
+                    }
+                    program.pop();
+                }
+            }
+            dead.insert((cycle, x));
+            false
+        }
+        let mut program = vec![];
+        assert!(search(0, 1, &ok, &mut HashSet::new(), &mut program));
+        program
+    }
+
+    #[test]
+    fn test_part2() {
+        let screen = [
+            "###...##..####.####.#..#.#..#.###..#..#.",
+            "#..#.#..#....#.#....#..#.#..#.#..#.#.#..",
+            "#..#.#......#..###..####.#..#.#..#.##...",
+            "###..#.##..#...#....#..#.#..#.###..#.#..",
+            "#.#..#..#.#....#....#..#.#..#.#.#..#.#..",
+            "#..#..###.####.####.#..#..##..#..#.#..#.",
+        ];
+        let program = program_for(&screen);
+        assert_eq!(part2(&parse(&program)), "RGZEHURK");
+    }
 }
@@ -1,89 +1,33 @@
 use std::iter::*;
 
-#[cfg(feature = "vis")]
-use std::path::PathBuf;
-
 use aoc::Vec3;
 
 type ParsedItem = (Vec3, Vec3);
 type Parsed = Vec<ParsedItem>;
 
-#[cfg(feature = "vis")]
-struct Drawer {
-    window: kiss3d::window::Window,
-    camera: kiss3d::camera::ArcBall,
-    cubes: Vec<kiss3d::scene::SceneNode>,
-    data: Vec<Parsed>,
-}
-
-#[cfg(feature = "vis")]
-impl Drawer {
-    fn new(data: Vec<Parsed>) -> Self {
-        let mut window = kiss3d::window::Window::new_with_size("Day 22", 100, 1200);
-        window.set_light(kiss3d::light::Light::StickToCamera);
-        let eye = kiss3d::nalgebra::Point3::new(0.0, 400.0, 150.0);
-        let at = kiss3d::nalgebra::Point3::new(0.0, 0.0, 150.0);
-        // let at = kiss3d::nalgebra::Point3::origin();
-        let mut camera = kiss3d::camera::ArcBall::new(eye, at);
-        camera.set_up_axis(kiss3d::nalgebra::Vector3::new(0.0, 0.0, 1.0));
-        let cubes = vec![];
-        Drawer {
-            window,
-            camera,
-            cubes,
-            data,
-        }
-    }
-
-    fn draw(&mut self, ix: usize) {
-        for c in &mut self.cubes {
-            self.window.remove_node(c);
-        }
-        self.cubes.clear();
-        for (s, e) in &self.data[ix] {
-            let mut c = self.window.add_cube(
-                (e[1] - s[1] + 1) as f32,
-                (e[0] - s[0] + 1) as f32,
-                (e[2] - s[2] + 1) as f32,
-            );
-            c.set_color(1.0, 1.0, 0.0);
-            c.append_translation(&kiss3d::nalgebra::Translation3::new(
-                s[1] as f32,
-                s[0] as f32,
-                s[2] as f32,
-            ));
-            self.cubes.push(c);
-        }
-    }
-
-    fn render(&mut self) {
-        let mut frame = 0;
-        let mut ix = 0;
-        let png_path = PathBuf::from("vis/22/part1");
-        if let Some(parent) = png_path.parent() {
-            std::fs::create_dir_all(parent).expect("could not create folder");
-        }
-        while self.window.render_with_camera(&mut self.camera) {
-            println!("{}/{}", frame, self.data.len());
-            self.draw(ix);
-            // Save image
-            let filename = png_path.parent().unwrap().join(&format!(
-                "{}_{:06}.png",
-                png_path.file_name().unwrap().to_str().unwrap(),
-                frame
-            ));
-            let img = self.window.snap_image();
-            img.save(filename).unwrap();
-            frame += 1;
-            ix += 100;
-            if ix > self.data.len() {
-                break;
+// Every 100th state of the bricks falling, z pointing up
+#[cfg(feature = "vis3d")]
+fn draw(states: &[Parsed]) {
+    let scenes: Vec<aoc::VoxelScene> = states
+        .iter()
+        .step_by(100)
+        .map(|bricks| {
+            let mut scene = aoc::VoxelScene::new();
+            scene.set_size(200, 1200);
+            for (s, e) in bricks {
+                for x in s[0]..=e[0] {
+                    for y in s[1]..=e[1] {
+                        for z in s[2]..=e[2] {
+                            scene.insert([x, z, y], [255, 255, 0]);
+                        }
+                    }
+                }
             }
-            let curr_yaw = self.camera.yaw();
-            self.camera.set_yaw(curr_yaw + 0.05);
-            // dbg!(&data);
-        }
-    }
+            scene
+        })
+        .collect();
+    aoc::save_scenes(&scenes, "vis/22/part1", 0.05);
+    aoc::show_scenes(&scenes, "Day 22", 1);
 }
 
 #[allow(clippy::needless_range_loop)]
@@ -134,11 +78,11 @@ fn collapse(data: &Parsed, mut drawer: impl FnMut(&Parsed)) -> (Vec<(Vec3, Vec3)
 }
 
 fn part1(data: &Parsed) -> i64 {
-    #[cfg(feature = "vis")]
+    #[cfg(feature = "vis3d")]
     let mut vis = vec![];
-    #[cfg(feature = "vis")]
+    #[cfg(feature = "vis3d")]
     let (data, _) = collapse(data, |x| vis.push(x.clone()));
-    #[cfg(not(feature = "vis"))]
+    #[cfg(not(feature = "vis3d"))]
     let (data, _) = collapse(data, |_| {});
 
     let mut actually_safe = vec![];
@@ -157,11 +101,8 @@ fn part1(data: &Parsed) -> i64 {
         }
     }
 
-    #[cfg(feature = "vis")]
-    {
-        let mut d = Drawer::new(vis);
-        d.render();
-    }
+    #[cfg(feature = "vis3d")]
+    draw(&vis);
 
     actually_safe.len() as i64
 }
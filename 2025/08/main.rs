@@ -2,12 +2,6 @@ use std::{cmp::Reverse, collections::BinaryHeap, iter::*};
 
 use aoc::{FxHashMap, FxHashSet};
 
-#[cfg(feature = "vis")]
-use std::path::PathBuf;
-
-#[cfg(feature = "vis")]
-use aoc::Itertools;
-
 #[derive(
     parse_display::Display, parse_display::FromStr, Debug, Clone, Copy, PartialEq, Eq, Hash,
 )]
@@ -21,87 +15,33 @@ struct Junction {
 type ParsedItem = Junction;
 type Parsed = Vec<ParsedItem>;
 
-#[cfg(feature = "vis")]
-struct Drawer {
-    window: kiss3d::window::Window,
-    camera: kiss3d::camera::ArcBall,
-    spheres: Vec<kiss3d::scene::SceneNode>,
-    data: Parsed,
-}
-
-#[cfg(feature = "vis")]
-impl Drawer {
-    fn new(data: &[Junction]) -> Self {
-        let mut window = kiss3d::window::Window::new_with_size("Day 8", 800, 800);
-        window.set_light(kiss3d::light::Light::StickToCamera);
-        let eye = kiss3d::nalgebra::Point3::new(50.0, 300.0, 50.0);
-        let at = kiss3d::nalgebra::Point3::new(50.0, 50.0, 50.0);
-        let mut camera = kiss3d::camera::ArcBall::new(eye, at);
-        camera.set_up_axis(kiss3d::nalgebra::Vector3::new(0.0, 0.0, 1.0));
-        let mut spheres = vec![];
-        for e in data {
-            let mut c = window.add_sphere(1.0);
-            c.set_color(1.0, 0.0, 0.0);
-            c.append_translation(&kiss3d::nalgebra::Translation3::new(
-                e.x as f32 / 1000.0,
-                e.y as f32 / 1000.0,
-                e.z as f32 / 1000.0,
-            ));
-            spheres.push(c);
+// The junctions in red and the connections made so far in green, every 20
+// connections, in voxels 1000 units wide
+#[cfg(feature = "vis3d")]
+fn draw(data: &[Junction], connections: &[(usize, usize)]) {
+    let voxel = |j: Junction| [j.x / 1000, j.z / 1000, j.y / 1000];
+    let mut scenes = vec![];
+    let mut scene = aoc::VoxelScene::new();
+    scene.set_size(800, 800);
+    for (n, (i, j)) in connections.iter().enumerate() {
+        let (a, b) = (voxel(data[*i]), voxel(data[*j]));
+        let steps = (0..3).map(|k| (b[k] - a[k]).abs()).max().unwrap().max(1);
+        for t in 0..=steps {
+            scene.insert(
+                std::array::from_fn(|k| a[k] + (b[k] - a[k]) * t / steps),
+                [0, 255, 0],
+            );
         }
-        Drawer {
-            window,
-            camera,
-            spheres,
-            data: data.to_vec(),
-        }
-    }
-
-    fn draw(&mut self, chains: &[FxHashSet<usize>], ix: usize) {
-        for c in chains.iter().take(ix) {
-            for (i, j) in c.iter().tuple_windows() {
-                self.window.draw_line(
-                    &kiss3d::nalgebra::Point3::new(
-                        self.data[*i].x as f32 / 1000.0,
-                        self.data[*i].y as f32 / 1000.0,
-                        self.data[*i].z as f32 / 1000.0,
-                    ),
-                    &kiss3d::nalgebra::Point3::new(
-                        self.data[*j].x as f32 / 1000.0,
-                        self.data[*j].y as f32 / 1000.0,
-                        self.data[*j].z as f32 / 1000.0,
-                    ),
-                    &kiss3d::nalgebra::Point3::new(0.0, 1.0, 0.0),
-                );
+        if n % 20 == 0 || n == connections.len() - 1 {
+            let mut frame = scene.clone();
+            for j in data {
+                frame.insert(voxel(*j), [255, 0, 0]);
             }
+            scenes.push(frame);
         }
     }
-
-    fn render(&mut self, chains: Vec<FxHashSet<usize>>) {
-        let png_path = PathBuf::from("vis/08/part2");
-        if let Some(parent) = png_path.parent() {
-            std::fs::create_dir_all(parent).expect("could not create folder");
-        }
-        let mut frame = 0;
-        while futures::executor::block_on(self.window.render_with_camera(&mut self.camera)) {
-            println!("{}/{}", frame, chains.len());
-            self.draw(&chains, frame);
-            // Save image
-            let filename = png_path.parent().unwrap().join(&format!(
-                "{}_{:06}.png",
-                png_path.file_name().unwrap().to_str().unwrap(),
-                frame
-            ));
-            let img = self.window.snap_image();
-            img.save(filename).unwrap();
-            frame += 1;
-            if frame >= chains.len() {
-                break;
-            }
-            let curr_yaw = self.camera.yaw();
-            self.camera.set_yaw(curr_yaw + 0.01);
-        }
-    }
+    aoc::save_scenes(&scenes, "vis/08/part2", 0.01);
+    aoc::show_scenes(&scenes, "Day 8", 1);
 }
 
 fn dist(n: Junction, goal: Junction) -> i64 {
@@ -166,8 +106,8 @@ fn part1(data: &Parsed) -> i64 {
 fn part2(data: &Parsed) -> i64 {
     let mut connections: FxHashMap<usize, FxHashSet<usize>> = FxHashMap::default();
     let mut dd = distances(data);
-    #[cfg(feature = "vis")]
-    let mut chains = vec![];
+    #[cfg(feature = "vis3d")]
+    let mut made = vec![];
     loop {
         if let Some(Reverse((_d, i, j))) = dd.pop() {
             if connections.entry(i).or_default().contains(&j) {
@@ -175,6 +115,8 @@ fn part2(data: &Parsed) -> i64 {
             }
             connections.entry(i).or_default().insert(j);
             connections.entry(j).or_default().insert(i);
+            #[cfg(feature = "vis3d")]
+            made.push((i, j));
             let mut seen: FxHashSet<usize> = FxHashSet::default();
             let mut todo = vec![i];
             while let Some(x) = todo.pop() {
@@ -188,15 +130,9 @@ fn part2(data: &Parsed) -> i64 {
                     seen.insert(*k);
                 }
             }
-            #[cfg(feature = "vis")]
-            chains.push(seen.clone());
-
             if seen.len() == data.len() {
-                #[cfg(feature = "vis")]
-                {
-                    let mut d = Drawer::new(data);
-                    d.render(chains);
-                }
+                #[cfg(feature = "vis3d")]
+                draw(data, &made);
                 return data[i].x * data[j].x;
             }
         }
@@ -190,6 +190,25 @@ fn dijkstra_grid_weighted_directed() {
     gd.draw(&grid);
 }
 
+fn astar_grid_char_visualized() {
+    let grid = aoc::parse_grid(&make_grid());
+    let a: aoc::Point = [1, 1];
+    let f: aoc::Point = [1, 7];
+    let mut vis =
+        aoc::SearchVisualizer::new(aoc::PrintGridDrawer::new(|x| x), &grid, '?', 'o', '*');
+    vis.set_every(10);
+    if let Some((cost, _path)) = aoc::astar_grid_observed(
+        &grid,
+        |_p, c| *c == '.',
+        |_p1, _c1, _p2, _c2| Some(1),
+        a,
+        f,
+        &mut vis,
+    ) {
+        println!("cost: {} after {} expansions", cost, vis.expansions());
+    }
+}
+
 fn main() {
     println!("A* graph:");
     astar_graph_char();
@@ -209,4 +228,6 @@ fn main() {
     astar_grid_weighted_directed();
     println!("Dijkstra weighted directed");
     dijkstra_grid_weighted_directed();
+    println!("A* grid visualized");
+    astar_grid_char_visualized();
 }
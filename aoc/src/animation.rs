@@ -0,0 +1,510 @@
+//! Writes frames from the bitmap drawers straight into an animated GIF or APNG
+//!
+//! This replaces saving numbered PNGs and running mkgif.sh/ffmpeg on them.
+//! Errors, also from creating the folder, are kept until finish, which
+//! returns the first one.
+
+use image::RgbImage;
+use rustc_hash::FxHashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+pub struct Animation {
+    format: AnimationFormat,
+    path: PathBuf,
+    delay_ms: u32,
+    repeat: Option<u16>,
+    palette: Option<Vec<[u8; 3]>>,
+    skip: usize,
+    dedup: bool,
+    size: Option<(u32, u32)>,
+    // Frames seen, including skipped ones
+    seen: usize,
+    // Frames written
+    written: usize,
+    // The last frame is held back so duplicates can be merged into it
+    pending: Option<(RgbImage, u32)>,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+    // APNG needs the frame count up front, so frames are kept until finish
+    apng_frames: Vec<(RgbImage, u32)>,
+    // The first error, frames after it are dropped
+    error: Option<io::Error>,
+    finished: bool,
+}
+
+fn gif_error(e: gif::EncodingError) -> io::Error {
+    match e {
+        gif::EncodingError::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+impl Animation {
+    // The format is picked from the extension, .png and .apng give APNG,
+    // anything else GIF
+    pub fn new(filename: &str) -> Animation {
+        let path = Path::new(filename);
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("png") | Some("apng") => AnimationFormat::Apng,
+            _ => AnimationFormat::Gif,
+        };
+        Animation::with_format(filename, format)
+    }
+
+    pub fn with_format(filename: &str, format: AnimationFormat) -> Animation {
+        let path = Path::new(filename);
+        let error = path.parent().and_then(|p| std::fs::create_dir_all(p).err());
+        Animation {
+            format,
+            path: path.into(),
+            delay_ms: 40,
+            repeat: None,
+            palette: None,
+            skip: 1,
+            dedup: false,
+            size: None,
+            seen: 0,
+            written: 0,
+            pending: None,
+            gif: None,
+            apng_frames: vec![],
+            error,
+            finished: false,
+        }
+    }
+
+    // Delay per frame, default is 40 ms (25 fps)
+    pub fn set_delay(&mut self, delay_ms: u32) {
+        self.delay_ms = delay_ms;
+    }
+
+    // How many times to play the animation, None (the default) loops forever.
+    // It is played at least once, Some(0) is the same as Some(1).
+    pub fn set_repeat(&mut self, repeat: Option<u16>) {
+        self.repeat = repeat.map(|n| n.max(1));
+    }
+
+    // Fixed colors for the GIF, pixels get the closest one. Without a palette
+    // each frame gets its own, quantized if there are more than 256 colors.
+    pub fn set_palette(&mut self, palette: &[[u8; 3]]) {
+        assert!(!palette.is_empty() && palette.len() <= 256);
+        self.palette = Some(palette.to_vec());
+    }
+
+    // Only keep every nth frame
+    pub fn set_skip(&mut self, n: usize) {
+        self.skip = n.max(1);
+    }
+
+    // Merge identical consecutive frames into one with a longer delay
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.dedup = dedup;
+    }
+
+    pub fn frames(&self) -> usize {
+        self.written
+    }
+
+    // Frames are cropped or padded (with white) to the size of the first one,
+    // use set_rect on the drawer to get a stable view
+    pub fn add_frame(&mut self, image: &RgbImage) {
+        assert!(!self.finished, "animation already finished");
+        self.seen += 1;
+        if !(self.seen - 1).is_multiple_of(self.skip) {
+            return;
+        }
+        let (w, h) = *self.size.get_or_insert(image.dimensions());
+        let image = if image.dimensions() == (w, h) {
+            image.clone()
+        } else {
+            let mut canvas = RgbImage::from_pixel(w, h, image::Rgb([255, 255, 255]));
+            image::imageops::replace(&mut canvas, image, 0, 0);
+            canvas
+        };
+        if let Some((last, delay)) = &mut self.pending {
+            if self.dedup && *last == image {
+                *delay += self.delay_ms;
+                return;
+            }
+        }
+        if let Some((last, delay)) = self.pending.take() {
+            self.write_frame(last, delay);
+        }
+        self.pending = Some((image, self.delay_ms));
+    }
+
+    fn write_frame(&mut self, image: RgbImage, delay_ms: u32) {
+        if self.error.is_some() {
+            return;
+        }
+        self.written += 1;
+        match self.format {
+            AnimationFormat::Gif => {
+                if let Err(e) = self.write_gif_frame(&image, delay_ms) {
+                    self.error = Some(e);
+                }
+            }
+            AnimationFormat::Apng => self.apng_frames.push((image, delay_ms)),
+        }
+    }
+
+    fn write_gif_frame(&mut self, image: &RgbImage, delay_ms: u32) -> io::Result<()> {
+        let (w, h) = image.dimensions();
+        let w = u16::try_from(w).map_err(|_| io::Error::other("too wide for gif"))?;
+        let h = u16::try_from(h).map_err(|_| io::Error::other("too high for gif"))?;
+        if self.gif.is_none() {
+            let file = File::create(&self.path)?;
+            let global: Vec<u8> = self.palette.iter().flatten().flatten().copied().collect();
+            let mut encoder =
+                gif::Encoder::new(BufWriter::new(file), w, h, &global).map_err(gif_error)?;
+            let repeat = match self.repeat {
+                None => gif::Repeat::Infinite,
+                Some(n) => gif::Repeat::Finite(n.saturating_sub(1)),
+            };
+            encoder.set_repeat(repeat).map_err(gif_error)?;
+            self.gif = Some(encoder);
+        }
+        let mut frame = if let Some(palette) = &self.palette {
+            let mut cache = FxHashMap::default();
+            let pixels: Vec<u8> = image
+                .pixels()
+                .map(|p| *cache.entry(p.0).or_insert_with(|| closest(palette, p.0)))
+                .collect();
+            gif::Frame::from_indexed_pixels(w, h, pixels, None)
+        } else {
+            let mut colors: FxHashMap<[u8; 3], u8> = FxHashMap::default();
+            let mut exact = true;
+            for p in image.pixels() {
+                if !colors.contains_key(&p.0) {
+                    if colors.len() == 256 {
+                        exact = false;
+                        break;
+                    }
+                    colors.insert(p.0, colors.len() as u8);
+                }
+            }
+            if exact {
+                // Exact local palette
+                let mut palette = vec![0; 3 * colors.len()];
+                for (c, ix) in &colors {
+                    let ix = *ix as usize * 3;
+                    palette[ix..ix + 3].copy_from_slice(c);
+                }
+                let pixels: Vec<u8> = image.pixels().map(|p| colors[&p.0]).collect();
+                gif::Frame::from_palette_pixels(w, h, pixels, palette, None)
+            } else {
+                gif::Frame::from_rgb_speed(w, h, image.as_raw(), 10)
+            }
+        };
+        // gif delays are in hundredths of a second
+        frame.delay = (delay_ms / 10).clamp(1, u16::MAX as u32) as u16;
+        self.gif
+            .as_mut()
+            .unwrap()
+            .write_frame(&frame)
+            .map_err(gif_error)
+    }
+
+    fn write_apng(&mut self) -> io::Result<()> {
+        let Some((w, h)) = self.size else {
+            return Ok(());
+        };
+        let file = File::create(&self.path)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), w, h);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(
+            self.apng_frames.len() as u32,
+            self.repeat.unwrap_or(0) as u32,
+        )?;
+        let mut writer = encoder.write_header()?;
+        for (image, delay_ms) in self.apng_frames.drain(..) {
+            writer.set_frame_delay(delay_ms.min(u16::MAX as u32) as u16, 1000)?;
+            writer.write_image_data(image.as_raw())?;
+        }
+        writer.finish()?;
+        Ok(())
+    }
+
+    // Writes everything out and returns the first error. Also done when the
+    // animation is dropped, which can only print the error.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if let Some((last, delay)) = self.pending.take() {
+            self.write_frame(last, delay);
+        }
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        match self.format {
+            AnimationFormat::Gif => match self.gif.take() {
+                Some(encoder) => encoder.into_inner().map_err(gif_error)?.flush(),
+                None => Ok(()),
+            },
+            AnimationFormat::Apng => self.write_apng(),
+        }
+    }
+}
+
+impl Drop for Animation {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            eprintln!("could not write {:?}: {}", self.path, e);
+        }
+    }
+}
+
+// Where the drawers put their frames: numbered files next to basename, or
+// for bitmaps the animation when one is set
+pub(crate) struct FrameSink {
+    basename: String,
+    extension: &'static str,
+    frame: usize,
+    animation: Option<Animation>,
+    // Creating the folder failed, saving frames will fail too
+    error: Option<io::Error>,
+}
+
+impl FrameSink {
+    pub fn new(basename: &str) -> FrameSink {
+        FrameSink::with_extension(basename, "png")
+    }
+
+    pub fn with_extension(basename: &str, extension: &'static str) -> FrameSink {
+        let path = Path::new(basename);
+        let error = path.parent().and_then(|p| std::fs::create_dir_all(p).err());
+        FrameSink {
+            basename: basename.into(),
+            extension,
+            frame: 0,
+            animation: None,
+            error,
+        }
+    }
+
+    // Numbering starts at 1, the drawers count a frame per draw_grid
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.animation = Some(animation);
+    }
+
+    pub fn take_animation(&mut self) -> Option<Animation> {
+        self.animation.take()
+    }
+
+    // basename_000001.png etc
+    pub fn filename(&self) -> PathBuf {
+        let path = Path::new(&self.basename);
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => parent.join(format!(
+                "{}_{:06}.{}",
+                name.to_str().unwrap(),
+                self.frame,
+                self.extension
+            )),
+            _ => PathBuf::from(format!(
+                "{}_{:06}.{}",
+                self.basename, self.frame, self.extension
+            )),
+        }
+    }
+
+    pub fn save_image(&self, image: &RgbImage) {
+        image.save(self.filename()).unwrap();
+    }
+
+    // Saves an already encoded frame, like an SVG
+    pub fn save_data(&self, data: &[u8]) {
+        std::fs::write(self.filename(), data).unwrap();
+    }
+
+    // Saves as a PNG or as the next animation frame
+    pub fn save_frame(&mut self, image: &RgbImage) {
+        match &mut self.animation {
+            Some(animation) => animation.add_frame(image),
+            None => self.save_image(image),
+        }
+    }
+
+    // The error creating the folder, or the animation's first error
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        match &mut self.animation {
+            Some(animation) => animation.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn closest(palette: &[[u8; 3]], c: [u8; 3]) -> u8 {
+    let dist = |p: &[u8; 3]| {
+        (0..3)
+            .map(|i| (p[i] as i32 - c[i] as i32).pow(2))
+            .sum::<i32>()
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| dist(p))
+        .unwrap()
+        .0 as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(v: u8) -> RgbImage {
+        RgbImage::from_fn(4, 3, |x, y| image::Rgb([v, x as u8 * 50, y as u8 * 50]))
+    }
+
+    fn tmp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("aoc_animation_{}_{}", std::process::id(), name))
+            .to_str()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_gif() {
+        let path = tmp_path("test.gif");
+        let mut anim = Animation::new(&path);
+        anim.set_dedup(true);
+        anim.set_skip(2);
+        for v in [0, 9, 0, 9, 0, 9, 100, 9, 200] {
+            anim.add_frame(&frame(v));
+        }
+        anim.finish().unwrap();
+        // Skipping leaves 0, 0, 0, 100, 200 and dedup merges the zeroes
+        assert_eq!(anim.frames(), 3);
+        let decoder = gif::DecodeOptions::new();
+        let mut decoder = decoder.read_info(File::open(&path).unwrap()).unwrap();
+        let mut delays = vec![];
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, vec![12, 4, 4]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_gif_palette() {
+        let path = tmp_path("palette.gif");
+        {
+            let mut anim = Animation::new(&path);
+            anim.set_palette(&[[0, 0, 0], [255, 255, 255]]);
+            anim.add_frame(&frame(0));
+            anim.add_frame(&RgbImage::new(2, 2));
+        }
+        let img = image::open(&path).unwrap().to_rgb8();
+        assert_eq!(img.dimensions(), (4, 3));
+        assert_eq!(img.get_pixel(3, 2).0, [0, 0, 0]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_error() {
+        // A folder where the file should go
+        let path = tmp_path("folder.gif");
+        std::fs::create_dir_all(&path).unwrap();
+        let mut anim = Animation::new(&path);
+        anim.add_frame(&frame(0));
+        anim.add_frame(&frame(1));
+        assert!(anim.finish().is_err());
+        assert!(anim.finish().is_ok());
+        std::fs::remove_dir(&path).unwrap();
+    }
+
+    #[test]
+    fn test_folder_error() {
+        // A file where the folder should go
+        let file = tmp_path("not_a_folder");
+        std::fs::write(&file, "").unwrap();
+        let path = format!("{}/anim.gif", file);
+        let mut anim = Animation::new(&path);
+        anim.add_frame(&frame(0));
+        assert!(anim.finish().is_err());
+        assert!(FrameSink::new(&format!("{}/frame", file)).finish().is_err());
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_frame_sink() {
+        let base = tmp_path("sink/frame");
+        let mut sink = FrameSink::new(&base);
+        sink.next_frame();
+        let filename = sink.filename();
+        assert!(filename.ends_with(format!(
+            "{}_000001.png",
+            Path::new(&base).file_name().unwrap().to_str().unwrap()
+        )));
+        sink.save_frame(&frame(0));
+        assert!(filename.exists());
+        let svg = FrameSink::with_extension(&base, "svg");
+        assert_eq!(svg.filename().extension().unwrap(), "svg");
+        assert!(svg.filename().to_str().unwrap().ends_with("_000000.svg"));
+        let gif = tmp_path("sink.gif");
+        sink.set_animation(Animation::new(&gif));
+        sink.next_frame();
+        sink.save_frame(&frame(0));
+        assert!(!sink.filename().exists());
+        sink.finish().unwrap();
+        assert!(Path::new(&gif).exists());
+        std::fs::remove_dir_all(Path::new(&base).parent().unwrap()).unwrap();
+        std::fs::remove_file(&gif).unwrap();
+    }
+
+    #[test]
+    fn test_repeat_once() {
+        let gif_path = tmp_path("once.gif");
+        let png_path = tmp_path("once.png");
+        for path in [&gif_path, &png_path] {
+            let mut anim = Animation::new(path);
+            anim.set_repeat(Some(0));
+            anim.add_frame(&frame(0));
+            anim.add_frame(&frame(1));
+            anim.finish().unwrap();
+        }
+        let decoder = gif::DecodeOptions::new();
+        let decoder = decoder.read_info(File::open(&gif_path).unwrap()).unwrap();
+        assert_eq!(decoder.repeat(), gif::Repeat::Finite(0));
+        let decoder = png::Decoder::new(std::io::BufReader::new(File::open(&png_path).unwrap()));
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().animation_control.unwrap().num_plays, 1);
+        std::fs::remove_file(&gif_path).unwrap();
+        std::fs::remove_file(&png_path).unwrap();
+    }
+
+    #[test]
+    fn test_apng() {
+        let path = tmp_path("test.png");
+        let mut anim = Animation::new(&path);
+        anim.set_repeat(Some(1));
+        for v in [0, 10, 20] {
+            anim.add_frame(&frame(v));
+        }
+        anim.finish().unwrap();
+        let decoder = png::Decoder::new(std::io::BufReader::new(File::open(&path).unwrap()));
+        let reader = decoder.read_info().unwrap();
+        let actl = reader.info().animation_control.unwrap();
+        assert_eq!(actl.num_frames, 3);
+        assert_eq!(actl.num_plays, 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+//! A terminal drawer using ANSI escape codes with 24-bit colors
+//!
+//! Only cells that changed since the previous frame are written, so large
+//! grids can be animated without flicker and without curses. The terminal is
+//! restored (colors reset, cursor shown below the grid) when the drawer is
+//! finished or dropped.
+
+use crate::{Grid, GridDrawer, Point};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+// The char and its color, None is the terminal default
+type Cell = (char, Option<[u8; 3]>);
+
+pub struct AnsiGridDrawer<F, T, W = io::Stdout>
+where
+    F: Fn(T) -> (char, [u8; 3]),
+    W: Write,
+{
+    to_cell: F,
+    out: W,
+    missing: char,
+    color_bg: bool,
+    rect: Option<(Point, Point)>,
+    // Extents of what is on screen, a change forces a full redraw
+    extents: Option<(Point, Point)>,
+    // None means unknown, i.e. must be written
+    screen: Vec<Option<Cell>>,
+    started: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<F, T> AnsiGridDrawer<F, T>
+where
+    F: Fn(T) -> (char, [u8; 3]),
+{
+    pub fn new(to_cell: F) -> AnsiGridDrawer<F, T> {
+        AnsiGridDrawer::with_writer(to_cell, io::stdout())
+    }
+}
+
+impl<F, T, W> AnsiGridDrawer<F, T, W>
+where
+    F: Fn(T) -> (char, [u8; 3]),
+    W: Write,
+{
+    pub fn with_writer(to_cell: F, out: W) -> AnsiGridDrawer<F, T, W> {
+        AnsiGridDrawer {
+            to_cell,
+            out,
+            missing: ' ',
+            color_bg: false,
+            rect: None,
+            extents: None,
+            screen: vec![],
+            started: false,
+            phantom: PhantomData,
+        }
+    }
+
+    // Shown for cells without a value, default is ' '
+    pub fn set_missing(&mut self, missing: char) {
+        self.missing = missing;
+    }
+
+    // Use the color as background instead of foreground, e.g. for heightmaps
+    pub fn set_color_bg(&mut self, color_bg: bool) {
+        self.color_bg = color_bg;
+    }
+
+    pub fn set_rect(&mut self, r: (Point, Point)) {
+        self.rect = Some(r);
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    // Forget what is on screen so the next frame is drawn in full
+    pub fn invalidate(&mut self) {
+        self.extents = None;
+    }
+
+    fn color_code(&self, color: Option<[u8; 3]>) -> String {
+        match color {
+            Some([r, g, b]) if self.color_bg => format!("\x1b[0;48;2;{};{};{}m", r, g, b),
+            Some([r, g, b]) => format!("\x1b[0;38;2;{};{};{}m", r, g, b),
+            None => "\x1b[0m".into(),
+        }
+    }
+
+    pub fn draw_grid<G: Grid<T>>(&mut self, area: &G)
+    where
+        T: PartialEq + Copy,
+    {
+        let ([min_x, min_y], [max_x, max_y]) = self.rect.unwrap_or_else(|| area.extents());
+        let width = (max_x - min_x + 1).max(0) as usize;
+        let height = (max_y - min_y + 1).max(0) as usize;
+        let mut s = String::new();
+        if !self.started {
+            // Hide the cursor
+            s.push_str("\x1b[?25l");
+            self.started = true;
+        }
+        if self.extents != Some(([min_x, min_y], [max_x, max_y])) {
+            s.push_str("\x1b[0m\x1b[2J");
+            self.extents = Some(([min_x, min_y], [max_x, max_y]));
+            self.screen = vec![None; width * height];
+        }
+        let mut cursor = None;
+        let mut color = None;
+        for (row, y) in (min_y..=max_y).enumerate() {
+            for (col, x) in (min_x..=max_x).enumerate() {
+                let cell = match area.get_value([x, y]) {
+                    Some(v) => {
+                        let (ch, rgb) = (self.to_cell)(v);
+                        (ch, Some(rgb))
+                    }
+                    None => (self.missing, None),
+                };
+                let ix = row * width + col;
+                if self.screen[ix] == Some(cell) {
+                    continue;
+                }
+                if cursor != Some((row, col)) {
+                    write!(s, "\x1b[{};{}H", row + 1, col + 1).unwrap();
+                }
+                if color != Some(cell.1) {
+                    s.push_str(&self.color_code(cell.1));
+                    color = Some(cell.1);
+                }
+                s.push(cell.0);
+                cursor = Some((row, col + 1));
+                self.screen[ix] = Some(cell);
+            }
+        }
+        if color.is_some_and(|c| c.is_some()) {
+            s.push_str("\x1b[0m");
+        }
+        // TODO: error handling
+        self.out.write_all(s.as_bytes()).unwrap();
+        self.out.flush().unwrap();
+    }
+
+    // Restores the terminal, leaving the cursor below the grid. Drawing again
+    // afterwards starts over with a full frame.
+    pub fn finish(&mut self) {
+        if !self.started {
+            return;
+        }
+        let rows = match self.extents {
+            Some(([_, min_y], [_, max_y])) => (max_y - min_y + 1).max(0),
+            None => 0,
+        };
+        // Errors are ignored here since this also runs on drop
+        let _ = write!(self.out, "\x1b[0m\x1b[{};1H\x1b[?25h", rows + 1);
+        let _ = self.out.flush();
+        self.started = false;
+        self.extents = None;
+    }
+}
+
+impl<F, T, W> Drop for AnsiGridDrawer<F, T, W>
+where
+    F: Fn(T) -> (char, [u8; 3]),
+    W: Write,
+{
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl<F, G, T, W> GridDrawer<G, T> for AnsiGridDrawer<F, T, W>
+where
+    F: Fn(T) -> (char, [u8; 3]),
+    G: Grid<T>,
+    T: PartialEq + Copy,
+    W: Write,
+{
+    fn draw(&mut self, area: &G) {
+        self.draw_grid(area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FxHashMap, Grid};
+
+    fn to_cell(v: u8) -> (char, [u8; 3]) {
+        if v == 0 {
+            ('.', [50, 50, 50])
+        } else {
+            ('#', [255, 0, 0])
+        }
+    }
+
+    #[test]
+    fn test_ansi_diff() {
+        let mut grid = vec![vec![0u8; 3]; 2];
+        let mut gd = AnsiGridDrawer::with_writer(to_cell, vec![]);
+        gd.draw(&grid);
+        let first = String::from_utf8(gd.get_ref().clone()).unwrap();
+        assert_eq!(
+            first,
+            "\x1b[?25l\x1b[0m\x1b[2J\x1b[1;1H\x1b[0;38;2;50;50;50m...\x1b[2;1H...\x1b[0m"
+        );
+        gd.get_mut().clear();
+        grid.set_value([2, 1], 1);
+        grid.set_value([0, 1], 1);
+        gd.draw(&grid);
+        let second = String::from_utf8(gd.get_ref().clone()).unwrap();
+        assert_eq!(second, "\x1b[2;1H\x1b[0;38;2;255;0;0m#\x1b[2;3H#\x1b[0m");
+        gd.get_mut().clear();
+        gd.draw(&grid);
+        assert!(gd.get_ref().is_empty());
+        gd.finish();
+        assert_eq!(
+            String::from_utf8(gd.get_ref().clone()).unwrap(),
+            "\x1b[0m\x1b[3;1H\x1b[?25h"
+        );
+    }
+
+    #[test]
+    fn test_ansi_missing_and_resize() {
+        let mut grid: FxHashMap<Point, u8> = FxHashMap::default();
+        grid.insert([0, 0], 1);
+        grid.insert([2, 0], 0);
+        let mut gd = AnsiGridDrawer::with_writer(to_cell, vec![]);
+        gd.set_missing('?');
+        gd.set_color_bg(true);
+        gd.draw(&grid);
+        let out = String::from_utf8(gd.get_ref().clone()).unwrap();
+        assert!(
+            out.ends_with("\x1b[1;1H\x1b[0;48;2;255;0;0m#\x1b[0m?\x1b[0;48;2;50;50;50m.\x1b[0m")
+        );
+        // Growing the grid redraws everything
+        gd.get_mut().clear();
+        grid.insert([0, 1], 0);
+        gd.draw(&grid);
+        let out = String::from_utf8(gd.get_ref().clone()).unwrap();
+        assert!(out.starts_with("\x1b[0m\x1b[2J"));
+    }
+}
@@ -0,0 +1,389 @@
+//! A circular doubly linked list stored in a Vec
+//!
+//! Every element gets a handle (its index in the backing Vec) when inserted.
+//! Handles stay valid until the element is removed and are never reused, so
+//! they can be kept in side tables, e.g. cup label -> handle.
+
+#[derive(Debug, Clone)]
+struct Node<T> {
+    value: Option<T>,
+    prev: usize,
+    next: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct CircularList<T> {
+    nodes: Vec<Node<T>>,
+    head: Option<usize>,
+    len: usize,
+}
+
+impl<T> Default for CircularList<T> {
+    fn default() -> Self {
+        CircularList {
+            nodes: vec![],
+            head: None,
+            len: 0,
+        }
+    }
+}
+
+impl<T> FromIterator<T> for CircularList<T> {
+    // The handles are 0..n in iteration order
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = Self::new();
+        for v in iter {
+            list.push(v);
+        }
+        list
+    }
+}
+
+impl<T> CircularList<T> {
+    pub fn new() -> CircularList<T> {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> CircularList<T> {
+        CircularList {
+            nodes: Vec::with_capacity(capacity),
+            head: None,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // The element iteration and rotation starts from
+    pub fn head(&self) -> Option<usize> {
+        self.head
+    }
+
+    pub fn set_head(&mut self, h: usize) {
+        assert!(self.contains(h));
+        self.head = Some(h);
+    }
+
+    pub fn contains(&self, h: usize) -> bool {
+        self.nodes.get(h).is_some_and(|n| n.value.is_some())
+    }
+
+    pub fn get(&self, h: usize) -> Option<&T> {
+        self.nodes.get(h).and_then(|n| n.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, h: usize) -> Option<&mut T> {
+        self.nodes.get_mut(h).and_then(|n| n.value.as_mut())
+    }
+
+    pub fn next(&self, h: usize) -> usize {
+        self.nodes[h].next
+    }
+
+    pub fn prev(&self, h: usize) -> usize {
+        self.nodes[h].prev
+    }
+
+    // The handle k steps after h (before if k is negative), wrapping around
+    pub fn offset(&self, h: usize, k: i64) -> usize {
+        assert!(self.contains(h));
+        let len = self.len as i64;
+        let mut steps = k.rem_euclid(len);
+        // Walk the shorter way around
+        if steps > len / 2 {
+            steps -= len;
+        }
+        let mut curr = h;
+        if steps >= 0 {
+            for _ in 0..steps {
+                curr = self.nodes[curr].next;
+            }
+        } else {
+            for _ in steps..0 {
+                curr = self.nodes[curr].prev;
+            }
+        }
+        curr
+    }
+
+    fn alloc(&mut self, value: T) -> usize {
+        let h = self.nodes.len();
+        self.nodes.push(Node {
+            value: Some(value),
+            prev: h,
+            next: h,
+        });
+        self.len += 1;
+        h
+    }
+
+    // Links the (unlinked) node h in after a
+    fn link_after(&mut self, a: usize, h: usize) {
+        let b = self.nodes[a].next;
+        self.nodes[h].prev = a;
+        self.nodes[h].next = b;
+        self.nodes[a].next = h;
+        self.nodes[b].prev = h;
+    }
+
+    fn unlink(&mut self, h: usize) {
+        let Node { prev, next, .. } = self.nodes[h];
+        self.nodes[prev].next = next;
+        self.nodes[next].prev = prev;
+        self.nodes[h].prev = h;
+        self.nodes[h].next = h;
+        if self.head == Some(h) {
+            self.head = if next == h { None } else { Some(next) };
+        }
+    }
+
+    // Appends at the end, i.e. just before head
+    pub fn push(&mut self, value: T) -> usize {
+        match self.head {
+            Some(head) => self.insert_before(head, value),
+            None => {
+                let h = self.alloc(value);
+                self.head = Some(h);
+                h
+            }
+        }
+    }
+
+    pub fn insert_after(&mut self, a: usize, value: T) -> usize {
+        assert!(self.contains(a));
+        let h = self.alloc(value);
+        self.link_after(a, h);
+        h
+    }
+
+    pub fn insert_before(&mut self, b: usize, value: T) -> usize {
+        let a = self.prev(b);
+        self.insert_after(a, value)
+    }
+
+    pub fn remove(&mut self, h: usize) -> T {
+        assert!(self.contains(h));
+        self.unlink(h);
+        self.len -= 1;
+        self.nodes[h].value.take().unwrap()
+    }
+
+    // Moves head k steps forward (backward if k is negative)
+    pub fn rotate(&mut self, k: i64) {
+        if let Some(head) = self.head {
+            self.head = Some(self.offset(head, k));
+        }
+    }
+
+    // Moves h k steps forward among the other elements, i.e. wrapping
+    // around at len - 1. Moving the head keeps it as head.
+    pub fn move_by(&mut self, h: usize, k: i64) {
+        assert!(self.contains(h));
+        let others = self.len as i64 - 1;
+        if others < 1 {
+            return;
+        }
+        let steps = k.rem_euclid(others);
+        if steps == 0 {
+            return;
+        }
+        let was_head = self.head == Some(h);
+        let mut dest = self.prev(h);
+        self.unlink(h);
+        self.len -= 1;
+        dest = self.offset(dest, steps);
+        self.len += 1;
+        self.link_after(dest, h);
+        if was_head {
+            self.head = Some(h);
+        }
+    }
+
+    // Takes the count elements starting at first out and puts them back
+    // after dest, keeping their order. dest can't be one of the moved ones.
+    pub fn splice_after(&mut self, dest: usize, first: usize, count: usize) {
+        assert!(self.contains(dest) && self.contains(first));
+        if count == 0 {
+            return;
+        }
+        let mut last = first;
+        for _ in 1..count {
+            assert!(last != dest, "splice destination inside moved range");
+            last = self.nodes[last].next;
+        }
+        assert!(last != dest, "splice destination inside moved range");
+        let before = self.nodes[first].prev;
+        let after = self.nodes[last].next;
+        if before == dest {
+            return;
+        }
+        if let Some(head) = self.head {
+            let mut curr = first;
+            for _ in 0..count {
+                if curr == head {
+                    self.head = Some(after);
+                    break;
+                }
+                curr = self.nodes[curr].next;
+            }
+        }
+        // Cut the run out
+        self.nodes[before].next = after;
+        self.nodes[after].prev = before;
+        // And put it back
+        let dest_next = self.nodes[dest].next;
+        self.nodes[dest].next = first;
+        self.nodes[first].prev = dest;
+        self.nodes[last].next = dest_next;
+        self.nodes[dest_next].prev = last;
+    }
+
+    // Handles once around the list, starting at h
+    pub fn handles_from(&self, h: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut curr = h;
+        let mut left = if self.contains(h) { self.len } else { 0 };
+        std::iter::from_fn(move || {
+            if left == 0 {
+                return None;
+            }
+            left -= 1;
+            let ret = curr;
+            curr = self.nodes[curr].next;
+            Some(ret)
+        })
+    }
+
+    // Values once around the list, starting at h
+    pub fn iter_from(&self, h: usize) -> impl Iterator<Item = &T> + '_ {
+        self.handles_from(h)
+            .map(|h| self.nodes[h].value.as_ref().unwrap())
+    }
+
+    // Values once around the list, starting at head
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.head
+            .into_iter()
+            .flat_map(move |head| self.iter_from(head))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marbles(players: usize, last: usize) -> usize {
+        let mut scores = vec![0; players];
+        let mut circle = CircularList::with_capacity(last + 1);
+        let mut curr = circle.push(0);
+        for marble in 1..=last {
+            if marble % 23 == 0 {
+                let r = circle.offset(curr, -7);
+                curr = circle.next(r);
+                scores[marble % players] += marble + circle.remove(r);
+            } else {
+                curr = circle.insert_after(circle.next(curr), marble);
+            }
+        }
+        scores.into_iter().max().unwrap()
+    }
+
+    #[test]
+    fn test_marbles() {
+        assert_eq!(marbles(9, 25), 32);
+        assert_eq!(marbles(10, 1618), 8317);
+        assert_eq!(marbles(13, 7999), 146373);
+    }
+
+    #[test]
+    fn test_cups() {
+        let labels = [3, 8, 9, 1, 2, 5, 4, 6, 7];
+        // Handle is label - 1
+        let mut cups: CircularList<usize> = (1..=9).collect();
+        for w in labels.windows(2) {
+            cups.splice_after(w[0] - 1, w[1] - 1, 1);
+        }
+        cups.set_head(labels[0] - 1);
+        assert_eq!(cups.iter().copied().collect::<Vec<_>>(), labels);
+        let mut curr = labels[0] - 1;
+        for _ in 0..10 {
+            let picked: Vec<usize> = cups.handles_from(cups.next(curr)).take(3).collect();
+            let mut dest = curr;
+            loop {
+                dest = (dest + 8) % 9;
+                if !picked.contains(&dest) {
+                    break;
+                }
+            }
+            cups.splice_after(dest, picked[0], 3);
+            curr = cups.next(curr);
+        }
+        let after_one: String = cups
+            .iter_from(0)
+            .skip(1)
+            .map(|c| char::from_digit(*c as u32, 10).unwrap())
+            .collect();
+        assert_eq!(after_one, "92658374");
+    }
+
+    #[test]
+    fn test_mixing() {
+        // 2022/20
+        let numbers = [1i64, 2, -3, 3, -2, 0, 4];
+        let mut list: CircularList<i64> = numbers.iter().copied().collect();
+        for (h, n) in numbers.iter().enumerate() {
+            list.move_by(h, *n);
+        }
+        let zero = 5;
+        let after: Vec<i64> = [1000, 2000, 3000]
+            .iter()
+            .map(|k| *list.get(list.offset(zero, *k)).unwrap())
+            .collect();
+        assert_eq!(after, vec![4, -3, 2]);
+    }
+
+    #[test]
+    fn test_rotate_remove() {
+        let mut list: CircularList<char> = "abcde".chars().collect();
+        list.rotate(-1);
+        assert_eq!(list.iter().collect::<String>(), "eabcd");
+        list.rotate(7);
+        assert_eq!(list.iter().collect::<String>(), "bcdea");
+        assert_eq!(list.remove(1), 'b');
+        assert!(!list.contains(1));
+        assert_eq!(list.iter().collect::<String>(), "cdea");
+        assert_eq!(list.iter_from(4).collect::<String>(), "eacd");
+        list.move_by(2, -1);
+        assert_eq!(list.iter().collect::<String>(), "cade");
+        assert_eq!(list.len(), 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_move_removed() {
+        let mut list: CircularList<char> = "abc".chars().collect();
+        list.remove(1);
+        list.move_by(1, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_splice_removed() {
+        let mut list: CircularList<char> = "abcd".chars().collect();
+        list.remove(3);
+        list.splice_after(3, 0, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_offset_empty() {
+        let mut list: CircularList<char> = "a".chars().collect();
+        list.remove(0);
+        list.offset(0, 3);
+    }
+}
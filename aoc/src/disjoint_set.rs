@@ -0,0 +1,307 @@
+//! Union-find with path compression and union by size
+
+use rustc_hash::FxHashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone, Default)]
+pub struct DisjointSet {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
+
+impl DisjointSet {
+    // n singleton sets, 0..n
+    pub fn new(n: usize) -> DisjointSet {
+        DisjointSet {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            count: n,
+        }
+    }
+
+    // Adds a new singleton set and returns its element
+    pub fn add(&mut self) -> usize {
+        let ix = self.parent.len();
+        self.parent.push(ix);
+        self.size.push(1);
+        self.count += 1;
+        ix
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    // Number of disjoint sets
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        // Point everything on the way directly to the root
+        let mut curr = x;
+        while self.parent[curr] != root {
+            let next = self.parent[curr];
+            self.parent[curr] = root;
+            curr = next;
+        }
+        root
+    }
+
+    // Returns false if a and b already were in the same set
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let mut ra = self.find(a);
+        let mut rb = self.find(b);
+        if ra == rb {
+            return false;
+        }
+        if self.size[ra] < self.size[rb] {
+            std::mem::swap(&mut ra, &mut rb);
+        }
+        self.parent[rb] = ra;
+        self.size[ra] += self.size[rb];
+        self.count -= 1;
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    // Size of the set containing x
+    pub fn size(&mut self, x: usize) -> usize {
+        let r = self.find(x);
+        self.size[r]
+    }
+
+    // All elements in the same set as x
+    pub fn members(&mut self, x: usize) -> Vec<usize> {
+        let r = self.find(x);
+        (0..self.len()).filter(|i| self.find(*i) == r).collect()
+    }
+
+    // All sets, each sorted, ordered by their smallest element
+    pub fn sets(&mut self) -> Vec<Vec<usize>> {
+        let mut root_ix = FxHashMap::default();
+        let mut sets: Vec<Vec<usize>> = vec![];
+        for i in 0..self.len() {
+            let r = self.find(i);
+            let ix = *root_ix.entry(r).or_insert_with(|| {
+                sets.push(vec![]);
+                sets.len() - 1
+            });
+            sets[ix].push(i);
+        }
+        sets
+    }
+
+    // Sizes of all sets, largest first
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = (0..self.len())
+            .filter(|i| self.parent[*i] == *i)
+            .map(|i| self.size[i])
+            .collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        sizes
+    }
+}
+
+// A DisjointSet over any hashable values, e.g. Point or Vec3. Values are added
+// on first use, also by the queries find, same, size and members: asking about
+// an unknown value adds it as a new singleton set. Use contains to check first.
+#[derive(Debug, Clone)]
+pub struct KeyedDisjointSet<K>
+where
+    K: Hash + Eq + Copy,
+{
+    set: DisjointSet,
+    index: FxHashMap<K, usize>,
+    keys: Vec<K>,
+}
+
+impl<K> Default for KeyedDisjointSet<K>
+where
+    K: Hash + Eq + Copy,
+{
+    fn default() -> Self {
+        KeyedDisjointSet {
+            set: DisjointSet::default(),
+            index: FxHashMap::default(),
+            keys: vec![],
+        }
+    }
+}
+
+impl<K> FromIterator<K> for KeyedDisjointSet<K>
+where
+    K: Hash + Eq + Copy,
+{
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for k in iter {
+            set.insert(k);
+        }
+        set
+    }
+}
+
+impl<K> KeyedDisjointSet<K>
+where
+    K: Hash + Eq + Copy,
+{
+    pub fn new() -> KeyedDisjointSet<K> {
+        Self::default()
+    }
+
+    // Adds k as a singleton set unless it's already there
+    pub fn insert(&mut self, k: K) -> usize {
+        if let Some(ix) = self.index.get(&k) {
+            return *ix;
+        }
+        let ix = self.set.add();
+        self.index.insert(k, ix);
+        self.keys.push(k);
+        ix
+    }
+
+    pub fn contains(&self, k: &K) -> bool {
+        self.index.contains_key(k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn count(&self) -> usize {
+        self.set.count()
+    }
+
+    // The representative of the set containing k
+    pub fn find(&mut self, k: K) -> K {
+        let ix = self.insert(k);
+        let r = self.set.find(ix);
+        self.keys[r]
+    }
+
+    pub fn union(&mut self, a: K, b: K) -> bool {
+        let a = self.insert(a);
+        let b = self.insert(b);
+        self.set.union(a, b)
+    }
+
+    pub fn same(&mut self, a: K, b: K) -> bool {
+        let a = self.insert(a);
+        let b = self.insert(b);
+        self.set.same(a, b)
+    }
+
+    pub fn size(&mut self, k: K) -> usize {
+        let ix = self.insert(k);
+        self.set.size(ix)
+    }
+
+    pub fn members(&mut self, k: K) -> Vec<K> {
+        let ix = self.insert(k);
+        self.set
+            .members(ix)
+            .into_iter()
+            .map(|i| self.keys[i])
+            .collect()
+    }
+
+    // All sets, in insertion order
+    pub fn sets(&mut self) -> Vec<Vec<K>> {
+        self.set
+            .sets()
+            .into_iter()
+            .map(|s| s.into_iter().map(|i| self.keys[i]).collect())
+            .collect()
+    }
+
+    pub fn sizes(&self) -> Vec<usize> {
+        self.set.sizes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{manhattan_vec4, Point, Vec4};
+
+    #[test]
+    fn test_disjoint_set() {
+        let mut ds = DisjointSet::new(6);
+        assert_eq!(ds.count(), 6);
+        assert!(ds.union(0, 1));
+        assert!(ds.union(2, 3));
+        assert!(ds.union(1, 3));
+        assert!(!ds.union(0, 2));
+        assert_eq!(ds.count(), 3);
+        assert!(ds.same(0, 3));
+        assert!(!ds.same(0, 4));
+        assert_eq!(ds.size(2), 4);
+        assert_eq!(ds.members(4), vec![4]);
+        assert_eq!(ds.sets(), vec![vec![0, 1, 2, 3], vec![4], vec![5]]);
+        assert_eq!(ds.sizes(), vec![4, 1, 1]);
+        let ix = ds.add();
+        assert_eq!(ix, 6);
+        assert_eq!(ds.count(), 4);
+    }
+
+    #[test]
+    fn test_keyed_disjoint_set() {
+        let mut ds: KeyedDisjointSet<Point> = [[0, 0], [5, 5]].into_iter().collect();
+        ds.union([0, 0], [1, 0]);
+        ds.union([1, 0], [1, 1]);
+        assert_eq!(ds.len(), 4);
+        assert_eq!(ds.count(), 2);
+        assert_eq!(ds.size([1, 1]), 3);
+        assert_eq!(ds.members([5, 5]), vec![[5, 5]]);
+        assert!(ds.same([0, 0], [1, 1]));
+        assert_eq!(ds.sets(), vec![vec![[0, 0], [1, 0], [1, 1]], vec![[5, 5]]]);
+        // Queries add unknown values
+        assert!(!ds.contains(&[9, 9]));
+        assert_eq!(ds.size([9, 9]), 1);
+        assert!(ds.contains(&[9, 9]));
+        assert_eq!((ds.len(), ds.count()), (5, 3));
+    }
+
+    #[test]
+    fn test_constellations() {
+        // Example from 2018/25
+        let points: Vec<Vec4> = vec![
+            [-1, 2, 2, 0],
+            [0, 0, 2, -2],
+            [0, 0, 0, -2],
+            [-1, 2, 0, 0],
+            [-2, -2, -2, 2],
+            [3, 0, 2, -1],
+            [-1, 3, 2, 2],
+            [-1, 0, -1, 0],
+            [0, 2, 1, -2],
+            [3, 0, 0, 0],
+        ];
+        let mut ds: KeyedDisjointSet<Vec4> = points.iter().copied().collect();
+        for a in &points {
+            for b in &points {
+                if manhattan_vec4(*a, *b) <= 3 {
+                    ds.union(*a, *b);
+                }
+            }
+        }
+        assert_eq!(ds.count(), 4);
+    }
+}
@@ -0,0 +1,295 @@
+//! PC Screen Fonts (PSF1 and PSF2) with their Unicode tables
+//!
+//! Font owns its data, so fonts can be loaded at runtime as well as embedded
+//! with include_bytes. Glyphs are looked up through the Unicode table when
+//! the font has one, and text can be drawn scaled onto an RgbImage or into a
+//! Grid.
+
+use crate::{FontError, Point};
+use image::{Rgb, RgbImage};
+use rustc_hash::FxHashMap;
+use std::path::Path;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE512: u8 = 0x01;
+const PSF1_MODEHASTAB: u8 = 0x02;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_STARTSEQ: u16 = 0xfffe;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_STARTSEQ: u8 = 0xfe;
+
+#[derive(Debug, Clone)]
+pub struct Font {
+    width: u32,
+    height: u32,
+    // Bytes per glyph
+    char_size: usize,
+    glyphs: Vec<u8>,
+    unicode: Option<FxHashMap<char, u32>>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, FontError> {
+    let bytes = data.get(offset..offset + 4).ok_or(FontError::OutOfBounds)?;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+impl Font {
+    // Either PSF version, told apart by the magic
+    pub fn parse(data: &[u8]) -> Result<Font, FontError> {
+        if data.starts_with(&PSF2_MAGIC) {
+            Font::parse_psf2(data)
+        } else if data.starts_with(&PSF1_MAGIC) {
+            Font::parse_psf1(data)
+        } else {
+            Err(FontError::InvalidMagic)
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Font, FontError> {
+        let data = std::fs::read(path).map_err(FontError::Io)?;
+        Font::parse(&data)
+    }
+
+    fn parse_psf1(data: &[u8]) -> Result<Font, FontError> {
+        if data.len() < 4 {
+            return Err(FontError::OutOfBounds);
+        }
+        let mode = data[2];
+        let height = data[3] as u32;
+        let length = if mode & PSF1_MODE512 != 0 { 512 } else { 256 };
+        let char_size = height as usize;
+        let end = 4 + length * char_size;
+        let glyphs = data.get(4..end).ok_or(FontError::OutOfBounds)?.to_vec();
+        let unicode = if mode & PSF1_MODEHASTAB != 0 {
+            let mut map = FxHashMap::default();
+            let table = &data[end..];
+            let mut glyph = 0;
+            let mut in_seq = false;
+            for pair in table.chunks_exact(2) {
+                match u16::from_le_bytes([pair[0], pair[1]]) {
+                    PSF1_SEPARATOR => {
+                        glyph += 1;
+                        in_seq = false;
+                    }
+                    // Only single code points are used, not sequences
+                    PSF1_STARTSEQ => in_seq = true,
+                    u if !in_seq => {
+                        if let Some(c) = char::from_u32(u as u32) {
+                            map.entry(c).or_insert(glyph);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Some(map)
+        } else {
+            None
+        };
+        Ok(Font {
+            width: 8,
+            height,
+            char_size,
+            glyphs,
+            unicode,
+        })
+    }
+
+    fn parse_psf2(data: &[u8]) -> Result<Font, FontError> {
+        let header_size = read_u32(data, 8)? as usize;
+        let flags = read_u32(data, 12)?;
+        let length = read_u32(data, 16)? as usize;
+        let char_size = read_u32(data, 20)? as usize;
+        let height = read_u32(data, 24)?;
+        let width = read_u32(data, 28)?;
+        let end = header_size + length * char_size;
+        let glyphs = data
+            .get(header_size..end)
+            .ok_or(FontError::OutOfBounds)?
+            .to_vec();
+        let unicode = if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+            let mut map = FxHashMap::default();
+            for (glyph, entry) in data[end..].split(|b| *b == PSF2_SEPARATOR).enumerate() {
+                // Only single code points are used, not sequences
+                let singles = entry.split(|b| *b == PSF2_STARTSEQ).next().unwrap();
+                if let Ok(s) = std::str::from_utf8(singles) {
+                    for c in s.chars() {
+                        map.entry(c).or_insert(glyph as u32);
+                    }
+                }
+            }
+            Some(map)
+        } else {
+            None
+        };
+        Ok(Font {
+            width,
+            height,
+            char_size,
+            glyphs,
+            unicode,
+        })
+    }
+
+    pub fn glyph_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn glyph_count(&self) -> u32 {
+        (self.glyphs.len() / self.char_size) as u32
+    }
+
+    pub fn has_unicode_table(&self) -> bool {
+        self.unicode.is_some()
+    }
+
+    // The glyph for c, from the Unicode table if there is one, otherwise c is
+    // used as the index
+    pub fn glyph_index(&self, c: char) -> Option<u32> {
+        match &self.unicode {
+            Some(map) => map.get(&c).copied(),
+            None => Some(c as u32).filter(|ix| *ix < self.glyph_count()),
+        }
+    }
+
+    // Raw glyph data by index
+    pub fn glyph(&self, index: u32) -> Option<&[u8]> {
+        let offset = index as usize * self.char_size;
+        self.glyphs.get(offset..offset + self.char_size)
+    }
+
+    pub fn glyph_for(&self, c: char) -> Option<&[u8]> {
+        self.glyph(self.glyph_index(c)?)
+    }
+
+    // Whether pixel (x, y) of the glyph is set
+    pub fn pixel(&self, glyph: &[u8], x: u32, y: u32) -> bool {
+        let row_bytes = self.width.div_ceil(8);
+        let byte = (y * row_bytes + x / 8) as usize;
+        glyph[byte] & (0x80 >> (x % 8)) != 0
+    }
+
+    // The set pixels of c, relative to its top left corner
+    pub fn glyph_points(&self, c: char) -> Vec<Point> {
+        let Some(glyph) = self.glyph_for(c) else {
+            return vec![];
+        };
+        let mut points = vec![];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.pixel(glyph, x, y) {
+                    points.push([x as i64, y as i64]);
+                }
+            }
+        }
+        points
+    }
+
+    // Size in pixels of s drawn at the given scale
+    pub fn text_size(&self, s: &str, scale: u32) -> (u32, u32) {
+        (
+            s.chars().count() as u32 * self.width * scale,
+            self.height * scale,
+        )
+    }
+
+    // Each font pixel becomes a scale x scale block. Pixels outside the
+    // image are clipped. Chars without a glyph are left blank.
+    pub fn draw_text(&self, image: &mut RgbImage, s: &str, pos: Point, scale: u32, rgb: [u8; 3]) {
+        let (w, h) = image.dimensions();
+        let scale = scale.max(1) as i64;
+        for (i, c) in s.chars().enumerate() {
+            let x0 = pos[0] + i as i64 * self.width as i64 * scale;
+            for [gx, gy] in self.glyph_points(c) {
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = x0 + gx * scale + dx;
+                        let y = pos[1] + gy * scale + dy;
+                        if x >= 0 && y >= 0 && x < w as i64 && y < h as i64 {
+                            image.put_pixel(x as u32, y as u32, Rgb(rgb));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// draw_text with SMALLFONT
+pub fn draw_text(image: &mut RgbImage, s: &str, pos: Point, scale: u32, rgb: [u8; 3]) {
+    crate::SMALLFONT.draw_text(image, s, pos, scale, rgb);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SMALLFONT;
+
+    // An 8x2 PSF1 font where glyph 1 has the top row set and maps to 'x'
+    fn psf1() -> Vec<u8> {
+        let mut data = vec![0x36, 0x04, PSF1_MODEHASTAB, 2];
+        let mut glyphs = vec![0u8; 256 * 2];
+        glyphs[2] = 0xff;
+        data.extend(glyphs);
+        for i in 0..256u16 {
+            if i == 1 {
+                data.extend(u16::from(b'x').to_le_bytes());
+                data.extend(PSF1_STARTSEQ.to_le_bytes());
+                data.extend(u16::from(b'y').to_le_bytes());
+                data.extend(u16::from(b'z').to_le_bytes());
+            }
+            data.extend(PSF1_SEPARATOR.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_psf1() {
+        let font = Font::parse(&psf1()).unwrap();
+        assert_eq!(font.glyph_size(), (8, 2));
+        assert_eq!(font.glyph_count(), 256);
+        assert_eq!(font.glyph_index('x'), Some(1));
+        // Part of a sequence, not a single char
+        assert_eq!(font.glyph_index('y'), None);
+        assert_eq!(font.glyph_points('x').len(), 8);
+        assert!(matches!(
+            Font::parse(&[1, 2, 3]),
+            Err(FontError::InvalidMagic)
+        ));
+        assert!(matches!(
+            Font::parse(&[0x36, 0x04, 0, 8]),
+            Err(FontError::OutOfBounds)
+        ));
+    }
+
+    #[test]
+    fn test_psf2_unicode() {
+        let font = Font::parse(include_bytes!("../fonts/Tamsyn5x9r.psf")).unwrap();
+        assert!(font.has_unicode_table());
+        assert_eq!(font.glyph_size(), SMALLFONT.glyph_size());
+        assert_eq!(font.glyph_index('A'), Some(65));
+        // Box drawing chars live in the control char slots
+        assert_eq!(font.glyph_index('▒'), Some(2));
+        assert_eq!(font.glyph_index('┘'), Some(11));
+        assert!(font.glyph_index('€').is_none());
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let mut image = RgbImage::new(40, 20);
+        draw_text(&mut image, "||", [1, 0], 2, [255, 0, 0]);
+        let lit: Vec<(u32, u32)> = image
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p.0 == [255, 0, 0])
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let (w, h) = SMALLFONT.text_size("||", 2);
+        assert_eq!((w, h), (20, 18));
+        // Scaled pixels come in 2x2 blocks, and both bars are drawn
+        assert!(!lit.is_empty() && lit.len().is_multiple_of(4));
+        assert!(lit.iter().all(|(x, y)| *x >= 1 && *x < 21 && *y < 18));
+        assert!(lit.iter().any(|(x, _)| *x < 11) && lit.iter().any(|(x, _)| *x >= 11));
+    }
+}
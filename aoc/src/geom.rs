@@ -0,0 +1,399 @@
+//! Exact 2D geometry on integer points
+//!
+//! Everything here works on `[T; 2]` for any signed integer `T`, so it can be
+//! used with both `Point` and `PointBig`. Intersections are returned as
+//! rationals so nothing is lost to floating point rounding.
+
+use num::rational::Ratio;
+use num::{Integer, Signed};
+use std::cmp::Ordering;
+
+pub trait Coord: Integer + Signed + Copy {}
+
+impl<T> Coord for T where T: Integer + Signed + Copy {}
+
+pub type RatioPoint<T> = [Ratio<T>; 2];
+
+fn sub<T: Coord>(a: [T; 2], b: [T; 2]) -> [T; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+pub fn cross<T: Coord>(a: [T; 2], b: [T; 2]) -> T {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+pub fn dot<T: Coord>(a: [T; 2], b: [T; 2]) -> T {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+// Cross product of (a - o) and (b - o)
+pub fn cross3<T: Coord>(o: [T; 2], a: [T; 2], b: [T; 2]) -> T {
+    cross(sub(a, o), sub(b, o))
+}
+
+// Less means a -> b -> c turns towards negative cross products, Greater
+// towards positive, and Equal means the points are collinear. With y pointing
+// down (as in all the grids) Greater is a clockwise turn on screen.
+pub fn orientation<T: Coord>(a: [T; 2], b: [T; 2], c: [T; 2]) -> Ordering {
+    cross3(a, b, c).cmp(&T::zero())
+}
+
+pub fn collinear<T: Coord>(a: [T; 2], b: [T; 2], c: [T; 2]) -> bool {
+    cross3(a, b, c).is_zero()
+}
+
+// Is p on the closed segment a-b?
+pub fn on_segment<T: Coord>(p: [T; 2], a: [T; 2], b: [T; 2]) -> bool {
+    collinear(a, b, p)
+        && p[0] >= a[0].min(b[0])
+        && p[0] <= a[0].max(b[0])
+        && p[1] >= a[1].min(b[1])
+        && p[1] <= a[1].max(b[1])
+}
+
+// Do the closed segments a1-a2 and b1-b2 touch?
+pub fn segments_intersect<T: Coord>(a1: [T; 2], a2: [T; 2], b1: [T; 2], b2: [T; 2]) -> bool {
+    let d1 = orientation(b1, b2, a1);
+    let d2 = orientation(b1, b2, a2);
+    let d3 = orientation(a1, a2, b1);
+    let d4 = orientation(a1, a2, b2);
+    if d1 != d2
+        && d3 != d4
+        && d1 != Ordering::Equal
+        && d2 != Ordering::Equal
+        && d3 != Ordering::Equal
+        && d4 != Ordering::Equal
+    {
+        return true;
+    }
+    on_segment(a1, b1, b2)
+        || on_segment(a2, b1, b2)
+        || on_segment(b1, a1, a2)
+        || on_segment(b2, a1, a2)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Intersection<T>
+where
+    T: Clone + Integer,
+{
+    Point(RatioPoint<T>),
+    // Collinear overlap, given as the end points of the shared part
+    Segment([T; 2], [T; 2]),
+}
+
+fn to_ratio<T: Coord>(p: [T; 2]) -> RatioPoint<T> {
+    [Ratio::from_integer(p[0]), Ratio::from_integer(p[1])]
+}
+
+// Solves a + t * da = b + u * db, returns (t, u) or None if parallel
+pub fn line_params<T: Coord>(
+    a: [T; 2],
+    da: [T; 2],
+    b: [T; 2],
+    db: [T; 2],
+) -> Option<(Ratio<T>, Ratio<T>)> {
+    let denom = cross(da, db);
+    if denom.is_zero() {
+        return None;
+    }
+    let ab = sub(b, a);
+    Some((
+        Ratio::new(cross(ab, db), denom),
+        Ratio::new(cross(ab, da), denom),
+    ))
+}
+
+fn point_at<T: Coord>(a: [T; 2], da: [T; 2], t: &Ratio<T>) -> RatioPoint<T> {
+    [
+        Ratio::from_integer(a[0]) + t * da[0],
+        Ratio::from_integer(a[1]) + t * da[1],
+    ]
+}
+
+// Intersection of the infinite lines through a with direction da and b with direction db
+pub fn line_intersection<T: Coord>(
+    a: [T; 2],
+    da: [T; 2],
+    b: [T; 2],
+    db: [T; 2],
+) -> Option<RatioPoint<T>> {
+    line_params(a, da, b, db).map(|(t, _)| point_at(a, da, &t))
+}
+
+// Intersection of the rays starting in a and b, going in direction da and db
+pub fn ray_intersection<T: Coord>(
+    a: [T; 2],
+    da: [T; 2],
+    b: [T; 2],
+    db: [T; 2],
+) -> Option<RatioPoint<T>> {
+    let (t, u) = line_params(a, da, b, db)?;
+    if t < Ratio::from_integer(T::zero()) || u < Ratio::from_integer(T::zero()) {
+        return None;
+    }
+    Some(point_at(a, da, &t))
+}
+
+// Intersection of the closed segments a1-a2 and b1-b2
+pub fn segment_intersection<T: Coord>(
+    a1: [T; 2],
+    a2: [T; 2],
+    b1: [T; 2],
+    b2: [T; 2],
+) -> Option<Intersection<T>> {
+    if !segments_intersect(a1, a2, b1, b2) {
+        return None;
+    }
+    let da = sub(a2, a1);
+    let db = sub(b2, b1);
+    if let Some((t, _)) = line_params(a1, da, b1, db) {
+        return Some(Intersection::Point(point_at(a1, da, &t)));
+    }
+    // Collinear and overlapping, find the shared part
+    let mut pts = [a1, a2, b1, b2];
+    pts.sort_by(|x, y| x[0].cmp(&y[0]).then(x[1].cmp(&y[1])));
+    let (s, e) = (pts[1], pts[2]);
+    if s == e {
+        Some(Intersection::Point(to_ratio(s)))
+    } else {
+        Some(Intersection::Segment(s, e))
+    }
+}
+
+// The smallest integer step in the direction of d
+pub fn reduce_direction<T: Coord>(d: [T; 2]) -> [T; 2] {
+    let g = d[0].gcd(&d[1]);
+    if g.is_zero() {
+        d
+    } else {
+        [d[0] / g, d[1] / g]
+    }
+}
+
+// All integer points strictly between a and b on the segment a-b
+pub fn lattice_points_between<T: Coord>(a: [T; 2], b: [T; 2]) -> impl Iterator<Item = [T; 2]> {
+    let step = reduce_direction(sub(b, a));
+    let mut curr = a;
+    std::iter::from_fn(move || {
+        if step == [T::zero(), T::zero()] {
+            return None;
+        }
+        curr = [curr[0] + step[0], curr[1] + step[1]];
+        if curr == b {
+            None
+        } else {
+            Some(curr)
+        }
+    })
+}
+
+// Can a see b, i.e. is there no blocked integer point strictly between them?
+pub fn line_of_sight<T, F>(a: [T; 2], b: [T; 2], blocked: F) -> bool
+where
+    T: Coord,
+    F: Fn([T; 2]) -> bool,
+{
+    lattice_points_between(a, b).all(|p| !blocked(p))
+}
+
+// 0 for directions in [start, start + 180 degrees), 1 for the rest
+fn half<T: Coord>(start: [T; 2], v: [T; 2]) -> u8 {
+    let c = cross(start, v);
+    if c.is_positive() || (c.is_zero() && dot(start, v).is_positive()) {
+        0
+    } else {
+        1
+    }
+}
+
+// Compares the directions a and b by the angle from start. Angles grow from
+// EAST towards SOUTH, which is clockwise on screen with y pointing down.
+// Zero vectors sort first.
+pub fn angle_cmp<T: Coord>(start: [T; 2], a: [T; 2], b: [T; 2]) -> Ordering {
+    let zero = [T::zero(), T::zero()];
+    match (a == zero, b == zero) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        _ => {}
+    }
+    half(start, a)
+        .cmp(&half(start, b))
+        .then_with(|| T::zero().cmp(&cross(a, b)))
+}
+
+// Sorts points clockwise (on screen) around center, starting at direction
+// start. Points in the same direction are sorted by distance.
+pub fn sort_by_angle<T: Coord>(center: [T; 2], start: [T; 2], points: &mut [[T; 2]]) {
+    points.sort_by(|a, b| {
+        let da = sub(*a, center);
+        let db = sub(*b, center);
+        angle_cmp(start, da, db).then_with(|| dot(da, da).cmp(&dot(db, db)))
+    });
+}
+
+// The order in which a laser rotating clockwise from start around center hits
+// the points, only hitting the closest point in each direction per revolution.
+pub fn sweep_order<T: Coord>(center: [T; 2], start: [T; 2], points: &[[T; 2]]) -> Vec<[T; 2]> {
+    let mut sorted: Vec<_> = points.iter().copied().filter(|p| *p != center).collect();
+    sort_by_angle(center, start, &mut sorted);
+    // Group by direction, each group is sorted by distance
+    let mut groups: Vec<Vec<[T; 2]>> = vec![];
+    let mut last_dir = None;
+    for p in sorted {
+        let dir = reduce_direction(sub(p, center));
+        if last_dir == Some(dir) {
+            groups.last_mut().unwrap().push(p);
+        } else {
+            groups.push(vec![p]);
+            last_dir = Some(dir);
+        }
+    }
+    let mut order = vec![];
+    let mut round = 0;
+    loop {
+        let before = order.len();
+        for g in &groups {
+            if let Some(p) = g.get(round) {
+                order.push(*p);
+            }
+        }
+        if order.len() == before {
+            break;
+        }
+        round += 1;
+    }
+    order
+}
+
+// Twice the signed area of the polygon (shoelace formula)
+pub fn polygon_area2<T: Coord>(polygon: &[[T; 2]]) -> T {
+    let n = polygon.len();
+    (0..n).fold(T::zero(), |acc, i| {
+        acc + cross(polygon[i], polygon[(i + 1) % n])
+    })
+}
+
+// Number of integer points on the boundary of the polygon
+pub fn polygon_boundary_points<T: Coord>(polygon: &[[T; 2]]) -> T {
+    let n = polygon.len();
+    (0..n).fold(T::zero(), |acc, i| {
+        let d = sub(polygon[(i + 1) % n], polygon[i]);
+        acc + d[0].gcd(&d[1])
+    })
+}
+
+// Number of integer points strictly inside the polygon (Pick's theorem)
+pub fn polygon_interior_points<T: Coord>(polygon: &[[T; 2]]) -> T {
+    let two = T::one() + T::one();
+    (polygon_area2(polygon).abs() - polygon_boundary_points(polygon) + two) / two
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Containment {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+// Where is p relative to the (simple) polygon?
+pub fn point_in_polygon<T: Coord>(p: [T; 2], polygon: &[[T; 2]]) -> Containment {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % n];
+        if on_segment(p, a, b) {
+            return Containment::Boundary;
+        }
+        // Count crossings of the ray going east from p
+        if (a[1] > p[1]) != (b[1] > p[1]) {
+            let c = cross3(a, b, p);
+            if (c.is_positive()) == (b[1] > a[1]) {
+                inside = !inside;
+            }
+        }
+    }
+    if inside {
+        Containment::Inside
+    } else {
+        Containment::Outside
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, PointBig, NORTH};
+
+    #[test]
+    fn test_segment_intersection() {
+        let a: Point = [0, 0];
+        let b: Point = [4, 4];
+        let c: Point = [0, 4];
+        let d: Point = [4, 0];
+        assert_eq!(
+            segment_intersection(a, b, c, d),
+            Some(Intersection::Point([Ratio::from(2), Ratio::from(2)]))
+        );
+        assert_eq!(
+            segment_intersection([0, 0], [3, 0], [1, 0], [5, 0]),
+            Some(Intersection::Segment([1, 0], [3, 0]))
+        );
+        assert_eq!(segment_intersection([0, 0], [1, 0], [2, 0], [5, 0]), None);
+        assert_eq!(segment_intersection([0, 0], [1, 1], [0, 1], [1, 2]), None);
+        assert_eq!(
+            segment_intersection([0, 0], [1, 0], [0, 0], [0, 1]),
+            Some(Intersection::Point([Ratio::from(0), Ratio::from(0)]))
+        );
+    }
+
+    #[test]
+    fn test_ray_intersection() {
+        // Hailstones A and B from 2023/24
+        let a: PointBig = [19, 13];
+        let b: PointBig = [18, 19];
+        let p = ray_intersection(a, [-2, 1], b, [-1, -1]).unwrap();
+        assert_eq!(p, [Ratio::new(43, 3), Ratio::new(46, 3)]);
+        // Crossed in the past for one of them
+        assert_eq!(ray_intersection(a, [-2, 1], [20, 19], [1, -5]), None);
+        // Parallel
+        assert_eq!(ray_intersection(b, [-1, -1], [20, 25], [-2, -2]), None);
+    }
+
+    #[test]
+    fn test_line_of_sight() {
+        let blocked: Vec<Point> = vec![[2, 2]];
+        assert!(!line_of_sight([0, 0], [4, 4], |p| blocked.contains(&p)));
+        assert!(line_of_sight([0, 0], [4, 3], |p| blocked.contains(&p)));
+        assert!(line_of_sight([0, 0], [2, 2], |p| blocked.contains(&p)));
+        assert_eq!(
+            lattice_points_between([0, 0], [6, 3]).collect::<Vec<Point>>(),
+            vec![[2, 1], [4, 2]]
+        );
+    }
+
+    #[test]
+    fn test_sweep_order() {
+        let center: Point = [8, 3];
+        let points: Vec<Point> = vec![[8, 1], [8, 0], [9, 0], [9, 3], [8, 5], [7, 3], [7, 0]];
+        assert_eq!(
+            sweep_order(center, NORTH, &points),
+            vec![[8, 1], [9, 0], [9, 3], [8, 5], [7, 3], [7, 0], [8, 0]]
+        );
+    }
+
+    #[test]
+    fn test_polygon() {
+        let poly: Vec<Point> = vec![[0, 0], [4, 0], [4, 4], [2, 2], [0, 4]];
+        assert_eq!(point_in_polygon([1, 1], &poly), Containment::Inside);
+        assert_eq!(point_in_polygon([2, 3], &poly), Containment::Outside);
+        assert_eq!(point_in_polygon([3, 3], &poly), Containment::Boundary);
+        assert_eq!(point_in_polygon([4, 2], &poly), Containment::Boundary);
+        assert_eq!(point_in_polygon([5, 2], &poly), Containment::Outside);
+        assert_eq!(polygon_area2(&poly).abs(), 24);
+        assert_eq!(polygon_boundary_points(&poly), 16);
+        assert_eq!(polygon_interior_points(&poly), 5);
+    }
+}
@@ -0,0 +1,319 @@
+//! A k-d tree for nearest neighbor and range queries on `Point`, `Vec3` and `Vec4`
+//!
+//! Results refer to points by their index in the slice the tree was built from.
+//! Euclidean distances are squared, so everything stays in exact integers.
+
+use std::collections::{BinaryHeap, VecDeque};
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Manhattan,
+    // Squared euclidean distance
+    Euclidean,
+}
+
+impl Metric {
+    pub fn distance<const N: usize>(&self, a: [i64; N], b: [i64; N]) -> i64 {
+        match self {
+            Metric::Manhattan => (0..N).map(|i| (a[i] - b[i]).abs()).sum(),
+            Metric::Euclidean => (0..N).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum(),
+        }
+    }
+
+    // Lower bound for the distance to anything on the other side of a split plane
+    fn axis_distance(&self, diff: i64) -> i64 {
+        match self {
+            Metric::Manhattan => diff.abs(),
+            Metric::Euclidean => diff * diff,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KdTree<const N: usize> {
+    points: Vec<[i64; N]>,
+    // (point, original index) laid out as an implicit tree, the median of
+    // every range is the node and the halves on each side are the children
+    nodes: Vec<([i64; N], usize)>,
+}
+
+impl<const N: usize> KdTree<N> {
+    pub fn new(points: &[[i64; N]]) -> KdTree<N> {
+        let mut nodes: Vec<_> = points.iter().copied().zip(0..).collect();
+        Self::build(&mut nodes, 0);
+        KdTree {
+            points: points.to_vec(),
+            nodes,
+        }
+    }
+
+    fn build(nodes: &mut [([i64; N], usize)], depth: usize) {
+        if nodes.len() <= 1 {
+            return;
+        }
+        let axis = depth % N;
+        let mid = nodes.len() / 2;
+        nodes.select_nth_unstable_by_key(mid, |(p, _)| p[axis]);
+        let (left, right) = nodes.split_at_mut(mid);
+        Self::build(left, depth + 1);
+        Self::build(&mut right[1..], depth + 1);
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn point(&self, ix: usize) -> [i64; N] {
+        self.points[ix]
+    }
+
+    pub fn points(&self) -> &[[i64; N]] {
+        &self.points
+    }
+
+    // Visits every node that could be within the current bound. The visitor
+    // gets (distance, index) and returns the new bound.
+    fn search<F>(
+        &self,
+        range: Range<usize>,
+        depth: usize,
+        p: [i64; N],
+        metric: Metric,
+        bound: &mut i64,
+        visit: &mut F,
+    ) where
+        F: FnMut(i64, usize) -> i64,
+    {
+        if range.is_empty() {
+            return;
+        }
+        let mid = range.start + range.len() / 2;
+        let (q, ix) = self.nodes[mid];
+        let d = metric.distance(p, q);
+        if d <= *bound {
+            *bound = visit(d, ix);
+        }
+        let axis = depth % N;
+        let diff = p[axis] - q[axis];
+        let (near, far) = if diff < 0 {
+            (range.start..mid, mid + 1..range.end)
+        } else {
+            (mid + 1..range.end, range.start..mid)
+        };
+        self.search(near, depth + 1, p, metric, bound, visit);
+        if metric.axis_distance(diff) <= *bound {
+            self.search(far, depth + 1, p, metric, bound, visit);
+        }
+    }
+
+    // The k nearest points to p as (distance, index), closest first
+    pub fn nearest(&self, p: [i64; N], k: usize, metric: Metric) -> Vec<(i64, usize)> {
+        if k == 0 {
+            return vec![];
+        }
+        let mut best: BinaryHeap<(i64, usize)> = BinaryHeap::new();
+        let mut bound = i64::MAX;
+        self.search(
+            0..self.nodes.len(),
+            0,
+            p,
+            metric,
+            &mut bound,
+            &mut |d, ix| {
+                best.push((d, ix));
+                if best.len() > k {
+                    best.pop();
+                }
+                if best.len() == k {
+                    best.peek().unwrap().0
+                } else {
+                    i64::MAX
+                }
+            },
+        );
+        best.into_sorted_vec()
+    }
+
+    // All points within radius of p as (distance, index), closest first
+    pub fn within(&self, p: [i64; N], radius: i64, metric: Metric) -> Vec<(i64, usize)> {
+        let mut found = vec![];
+        let mut bound = radius;
+        self.search(
+            0..self.nodes.len(),
+            0,
+            p,
+            metric,
+            &mut bound,
+            &mut |d, ix| {
+                found.push((d, ix));
+                radius
+            },
+        );
+        found.sort_unstable();
+        found
+    }
+
+    // All pairs (distance, i, j) with i < j within radius of each other, closest first
+    pub fn pairs_within(&self, radius: i64, metric: Metric) -> Vec<(i64, usize, usize)> {
+        self.pairs_between(-1, radius, metric)
+    }
+
+    // All pairs with a distance in ]lo, hi]
+    fn pairs_between(&self, lo: i64, hi: i64, metric: Metric) -> Vec<(i64, usize, usize)> {
+        let mut pairs = vec![];
+        for (i, p) in self.points.iter().enumerate() {
+            for (d, j) in self.within(*p, hi, metric) {
+                if j > i && d > lo {
+                    pairs.push((d, i, j));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    // Iterates over all pairs (distance, i, j) with i < j, closest first. The
+    // pairs are found in batches of growing radius, so taking only the first
+    // few is cheap.
+    pub fn pairwise_distances(&self, metric: Metric) -> PairwiseDistances<'_, N> {
+        let max = if self.points.is_empty() {
+            0
+        } else {
+            let mut min_p = self.points[0];
+            let mut max_p = self.points[0];
+            for p in &self.points {
+                for i in 0..N {
+                    min_p[i] = min_p[i].min(p[i]);
+                    max_p[i] = max_p[i].max(p[i]);
+                }
+            }
+            metric.distance(min_p, max_p)
+        };
+        PairwiseDistances {
+            tree: self,
+            metric,
+            done: -1,
+            radius: 1,
+            max,
+            batch: VecDeque::new(),
+        }
+    }
+}
+
+pub struct PairwiseDistances<'a, const N: usize> {
+    tree: &'a KdTree<N>,
+    metric: Metric,
+    // All pairs up to this distance have been found
+    done: i64,
+    radius: i64,
+    // Largest possible distance between two points
+    max: i64,
+    batch: VecDeque<(i64, usize, usize)>,
+}
+
+impl<const N: usize> Iterator for PairwiseDistances<'_, N> {
+    type Item = (i64, usize, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.batch.is_empty() {
+            if self.done >= self.max {
+                return None;
+            }
+            let hi = self.radius.min(self.max);
+            self.batch = self.tree.pairs_between(self.done, hi, self.metric).into();
+            self.done = hi;
+            self.radius = self.radius.saturating_mul(2);
+        }
+        self.batch.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point, Vec3, Vec4};
+
+    fn random_points<const N: usize>(n: usize, range: i64) -> Vec<[i64; N]> {
+        let mut seed: u64 = 12345;
+        let mut next = move || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) as i64).rem_euclid(2 * range + 1) - range
+        };
+        (0..n).map(|_| [0; N].map(|_| next())).collect()
+    }
+
+    fn brute_pairs<const N: usize>(
+        points: &[[i64; N]],
+        metric: Metric,
+    ) -> Vec<(i64, usize, usize)> {
+        let mut pairs = vec![];
+        for i in 0..points.len() {
+            for j in (i + 1)..points.len() {
+                pairs.push((metric.distance(points[i], points[j]), i, j));
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn test_nearest() {
+        let points: Vec<Point> = vec![[0, 0], [5, 5], [1, 1], [-3, 2], [10, 0], [2, 0]];
+        let tree = KdTree::new(&points);
+        assert_eq!(
+            tree.nearest([0, 0], 3, Metric::Euclidean),
+            vec![(0, 0), (2, 2), (4, 5)]
+        );
+        assert_eq!(tree.nearest([9, 1], 1, Metric::Manhattan), vec![(2, 4)]);
+        let points: Vec<Vec3> = random_points(200, 50);
+        let tree = KdTree::new(&points);
+        for metric in [Metric::Manhattan, Metric::Euclidean] {
+            for p in random_points::<3>(20, 60) {
+                let mut expected: Vec<_> = (0..points.len())
+                    .map(|i| (metric.distance(p, points[i]), i))
+                    .collect();
+                expected.sort_unstable();
+                let found = tree.nearest(p, 5, metric);
+                let found_d: Vec<_> = found.iter().map(|x| x.0).collect();
+                let expected_d: Vec<_> = expected[..5].iter().map(|x| x.0).collect();
+                assert_eq!(found_d, expected_d);
+            }
+        }
+    }
+
+    #[test]
+    fn test_within() {
+        let points: Vec<Vec4> = random_points(300, 10);
+        let tree = KdTree::new(&points);
+        let pairs = tree.pairs_within(3, Metric::Manhattan);
+        let expected: Vec<_> = brute_pairs(&points, Metric::Manhattan)
+            .into_iter()
+            .filter(|x| x.0 <= 3)
+            .collect();
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn test_pairwise_distances() {
+        let points: Vec<Vec3> = random_points(100, 1000);
+        let tree = KdTree::new(&points);
+        for metric in [Metric::Manhattan, Metric::Euclidean] {
+            let pairs: Vec<_> = tree.pairwise_distances(metric).collect();
+            assert_eq!(pairs, brute_pairs(&points, metric));
+        }
+        let empty: Vec<Point> = vec![];
+        assert_eq!(
+            KdTree::new(&empty)
+                .pairwise_distances(Metric::Manhattan)
+                .count(),
+            0
+        );
+    }
+}
@@ -7,7 +7,7 @@ use std::error;
 use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::BufReader;
+use std::io::{self, BufReader};
 use std::iter::*;
 use std::marker::PhantomData;
 use std::num::ParseIntError;
@@ -88,6 +88,49 @@ pub use smallvec::{smallvec, SmallVec};
 mod asciiset;
 pub use asciiset::*;
 
+mod circular_list;
+pub use circular_list::*;
+
+pub mod geom;
+
+mod disjoint_set;
+pub use disjoint_set::*;
+
+mod kdtree;
+pub use kdtree::*;
+
+mod matrix;
+pub use matrix::*;
+
+pub mod ocr;
+
+mod animation;
+pub use animation::*;
+
+mod svg;
+pub use svg::*;
+
+mod ansi;
+pub use ansi::*;
+
+mod stepper;
+pub use stepper::*;
+
+mod recording;
+pub use recording::*;
+
+mod overlay;
+pub use overlay::*;
+
+mod search;
+pub use search::*;
+
+mod voxel;
+pub use voxel::*;
+
+mod font;
+pub use font::*;
+
 pub fn length(v: FVec3) -> f64 {
     vec_square_length(v).sqrt()
 }
@@ -501,13 +544,52 @@ pub fn astar_graph<T: petgraph::EdgeType>(
     start: Point,
     goal: Point,
 ) -> Option<(i64, Vec<Point>)> {
-    petgraph::algo::astar(
-        &graph,
-        start,
-        |finish| finish == goal,                             // is finish
-        |(_n1, _n2, e)| *e,                                  // true cost
-        |n| (goal[0] - n[0]).abs() + (goal[1] - n[1]).abs(), // estimated cost: manhattan distance}
-    )
+    astar_graph_observed(graph, start, goal, &mut NopSearchObserver {})
+}
+
+// Like astar_graph but reporting progress to observer, see SearchObserver
+pub fn astar_graph_observed<T: petgraph::EdgeType>(
+    graph: &GraphMap<Point, i64, T>,
+    start: Point,
+    goal: Point,
+    observer: &mut dyn SearchObserver,
+) -> Option<(i64, Vec<Point>)> {
+    let mut frontier = BinaryHeap::new();
+    let mut came_from = FxHashMap::default();
+    let mut gscore: FxHashMap<Point, i64> = FxHashMap::default();
+    gscore.insert(start, 0);
+    frontier.push(Reverse((manhattan(start, goal), start)));
+    observer.pushed(start, manhattan(start, goal));
+    while let Some(Reverse((est, current))) = frontier.pop() {
+        let g = gscore[&current];
+        if est > g + manhattan(current, goal) {
+            // Stale entry, a cheaper way here was found after it was pushed
+            continue;
+        }
+        observer.expanded(current, g);
+        if current == goal {
+            let mut path = vec![goal];
+            let mut curr = goal;
+            while curr != start {
+                curr = came_from[&curr];
+                path.push(curr)
+            }
+            path.reverse();
+            observer.path_found(g, &path);
+            return Some((g, path));
+        }
+        for (_, nb, e) in graph.edges(current) {
+            let new_g = g + *e;
+            if gscore.get(&nb).is_none_or(|nb_g| new_g < *nb_g) {
+                came_from.insert(nb, current);
+                gscore.insert(nb, new_g);
+                let new_f = new_g + manhattan(nb, goal);
+                frontier.push(Reverse((new_f, nb)));
+                observer.pushed(nb, new_f);
+            }
+        }
+    }
+    None
 }
 
 pub fn manhattan(n: Point, goal: Point) -> i64 {
@@ -536,6 +618,28 @@ pub fn astar_grid<T>(
     start: Point,
     goal: Point,
 ) -> Option<(i64, Vec<Point>)>
+where
+    T: PartialEq + Copy,
+{
+    astar_grid_observed(
+        grid,
+        is_node,
+        get_edge_cost,
+        start,
+        goal,
+        &mut NopSearchObserver {},
+    )
+}
+
+// Like astar_grid but reporting progress to observer, see SearchObserver
+pub fn astar_grid_observed<T>(
+    grid: &dyn Grid<T>,
+    is_node: fn(&Point, &T) -> bool,
+    get_edge_cost: fn(&Point, &T, &Point, &T) -> Option<i64>,
+    start: Point,
+    goal: Point,
+    observer: &mut dyn SearchObserver,
+) -> Option<(i64, Vec<Point>)>
 where
     T: PartialEq + Copy,
 {
@@ -546,7 +650,13 @@ where
     gscore.insert(start, 0);
     fscore.insert(start, manhattan(start, goal));
     frontier.push(Reverse((manhattan(start, goal), start)));
-    while let Some(Reverse((_est, current))) = frontier.pop() {
+    observer.pushed(start, manhattan(start, goal));
+    while let Some(Reverse((est, current))) = frontier.pop() {
+        if est > fscore[&current] {
+            // Stale entry, a cheaper way here was found after it was pushed
+            continue;
+        }
+        observer.expanded(current, gscore[&current]);
         if current == goal {
             let mut path = vec![goal];
             let mut curr = goal;
@@ -554,7 +664,9 @@ where
                 curr = came_from[&curr];
                 path.push(curr)
             }
-            return Some((gscore.get_value(goal).unwrap(), path));
+            let cost = gscore.get_value(goal).unwrap();
+            observer.path_found(cost, &path);
+            return Some((cost, path));
         }
         let g = *gscore.entry(current).or_insert(i64::MAX);
         let curr_val = grid.get_value(current).unwrap();
@@ -570,6 +682,7 @@ where
                             let new_f = new_g + manhattan(goal, nb);
                             *fscore.entry(nb).or_insert(i64::MAX) = new_f;
                             frontier.push(Reverse((new_f, nb)));
+                            observer.pushed(nb, new_f);
                         }
                     }
                 }
@@ -586,6 +699,28 @@ pub fn dijkstra_grid<T>(
     start: Point,
     goal: Point,
 ) -> Option<(i64, Vec<Point>)>
+where
+    T: PartialEq + Copy,
+{
+    dijkstra_grid_observed(
+        grid,
+        is_node,
+        get_edge_cost,
+        start,
+        goal,
+        &mut NopSearchObserver {},
+    )
+}
+
+// Like dijkstra_grid but reporting progress to observer, see SearchObserver
+pub fn dijkstra_grid_observed<T>(
+    grid: &dyn Grid<T>,
+    is_node: fn(&Point, &T) -> bool,
+    get_edge_cost: fn(&Point, &T, &Point, &T) -> Option<i64>,
+    start: Point,
+    goal: Point,
+    observer: &mut dyn SearchObserver,
+) -> Option<(i64, Vec<Point>)>
 where
     T: PartialEq + Copy,
 {
@@ -593,10 +728,12 @@ where
     let mut visited: FxHashSet<Point> = FxHashSet::default();
     let mut came_from = FxHashMap::default();
     frontier.push(Reverse((0, start)));
+    observer.pushed(start, 0);
     while let Some(Reverse((score, current))) = frontier.pop() {
         if visited.contains(&current) {
             continue;
         }
+        observer.expanded(current, score);
         if current == goal {
             let mut path = vec![goal];
             let mut curr = goal;
@@ -604,7 +741,9 @@ where
                 curr = came_from[&curr];
                 path.push(curr)
             }
-            return Some((score, path.into_iter().rev().collect()));
+            let path: Vec<Point> = path.into_iter().rev().collect();
+            observer.path_found(score, &path);
+            return Some((score, path));
         }
         let curr_val = grid.get_value(current).unwrap();
         for nb in neighbors(current) {
@@ -617,6 +756,7 @@ where
                         let new_score = score + edge_cost;
                         came_from.insert(nb, current);
                         frontier.push(Reverse((new_score, nb)));
+                        observer.pushed(nb, new_score);
                     }
                 }
             }
@@ -839,23 +979,22 @@ where
         self.text(s, pos, value);
     }
 
-    fn text(&mut self, a: &str, mut pos: Point, value: T) {
-        let (w, h) = SMALLFONT.glyph_size();
+    fn text(&mut self, a: &str, pos: Point, value: T) {
+        self.text_with_font(&SMALLFONT, a, pos, 1, value);
+    }
+
+    // Like text but with any font, each font pixel scale x scale cells
+    fn text_with_font(&mut self, font: &Font, a: &str, mut pos: Point, scale: i64, value: T) {
+        let w = font.glyph_size().0 as i64;
         for c in a.chars() {
-            if let Some(glyph) = SMALLFONT.glyph(c as u32) {
-                for y in 0..h {
-                    for x in 0..w {
-                        let byte = y + (x / 8);
-                        let bit = 7 - (x % 8);
-                        if (glyph[byte as usize] & (1 << bit)) == 0 {
-                            // Do nothing
-                        } else {
-                            self.set_value(point_add(pos, [x as i64, y as i64]), value);
-                        }
+            for [x, y] in font.glyph_points(c) {
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        self.set_value(point_add(pos, [x * scale + dx, y * scale + dy]), value);
                     }
                 }
             }
-            pos[0] += w as i64;
+            pos[0] += w * scale;
         }
     }
 }
@@ -1364,8 +1503,7 @@ where
 {
     sprite_dimension: (i64, i64),
     to_sprite: F,
-    basename: String,
-    frame: usize,
+    frames: FrameSink,
     rect: Option<(Point, Point)>,
     bg: [u8; 3],
     unset: Option<T>,
@@ -1378,6 +1516,7 @@ where
 // ffmpeg -i "basename_%06d.png" -filter_complex "[0:v] palettegen" basename_palette.png
 // ffmpeg -framerate 25 -i "basename_%06d.png" -i basename_palette.png -filter_complex "[0:v][1:v] paletteuse" basename.gif
 // You can change the start number with the -start_number input option.
+// Or use set_animation to skip the PNGs and write a GIF/APNG directly.
 impl<F, G, T> BitmapSpriteGridDrawer<F, G, T>
 where
     F: Fn(T) -> Vec<[u8; 3]>,
@@ -1389,16 +1528,10 @@ where
         to_sprite: F,
         basename: &str,
     ) -> BitmapSpriteGridDrawer<F, G, T> {
-        // TODO: error handling
-        let path = Path::new(basename);
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).expect("could not create folder");
-        }
         BitmapSpriteGridDrawer {
             sprite_dimension,
             to_sprite,
-            frame: 0,
-            basename: basename.into(),
+            frames: FrameSink::new(basename),
             rect: None,
             bg: [255, 255, 255],
             unset: None,
@@ -1420,25 +1553,36 @@ where
         self.unset = Some(unset);
     }
 
+    // Frames go into the animation instead of numbered PNGs
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.frames.set_animation(animation);
+    }
+
+    // The animation, to finish it and see any error
+    pub fn take_animation(&mut self) -> Option<Animation> {
+        self.frames.take_animation()
+    }
+
+    // Finishes the animation if there is one, returning the first error
+    pub fn finish(self) -> io::Result<()> {
+        self.frames.finish()
+    }
+
+    // Saves the current image, as a PNG or as the next animation frame
+    pub fn save_frame(&mut self) {
+        if let Some(image) = &self.image {
+            self.frames.save_frame(image);
+        }
+    }
+
     pub fn save_image(&self) {
-        let path = Path::new(&self.basename);
-        let filename = if let Some(parent) = path.parent() {
-            parent.join(format!(
-                "{}_{:06}.png",
-                path.file_name().unwrap().to_str().unwrap(),
-                self.frame
-            ))
-        } else {
-            PathBuf::from(&format!("{}_{}.png", self.basename, self.frame))
-        };
-        println!("saving to {:?}", filename);
         if let Some(image) = &self.image {
-            image.save(filename).unwrap();
+            self.frames.save_image(image);
         }
     }
 
     pub fn draw_grid(&mut self, area: &G) {
-        self.frame += 1;
+        self.frames.next_frame();
         let ([mut min_x, mut min_y], [mut max_x, mut max_y]) = area.extents();
         // "clip" to rect
         if let Some(([cmin_x, cmin_y], [cmax_x, cmax_y])) = self.rect {
@@ -1485,6 +1629,19 @@ where
         self.image = Some(image);
     }
 
+    // Stamps text in SMALLFONT onto the current image, e.g. a frame counter.
+    // Use between draw_grid and save_frame.
+    pub fn draw_text(&mut self, text: &str, pos: Point, scale: u32, rgb: [u8; 3]) {
+        if let Some(ref mut image) = self.image {
+            draw_text(image, text, pos, scale, rgb);
+        }
+    }
+
+    // The current image, to draw on it with other fonts etc
+    pub fn image_mut(&mut self) -> Option<&mut RgbImage> {
+        self.image.as_mut()
+    }
+
     pub fn put_pixel(&mut self, p: Point, rgb: [u8; 3]) {
         if let Some(ref mut image) = self.image {
             let x = p[0] as u32;
@@ -1508,7 +1665,7 @@ where
 {
     fn draw(&mut self, area: &G) {
         self.draw_grid(area);
-        self.save_image();
+        self.save_frame();
     }
 }
 
@@ -1518,15 +1675,13 @@ fn glyph_to_sprite<F: Fn(T) -> (char, [u8; 3]), T: PartialEq + Copy>(
 ) -> Vec<[u8; 3]> {
     let (c, col) = to_glyph(x);
     let mut v = vec![];
-    let g = SMALLFONT.glyph(c as u32).unwrap();
+    let g = SMALLFONT.glyph_for(c).unwrap();
     for y in 0..SMALLFONT.glyph_size().1 {
         for x in 0..SMALLFONT.glyph_size().0 {
-            let byte = y + (x / 8);
-            let bit = 7 - (x % 8);
-            if (g[byte as usize] & (1 << bit)) == 0 {
-                v.push([0x0, 0x0, 0x0]);
-            } else {
+            if SMALLFONT.pixel(g, x, y) {
                 v.push(col);
+            } else {
+                v.push([0x0, 0x0, 0x0]);
             }
         }
     }
@@ -1558,8 +1713,7 @@ where
     T: PartialEq + Copy,
 {
     to_color: F,
-    basename: String,
-    frame: usize,
+    frames: FrameSink,
     rect: Option<(Point, Point)>,
     bg: [u8; 3],
     image: Option<RgbImage>,
@@ -1571,6 +1725,7 @@ where
 // ffmpeg -i "basename_%06d.png" -filter_complex "[0:v] palettegen" basename_palette.png
 // ffmpeg -framerate 25 -i "basename_%06d.png" -i basename_palette.png -filter_complex "[0:v][1:v] paletteuse" basename.gif
 // You can change the start number with the -start_number input option.
+// Or use set_animation to skip the PNGs and write a GIF/APNG directly.
 impl<F, G, T> BitmapGridDrawer<F, G, T>
 where
     F: Fn(T) -> [u8; 3],
@@ -1578,15 +1733,9 @@ where
     T: PartialEq + Copy,
 {
     pub fn new(to_color: F, basename: &str) -> BitmapGridDrawer<F, G, T> {
-        // TODO: error handling
-        let path = Path::new(basename);
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).expect("could not create folder");
-        }
         BitmapGridDrawer {
             to_color,
-            frame: 0,
-            basename: basename.into(),
+            frames: FrameSink::new(basename),
             rect: None,
             bg: [255, 255, 255],
             image: None,
@@ -1603,24 +1752,36 @@ where
         self.bg = bg;
     }
 
+    // Frames go into the animation instead of numbered PNGs
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.frames.set_animation(animation);
+    }
+
+    // The animation, to finish it and see any error
+    pub fn take_animation(&mut self) -> Option<Animation> {
+        self.frames.take_animation()
+    }
+
+    // Finishes the animation if there is one, returning the first error
+    pub fn finish(self) -> io::Result<()> {
+        self.frames.finish()
+    }
+
+    // Saves the current image, as a PNG or as the next animation frame
+    pub fn save_frame(&mut self) {
+        if let Some(image) = &self.image {
+            self.frames.save_frame(image);
+        }
+    }
+
     pub fn save_image(&self) {
-        let path = Path::new(&self.basename);
-        let filename = if let Some(parent) = path.parent() {
-            parent.join(format!(
-                "{}_{:06}.png",
-                path.file_name().unwrap().to_str().unwrap(),
-                self.frame
-            ))
-        } else {
-            PathBuf::from(&format!("{}_{}.png", self.basename, self.frame))
-        };
         if let Some(image) = &self.image {
-            image.save(filename).unwrap();
+            self.frames.save_image(image);
         }
     }
 
     pub fn draw_grid(&mut self, area: &G) {
-        self.frame += 1;
+        self.frames.next_frame();
         let ([mut min_x, mut min_y], [mut max_x, mut max_y]) = area.extents();
         // "clip" to rect
         if let Some(([cmin_x, cmin_y], [cmax_x, cmax_y])) = self.rect {
@@ -1647,6 +1808,19 @@ where
         self.image = Some(image);
     }
 
+    // Stamps text in SMALLFONT onto the current image, e.g. a frame counter.
+    // Use between draw_grid and save_frame.
+    pub fn draw_text(&mut self, text: &str, pos: Point, scale: u32, rgb: [u8; 3]) {
+        if let Some(ref mut image) = self.image {
+            draw_text(image, text, pos, scale, rgb);
+        }
+    }
+
+    // The current image, to draw on it with other fonts etc
+    pub fn image_mut(&mut self) -> Option<&mut RgbImage> {
+        self.image.as_mut()
+    }
+
     pub fn put_pixel(&mut self, p: Point, rgb: [u8; 3]) {
         if let Some(ref mut image) = self.image {
             let x = p[0] as u32;
@@ -1670,7 +1844,7 @@ where
 {
     fn draw(&mut self, area: &G) {
         self.draw_grid(area);
-        self.save_image();
+        self.save_frame();
     }
 }
 
@@ -2228,8 +2402,7 @@ where
     T: PartialEq + Copy,
 {
     to_color: F,
-    basename: String,
-    frame: usize,
+    frames: FrameSink,
     image: Option<RgbImage>,
     hexagon: Vec<Vec<[u8; 3]>>,
     phantom: PhantomData<T>,
@@ -2240,6 +2413,7 @@ where
 // ffmpeg -i "basename_%06d.png" -filter_complex "[0:v] palettegen" basename_palette.png
 // ffmpeg -framerate 25 -i "basename_%06d.png" -i basename_palette.png -filter_complex "[0:v][1:v] paletteuse" basename.gif
 // You can change the start number with the -start_number input option.
+// Or use set_animation to skip the PNGs and write a GIF/APNG directly.
 impl<F, G, T> BitmapHexGridDrawer<F, G, T>
 where
     F: Fn(T) -> [u8; 3],
@@ -2247,11 +2421,6 @@ where
     T: PartialEq + Copy + Default,
 {
     pub fn new(to_color: F, basename: &str) -> BitmapHexGridDrawer<F, G, T> {
-        // TODO: error handling
-        let path = Path::new(basename);
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent).expect("could not create folder");
-        }
         // Make a hexagon
         let mut hex = vec![vec![[255, 255, 255]; 7]; 10];
         hex.set_value([3, 0], [180, 180, 180]);
@@ -2274,8 +2443,7 @@ where
         hex.set_value([3, 7], [180, 180, 180]);
         BitmapHexGridDrawer {
             to_color,
-            frame: 0,
-            basename: basename.into(),
+            frames: FrameSink::new(basename),
             image: None,
             hexagon: hex,
             phantom: PhantomData,
@@ -2283,24 +2451,36 @@ where
         }
     }
 
+    // Frames go into the animation instead of numbered PNGs
+    pub fn set_animation(&mut self, animation: Animation) {
+        self.frames.set_animation(animation);
+    }
+
+    // The animation, to finish it and see any error
+    pub fn take_animation(&mut self) -> Option<Animation> {
+        self.frames.take_animation()
+    }
+
+    // Finishes the animation if there is one, returning the first error
+    pub fn finish(self) -> io::Result<()> {
+        self.frames.finish()
+    }
+
+    // Saves the current image, as a PNG or as the next animation frame
+    pub fn save_frame(&mut self) {
+        if let Some(image) = &self.image {
+            self.frames.save_frame(image);
+        }
+    }
+
     pub fn save_image(&self) {
-        let path = Path::new(&self.basename);
-        let filename = if let Some(parent) = path.parent() {
-            parent.join(format!(
-                "{}_{:06}.png",
-                path.file_name().unwrap().to_str().unwrap(),
-                self.frame
-            ))
-        } else {
-            PathBuf::from(&format!("{}_{}.png", self.basename, self.frame))
-        };
         if let Some(image) = &self.image {
-            image.save(filename).unwrap();
+            self.frames.save_image(image);
         }
     }
 
     pub fn draw_grid(&mut self, area: &G) {
-        self.frame += 1;
+        self.frames.next_frame();
         let g = self.convert(area);
         let ([min_x, min_y], [max_x, max_y]) = g.extents();
         let width = max_x - min_x + 1;
@@ -2334,6 +2514,19 @@ where
         self.image = Some(image);
     }
 
+    // Stamps text in SMALLFONT onto the current image, e.g. a frame counter.
+    // Use between draw_grid and save_frame.
+    pub fn draw_text(&mut self, text: &str, pos: Point, scale: u32, rgb: [u8; 3]) {
+        if let Some(ref mut image) = self.image {
+            draw_text(image, text, pos, scale, rgb);
+        }
+    }
+
+    // The current image, to draw on it with other fonts etc
+    pub fn image_mut(&mut self) -> Option<&mut RgbImage> {
+        self.image.as_mut()
+    }
+
     pub fn put_pixel(&mut self, p: Point, rgb: [u8; 3]) {
         if let Some(ref mut image) = self.image {
             let x = p[0] as u32;
@@ -2353,78 +2546,39 @@ where
 {
     fn draw(&mut self, area: &G) {
         self.draw_grid(area);
-        self.save_image();
+        self.save_frame();
     }
 }
 
-// psf font parser from https://gist.github.com/hinzundcode/0480c5c8aa220cd43cc8da634119a3c0
-const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
-
 #[derive(Debug)]
 pub enum FontError {
     OutOfBounds,
     InvalidMagic,
+    Io(std::io::Error),
 }
 
-#[repr(C, packed)]
-struct PSF2Header {
-    magic: [u8; 4],
-    version: u32,
-    header_size: u32,
-    flags: u32,
-    length: u32,
-    char_size: u32,
-    height: u32,
-    width: u32,
-}
-
-pub struct PSF2Font<'a> {
-    data: &'a [u8],
-    header: &'a PSF2Header,
-}
-
-impl<'a> PSF2Font<'a> {
-    pub fn parse(data: &'a [u8]) -> Result<Self, FontError> {
-        if data.len() < std::mem::size_of::<PSF2Header>() {
-            return Err(FontError::OutOfBounds);
-        }
-
-        let header = unsafe { &(*(data.as_ptr() as *const PSF2Header)) };
-
-        if header.magic != PSF2_MAGIC {
-            return Err(FontError::InvalidMagic);
-        }
-
-        let last_glyph_pos = header.header_size + header.char_size * (header.length - 1);
-        if data.len() < last_glyph_pos as usize {
-            return Err(FontError::OutOfBounds);
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::OutOfBounds => write!(f, "font data is truncated"),
+            FontError::InvalidMagic => write!(f, "not a psf font"),
+            FontError::Io(e) => write!(f, "could not read font: {}", e),
         }
-
-        Ok(PSF2Font { data, header })
-    }
-
-    pub fn glyph_size(&self) -> (u32, u32) {
-        (self.header.width, self.header.height)
-    }
-
-    pub fn glyph_count(&self) -> u32 {
-        self.header.length
     }
+}
 
-    pub fn glyph(&self, index: u32) -> Option<&[u8]> {
-        if index >= self.header.length {
-            return None;
+impl error::Error for FontError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FontError::Io(e) => Some(e),
+            _ => None,
         }
-
-        let length = self.header.char_size as usize;
-        let offset = self.header.header_size as usize + index as usize * length;
-        Some(&self.data[offset..(offset + length)])
     }
 }
 
 lazy_static! {
-    pub static ref SMALLFONT: PSF2Font<'static> =
-        PSF2Font::parse(include_bytes!("../fonts/Tamsyn5x9r.psf")).unwrap();
+    pub static ref SMALLFONT: Font =
+        Font::parse(include_bytes!("../fonts/Tamsyn5x9r.psf")).unwrap();
 }
 
 pub fn read_lines_from<P: AsRef<Path>>(filename: P) -> Vec<String> {
@@ -0,0 +1,247 @@
+//! Square matrices and affine maps with modular arithmetic
+//!
+//! The modular operations reduce after every multiplication, so T only needs
+//! to hold the square of the modulus (i128 works for all the puzzle sizes).
+
+use num::{Integer, Signed};
+use std::ops::Mul;
+
+use crate::mod_inv;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Matrix<T, const N: usize> {
+    pub m: [[T; N]; N],
+}
+
+impl<T, const N: usize> Matrix<T, N>
+where
+    T: Integer + Copy,
+{
+    pub fn new(m: [[T; N]; N]) -> Matrix<T, N> {
+        Matrix { m }
+    }
+
+    pub fn zero() -> Matrix<T, N> {
+        Matrix {
+            m: [[T::zero(); N]; N],
+        }
+    }
+
+    pub fn identity() -> Matrix<T, N> {
+        let mut m = [[T::zero(); N]; N];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = T::one();
+        }
+        Matrix { m }
+    }
+
+    // The matrix that takes [x(n-1), ..., x(n-N)] to [x(n), ..., x(n-N+1)]
+    // for the recurrence x(n) = coeffs[0] * x(n-1) + ... + coeffs[N-1] * x(n-N)
+    pub fn companion(coeffs: [T; N]) -> Matrix<T, N> {
+        let mut m = [[T::zero(); N]; N];
+        m[0] = coeffs;
+        for (i, row) in m.iter_mut().enumerate().skip(1) {
+            row[i - 1] = T::one();
+        }
+        Matrix { m }
+    }
+
+    fn mul_with(&self, other: &Matrix<T, N>, reduce: impl Fn(T) -> T) -> Matrix<T, N> {
+        let mut m = [[T::zero(); N]; N];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, v) in row.iter_mut().enumerate() {
+                for k in 0..N {
+                    *v = reduce(*v + reduce(self.m[i][k] * other.m[k][j]));
+                }
+            }
+        }
+        Matrix { m }
+    }
+
+    pub fn mul_mod(&self, other: &Matrix<T, N>, modulus: T) -> Matrix<T, N> {
+        self.mul_with(other, |x| x.mod_floor(&modulus))
+    }
+
+    pub fn pow(&self, e: u64) -> Matrix<T, N> {
+        self.pow_with(e, |x| x)
+    }
+
+    pub fn pow_mod(&self, e: u64, modulus: T) -> Matrix<T, N> {
+        self.pow_with(e, |x| x.mod_floor(&modulus))
+    }
+
+    // Exponentiation by squaring
+    fn pow_with(&self, mut e: u64, reduce: impl Fn(T) -> T + Copy) -> Matrix<T, N> {
+        let mut result = Matrix {
+            m: Self::identity().m.map(|row| row.map(reduce)),
+        };
+        let mut base = *self;
+        while e > 0 {
+            if e & 1 == 1 {
+                result = result.mul_with(&base, reduce);
+            }
+            base = base.mul_with(&base, reduce);
+            e >>= 1;
+        }
+        result
+    }
+
+    pub fn transform(&self, v: [T; N]) -> [T; N] {
+        self.transform_with(v, |x| x)
+    }
+
+    pub fn transform_mod(&self, v: [T; N], modulus: T) -> [T; N] {
+        self.transform_with(v, |x| x.mod_floor(&modulus))
+    }
+
+    fn transform_with(&self, v: [T; N], reduce: impl Fn(T) -> T) -> [T; N] {
+        let mut out = [T::zero(); N];
+        for (i, o) in out.iter_mut().enumerate() {
+            for (k, x) in v.iter().enumerate() {
+                *o = reduce(*o + reduce(self.m[i][k] * *x));
+            }
+        }
+        out
+    }
+}
+
+impl<T, const N: usize> Mul for Matrix<T, N>
+where
+    T: Integer + Copy,
+{
+    type Output = Matrix<T, N>;
+
+    fn mul(self, rhs: Matrix<T, N>) -> Matrix<T, N> {
+        self.mul_with(&rhs, |x| x)
+    }
+}
+
+// The map x -> a * x + b (mod m)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Affine<T> {
+    pub a: T,
+    pub b: T,
+    pub m: T,
+}
+
+impl<T> Affine<T>
+where
+    T: Integer + Signed + Copy,
+{
+    pub fn new(a: T, b: T, m: T) -> Affine<T> {
+        Affine {
+            a: a.mod_floor(&m),
+            b: b.mod_floor(&m),
+            m,
+        }
+    }
+
+    pub fn identity(m: T) -> Affine<T> {
+        Affine::new(T::one(), T::zero(), m)
+    }
+
+    pub fn apply(&self, x: T) -> T {
+        (self.a * x.mod_floor(&self.m) + self.b).mod_floor(&self.m)
+    }
+
+    // First self, then next
+    pub fn then(&self, next: &Affine<T>) -> Affine<T> {
+        assert!(self.m == next.m);
+        Affine::new(
+            (self.a * next.a).mod_floor(&self.m),
+            (self.b * next.a).mod_floor(&self.m) + next.b,
+            self.m,
+        )
+    }
+
+    // self(inner(x))
+    pub fn compose(&self, inner: &Affine<T>) -> Affine<T> {
+        inner.then(self)
+    }
+
+    // Only exists if a and m are coprime
+    pub fn inverse(&self) -> Option<Affine<T>> {
+        let a_inv = mod_inv(self.a, self.m)?;
+        Some(Affine::new(
+            a_inv,
+            (-a_inv * self.b).mod_floor(&self.m),
+            self.m,
+        ))
+    }
+
+    // Applying self e times
+    pub fn pow(&self, mut e: u64) -> Affine<T> {
+        let mut result = Affine::identity(self.m);
+        let mut base = *self;
+        while e > 0 {
+            if e & 1 == 1 {
+                result = result.then(&base);
+            }
+            base = base.then(&base);
+            e >>= 1;
+        }
+        result
+    }
+
+    // [x, 1] -> [a * x + b, 1]
+    pub fn to_matrix(&self) -> Matrix<T, 2> {
+        Matrix::new([[self.a, self.b], [T::zero(), T::one()]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matrix_pow() {
+        let fib = Matrix::companion([1i64, 1]);
+        assert_eq!(fib.pow(10).transform([1, 0]), [89, 55]);
+        assert_eq!(fib.pow(0), Matrix::identity());
+        let fib = Matrix::companion([1i128, 1]);
+        // F(1000) mod 1e9+7
+        assert_eq!(fib.pow_mod(1000, 1_000_000_007).m[0][1], 517691607);
+        let m = Matrix::new([[1i64, 2, 0], [0, 1, 3], [4, 0, 1]]);
+        assert_eq!(m.pow(3), m * m * m);
+        assert_eq!(
+            m.pow_mod(5, 7).transform_mod([1, 2, 3], 7),
+            m.pow(5).transform([1, 2, 3]).map(|x| x.rem_euclid(7))
+        );
+    }
+
+    #[test]
+    fn test_affine() {
+        let f = Affine::new(3i128, 4, 11);
+        let g = Affine::new(5i128, -2, 11);
+        for x in 0..11 {
+            assert_eq!(f.then(&g).apply(x), g.apply(f.apply(x)));
+            assert_eq!(f.compose(&g).apply(x), f.apply(g.apply(x)));
+            assert_eq!(f.inverse().unwrap().apply(f.apply(x)), x);
+            let mut y = x;
+            for _ in 0..13 {
+                y = f.apply(y);
+            }
+            assert_eq!(f.pow(13).apply(x), y);
+            assert_eq!(f.to_matrix().transform_mod([x, 1], 11)[0], f.apply(x));
+        }
+        assert_eq!(Affine::new(2i64, 1, 4).inverse(), None);
+    }
+
+    #[test]
+    fn test_shuffle() {
+        // 2019/22: deal with increment 7, deal into new stack, deal into new stack
+        let len = 10i128;
+        let deal_new = Affine::new(-1, -1, len);
+        let shuffle = Affine::new(7, 0, len).then(&deal_new).then(&deal_new);
+        // Which card ends up at each position
+        let inv = shuffle.inverse().unwrap();
+        let deck: Vec<i128> = (0..len).map(|i| inv.apply(i)).collect();
+        assert_eq!(deck, vec![0, 3, 6, 9, 2, 5, 8, 1, 4, 7]);
+        // Huge sizes stay exact with i128
+        let len = 119315717514047i128;
+        let cut = Affine::new(1, -3, len);
+        let big = cut.pow(101741582076661);
+        assert_eq!(big.apply(0), (-3 * 101741582076661i128).rem_euclid(len));
+        assert_eq!(big.inverse().unwrap().apply(big.apply(2020)), 2020);
+    }
+}
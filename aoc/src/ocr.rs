@@ -0,0 +1,320 @@
+//! Reads the block letters some puzzles draw their answer with
+//!
+//! Both the 6 pixel high font (2016/08, 2019/08, 2019/11, 2021/13, 2022/10)
+//! and the 10 pixel high one (2018/10) are supported.
+
+use crate::{Grid, Point};
+use std::error;
+use std::fmt;
+
+const SMALL: &[(char, &[&str])] = &[
+    ('A', &[".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', &["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', &[".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', &["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', &["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', &[".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', &["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', &[".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', &["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', &["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', &["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', &[".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', &["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', &["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', &[".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', &["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', &["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', &["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE: &[(char, &[&str])] = &[
+    (
+        'A',
+        &[
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        &[
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        &[
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        &[
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        &[
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        &[
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        &[
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        &[
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        &[
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        &[
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        &[
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    // Nothing is lit
+    Empty,
+    // The lit area has a height no font has
+    UnknownHeight(i64),
+    // The glyph starting at this column didn't match any letter
+    UnknownGlyph { column: i64, glyph: String },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::Empty => write!(f, "no lit pixels"),
+            OcrError::UnknownHeight(h) => write!(f, "no font with height {}", h),
+            OcrError::UnknownGlyph { column, glyph } => {
+                write!(f, "unknown glyph at column {}:\n{}", column, glyph)
+            }
+        }
+    }
+}
+
+impl error::Error for OcrError {}
+
+struct Glyph {
+    c: char,
+    // Trimmed to the lit columns
+    rows: Vec<Vec<bool>>,
+}
+
+fn make_font(font: &[(char, &[&str])]) -> Vec<Glyph> {
+    font.iter()
+        .map(|(c, rows)| {
+            let rows: Vec<Vec<bool>> = rows
+                .iter()
+                .map(|r| r.chars().map(|x| x == '#').collect())
+                .collect();
+            let lit = |x: usize| rows.iter().any(|r| r[x]);
+            let first = (0..rows[0].len()).find(|x| lit(*x)).unwrap();
+            let last = (0..rows[0].len()).rev().find(|x| lit(*x)).unwrap();
+            Glyph {
+                c: *c,
+                rows: rows.iter().map(|r| r[first..=last].to_vec()).collect(),
+            }
+        })
+        .collect()
+}
+
+lazy_static! {
+    static ref SMALL_FONT: Vec<Glyph> = make_font(SMALL);
+    static ref LARGE_FONT: Vec<Glyph> = make_font(LARGE);
+}
+
+// Decodes the letters drawn in grid, where is_lit tells which values are pixels
+pub fn ocr<T, F>(grid: &dyn Grid<T>, is_lit: F) -> Result<String, OcrError>
+where
+    T: PartialEq + Copy,
+    F: Fn(T) -> bool,
+{
+    let lit = |p: Point| grid.get_value(p).is_some_and(&is_lit);
+    let lit_points: Vec<Point> = grid.points().filter(|p| lit(*p)).collect();
+    if lit_points.is_empty() {
+        return Err(OcrError::Empty);
+    }
+    let min_x = lit_points.iter().map(|p| p[0]).min().unwrap();
+    let max_x = lit_points.iter().map(|p| p[0]).max().unwrap();
+    let min_y = lit_points.iter().map(|p| p[1]).min().unwrap();
+    let max_y = lit_points.iter().map(|p| p[1]).max().unwrap();
+    let height = max_y - min_y + 1;
+    let font: &[Glyph] = match height {
+        6 => &SMALL_FONT,
+        10 => &LARGE_FONT,
+        _ => return Err(OcrError::UnknownHeight(height)),
+    };
+    let column_lit = |x: i64| (min_y..=max_y).any(|y| lit([x, y]));
+    let matches = |g: &Glyph, x: i64| {
+        g.rows.iter().enumerate().all(|(dy, row)| {
+            row.iter()
+                .enumerate()
+                .all(|(dx, on)| lit([x + dx as i64, min_y + dy as i64]) == *on)
+        })
+    };
+    let mut s = String::new();
+    let mut x = min_x;
+    while x <= max_x {
+        if !column_lit(x) {
+            x += 1;
+            continue;
+        }
+        // Letters can touch, so take the widest glyph that fits
+        if let Some(g) = font
+            .iter()
+            .filter(|g| matches(g, x))
+            .max_by_key(|g| g.rows[0].len())
+        {
+            s.push(g.c);
+            x += g.rows[0].len() as i64;
+        } else {
+            let mut end = x;
+            while end <= max_x && column_lit(end) {
+                end += 1;
+            }
+            let glyph = (min_y..=max_y)
+                .map(|y| {
+                    (x..end)
+                        .map(|xx| if lit([xx, y]) { '#' } else { '.' })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Err(OcrError::UnknownGlyph { column: x, glyph });
+        }
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, FxHashMap};
+
+    #[test]
+    fn test_small() {
+        // The Y touches the B, like in 2016/08
+        let lines = vec![
+            "...................................",
+            ".#..#.###.#....####.#...####...##..",
+            ".#..#..#..#....#....#...##..#.#..#.",
+            ".####..#..#....###...#.#.###..#....",
+            ".#..#..#..#....#......#..#..#.#.##.",
+            ".#..#..#..#....#......#..#..#.#..#.",
+            ".#..#.###.####.####...#..###...###.",
+        ];
+        let grid = parse_grid(&lines);
+        assert_eq!(ocr(&grid, |c| c == '#'), Ok("HILEYBG".to_string()));
+    }
+
+    #[test]
+    fn test_large() {
+        let lines = [
+            "#....#..######",
+            "#....#.......#",
+            ".#..#........#",
+            ".#..#.......#.",
+            "..##.......#..",
+            "..##......#...",
+            ".#..#....#....",
+            ".#..#...#.....",
+            "#....#..#.....",
+            "#....#..######",
+        ];
+        let mut grid: FxHashMap<Point, bool> = FxHashMap::default();
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                if c == '#' {
+                    grid.insert([x as i64 + 10, y as i64 - 3], true);
+                }
+            }
+        }
+        assert_eq!(ocr(&grid, |v| v), Ok("XZ".to_string()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let grid = vec![vec![0; 10]; 6];
+        assert_eq!(ocr(&grid, |v| v == 1), Err(OcrError::Empty));
+        let lines = ["#..#", "####", "#..#"];
+        let grid = parse_grid(&lines);
+        assert_eq!(ocr(&grid, |c| c == '#'), Err(OcrError::UnknownHeight(3)));
+        let lines = [
+            "####.##", "#.....#", "###...#", "#.....#", "#.....#", "#.....#",
+        ];
+        let grid = parse_grid(&lines);
+        assert_eq!(
+            ocr(&grid, |c| c == '#'),
+            Err(OcrError::UnknownGlyph {
+                column: 5,
+                glyph: "##\n.#\n.#\n.#\n.#\n.#".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Layers of markers drawn on top of a grid without touching it
+//!
+//! LayeredGridDrawer wraps any drawer (print, curses, bitmap, ...). Each
+//! layer is a sparse grid of points, paths and labels which is composed over
+//! the base grid when drawing, later layers on top.
+
+use crate::{plot_line, FxHashMap, Grid, GridDrawer, Point};
+use std::marker::PhantomData;
+
+pub struct LayeredGridDrawer<D, T>
+where
+    D: GridDrawer<FxHashMap<Point, T>, T>,
+    T: PartialEq + Copy + Default,
+{
+    drawer: D,
+    layers: Vec<FxHashMap<Point, T>>,
+    visible: Vec<bool>,
+    phantom: PhantomData<T>,
+}
+
+impl<D, T> LayeredGridDrawer<D, T>
+where
+    D: GridDrawer<FxHashMap<Point, T>, T>,
+    T: PartialEq + Copy + Default,
+{
+    pub fn new(drawer: D) -> LayeredGridDrawer<D, T> {
+        LayeredGridDrawer {
+            drawer,
+            layers: vec![],
+            visible: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn drawer(&mut self) -> &mut D {
+        &mut self.drawer
+    }
+
+    // Adds an empty layer on top and returns its index
+    pub fn add_layer(&mut self) -> usize {
+        self.layers.push(FxHashMap::default());
+        self.visible.push(true);
+        self.layers.len() - 1
+    }
+
+    // The layer is a Grid too, so fill, line, blit etc work on it
+    pub fn layer(&mut self, ix: usize) -> &mut FxHashMap<Point, T> {
+        &mut self.layers[ix]
+    }
+
+    pub fn clear_layer(&mut self, ix: usize) {
+        self.layers[ix].clear();
+    }
+
+    pub fn set_visible(&mut self, ix: usize, visible: bool) {
+        self.visible[ix] = visible;
+    }
+
+    pub fn add_points<I>(&mut self, ix: usize, points: I, value: T)
+    where
+        I: IntoIterator<Item = Point>,
+    {
+        for p in points {
+            self.layers[ix].insert(p, value);
+        }
+    }
+
+    // The points, plus straight lines between consecutive ones, so both
+    // astar_grid style paths and corner lists work
+    pub fn add_path(&mut self, ix: usize, path: &[Point], value: T) {
+        for p in path {
+            self.layers[ix].insert(*p, value);
+        }
+        for w in path.windows(2) {
+            for p in plot_line(w[0], w[1]) {
+                self.layers[ix].insert(p, value);
+            }
+        }
+    }
+
+    // Text in SMALLFONT with its top left corner at pos
+    pub fn add_label(&mut self, ix: usize, text: &str, pos: Point, value: T) {
+        self.layers[ix].text(text, pos, value);
+    }
+
+    // The base with the visible layers on top
+    pub fn compose(&self, base: &dyn Grid<T>) -> FxHashMap<Point, T> {
+        let mut g: FxHashMap<Point, T> = base
+            .points()
+            .filter_map(|p| base.get_value(p).map(|v| (p, v)))
+            .collect();
+        for (layer, visible) in self.layers.iter().zip(&self.visible) {
+            if *visible {
+                g.extend(layer.iter().map(|(p, v)| (*p, *v)));
+            }
+        }
+        g
+    }
+}
+
+impl<D, G, T> GridDrawer<G, T> for LayeredGridDrawer<D, T>
+where
+    D: GridDrawer<FxHashMap<Point, T>, T>,
+    G: Grid<T>,
+    T: PartialEq + Copy + Default,
+{
+    fn draw(&mut self, area: &G) {
+        let g = self.compose(area);
+        self.drawer.draw(&g);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, RecordingGridDrawer};
+
+    #[test]
+    fn test_layers() {
+        let grid = parse_grid(&[".....", ".#...", ".....", "....."]);
+        let mut gd = LayeredGridDrawer::new(RecordingGridDrawer::new(|c| c));
+        let path = gd.add_layer();
+        let robots = gd.add_layer();
+        gd.add_path(path, &[[0, 0], [0, 2], [3, 2]], '*');
+        gd.add_points(robots, [[3, 2], [4, 3]], 'R');
+        gd.draw(&grid);
+        gd.set_visible(path, false);
+        gd.draw(&grid);
+        gd.clear_layer(robots);
+        gd.set_visible(path, true);
+        gd.draw(&grid);
+        let rec = gd.drawer();
+        rec.assert_frame(0, "*....\n*#...\n***R.\n....R");
+        rec.assert_frame(1, ".....\n.#...\n...R.\n....R");
+        rec.assert_frame(2, "*....\n*#...\n****.\n.....");
+    }
+
+    #[test]
+    fn test_label() {
+        let grid = vec![vec![0u8; 12]; 10];
+        let mut gd =
+            LayeredGridDrawer::new(RecordingGridDrawer::new(|v| if v == 0 { '.' } else { '#' }));
+        let labels = gd.add_layer();
+        gd.add_label(labels, "A", [1, 1], 1);
+        gd.draw(&grid);
+        let composed = gd.compose(&grid);
+        // The base isn't touched and the label is inside it
+        assert!(grid.iter().flatten().all(|v| *v == 0));
+        assert_eq!(composed.extents(), ([0, 0], [11, 9]));
+        assert!(composed.values().any(|v| *v == 1));
+        assert_eq!(composed.get_value([0, 0]), Some(0));
+    }
+}
@@ -0,0 +1,313 @@
+//! A drawer that keeps every frame, for asserting on simulations in tests
+//!
+//! Frames are stored as the raw values and turned into text with to_ch when
+//! compared. assert_grid_eq gives a line by line diff when they don't match.
+
+use crate::{Grid, GridDrawer, Point};
+use std::fmt::Write;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame<T> {
+    min: Point,
+    width: usize,
+    height: usize,
+    cells: Vec<Option<T>>,
+}
+
+impl<T> RecordedFrame<T>
+where
+    T: PartialEq + Copy,
+{
+    pub fn new(area: &dyn Grid<T>) -> RecordedFrame<T> {
+        let ([min_x, min_y], [max_x, max_y]) = area.extents();
+        let width = (max_x - min_x + 1).max(0) as usize;
+        let height = (max_y - min_y + 1).max(0) as usize;
+        let mut cells = Vec::with_capacity(width * height);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                cells.push(area.get_value([x, y]));
+            }
+        }
+        RecordedFrame {
+            min: [min_x, min_y],
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn extents(&self) -> (Point, Point) {
+        let [min_x, min_y] = self.min;
+        (
+            self.min,
+            [
+                min_x + self.width as i64 - 1,
+                min_y + self.height as i64 - 1,
+            ],
+        )
+    }
+
+    pub fn get(&self, [x, y]: Point) -> Option<T> {
+        let xx = x - self.min[0];
+        let yy = y - self.min[1];
+        if xx < 0 || yy < 0 || xx >= self.width as i64 || yy >= self.height as i64 {
+            return None;
+        }
+        self.cells[yy as usize * self.width + xx as usize]
+    }
+
+    // One line per row, without a trailing newline
+    pub fn to_text<F: Fn(T) -> char>(&self, to_ch: F, missing: char) -> String {
+        let mut s = String::with_capacity((self.width + 1) * self.height);
+        for (i, row) in self.cells.chunks(self.width.max(1)).enumerate() {
+            if i > 0 {
+                s.push('\n');
+            }
+            s.extend(row.iter().map(|v| v.map_or(missing, &to_ch)));
+        }
+        s
+    }
+}
+
+pub struct RecordingGridDrawer<F, T>
+where
+    F: Fn(T) -> char,
+{
+    to_ch: F,
+    missing: char,
+    frames: Vec<RecordedFrame<T>>,
+    phantom: PhantomData<T>,
+}
+
+impl<F, T> RecordingGridDrawer<F, T>
+where
+    F: Fn(T) -> char,
+    T: PartialEq + Copy,
+{
+    pub fn new(to_ch: F) -> RecordingGridDrawer<F, T> {
+        RecordingGridDrawer {
+            to_ch,
+            missing: '.',
+            frames: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    // Char for cells without a value, default is '.' like PrintGridDrawer
+    pub fn set_missing(&mut self, missing: char) {
+        self.missing = missing;
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn frames(&self) -> &[RecordedFrame<T>] {
+        &self.frames
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    pub fn frame_text(&self, ix: usize) -> String {
+        self.frames[ix].to_text(&self.to_ch, self.missing)
+    }
+
+    pub fn texts(&self) -> Vec<String> {
+        (0..self.len()).map(|ix| self.frame_text(ix)).collect()
+    }
+
+    pub fn last_text(&self) -> Option<String> {
+        self.len().checked_sub(1).map(|ix| self.frame_text(ix))
+    }
+
+    // Panics with a diff unless frame ix looks like expected, see
+    // assert_grid_eq
+    pub fn assert_frame(&self, ix: usize, expected: &str) {
+        assert!(
+            ix < self.len(),
+            "frame {} not recorded, only {} frames",
+            ix,
+            self.len()
+        );
+        if let Some(diff) = grid_diff(expected, &self.frame_text(ix)) {
+            panic!("frame {} differs from expected:\n{}", ix, diff);
+        }
+    }
+
+    pub fn assert_last(&self, expected: &str) {
+        assert!(!self.is_empty(), "no frames recorded");
+        self.assert_frame(self.len() - 1, expected);
+    }
+}
+
+impl<F, G, T> GridDrawer<G, T> for RecordingGridDrawer<F, T>
+where
+    F: Fn(T) -> char,
+    G: Grid<T>,
+    T: PartialEq + Copy,
+{
+    fn draw(&mut self, area: &G) {
+        self.frames.push(RecordedFrame::new(area));
+    }
+}
+
+// Removes blank first and last lines and the common indentation, so
+// expected grids can be written as indented raw strings
+pub fn dedent_grid(s: &str) -> String {
+    let lines: Vec<&str> = s.lines().collect();
+    let first = lines.iter().position(|l| !l.trim().is_empty());
+    let last = lines.iter().rposition(|l| !l.trim().is_empty());
+    let (Some(first), Some(last)) = (first, last) else {
+        return String::new();
+    };
+    let lines = &lines[first..=last];
+    let indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|l| l.get(indent..).unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// None if actual is expected after dedent_grid, else the rows side by side
+// with the differing ones marked and the first differing column. actual is
+// compared as it is, so shifted or padded frames don't match.
+pub fn grid_diff(expected: &str, actual: &str) -> Option<String> {
+    let expected = dedent_grid(expected);
+    if expected == actual {
+        return None;
+    }
+    let e: Vec<&str> = expected.lines().collect();
+    let a: Vec<&str> = actual.lines().collect();
+    let width = e.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let width = width.max("expected".len());
+    let mut s = String::new();
+    writeln!(s, "    {:width$} | actual", "expected").unwrap();
+    for row in 0..e.len().max(a.len()) {
+        let el = e.get(row).copied().unwrap_or("");
+        let al = a.get(row).copied().unwrap_or("");
+        if el == al {
+            writeln!(s, "    {:width$} | {}", el, al).unwrap();
+        } else {
+            let col = el
+                .chars()
+                .zip(al.chars())
+                .position(|(x, y)| x != y)
+                .unwrap_or(el.chars().count().min(al.chars().count()));
+            writeln!(s, "  > {:width$} | {}   row {} col {}", el, al, row, col).unwrap();
+        }
+    }
+    if e.len() != a.len() {
+        writeln!(s, "expected {} rows, got {}", e.len(), a.len()).unwrap();
+    }
+    Some(s)
+}
+
+// Like assert_eq for grids as text, with a readable diff
+pub fn assert_grid_eq(actual: &str, expected: &str) {
+    if let Some(diff) = grid_diff(expected, actual) {
+        panic!("grids differ:\n{}", diff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_grid, FxHashMap, SMALLFONT};
+
+    // The scenarios from examples/grid.rs
+    fn make_grid() -> Vec<Vec<char>> {
+        parse_grid(&[
+            "+.......+",
+            ".........",
+            ".#.......",
+            ".....AA..",
+            ".....AA..",
+            ".####....",
+            ".........",
+            ".........",
+            ".........",
+            "+.......+",
+        ])
+    }
+
+    #[test]
+    fn test_recording() {
+        let mut grid = make_grid();
+        let mut gd = RecordingGridDrawer::new(|c| c);
+        gd.draw(&grid);
+        grid.flip_horizontal();
+        grid.set_value([0, 1], '@');
+        gd.draw(&grid);
+        assert_eq!(gd.len(), 2);
+        gd.assert_frame(
+            0,
+            "
+            +.......+
+            .........
+            .#.......
+            .....AA..
+            .....AA..
+            .####....
+            .........
+            .........
+            .........
+            +.......+
+            ",
+        );
+        assert_eq!(gd.frame_text(1).lines().nth(1), Some("@........"));
+        assert_eq!(gd.frames()[1].get([1, 5]), Some('.'));
+        assert_eq!(gd.frames()[1].get([7, 5]), Some('#'));
+    }
+
+    #[test]
+    fn test_text_and_missing() {
+        let mut g = vec![vec![' '; 2 * SMALLFONT.glyph_size().0 as usize]; 8];
+        g.text("Hi", [0, 0], '#');
+        let mut gd = RecordingGridDrawer::new(|c| c);
+        gd.draw(&g);
+        assert_eq!(gd.last_text().unwrap().lines().count(), 8);
+        let mut sparse: FxHashMap<Point, i64> = FxHashMap::default();
+        sparse.insert([-1, 0], 1);
+        sparse.insert([1, 1], 2);
+        let mut gd = RecordingGridDrawer::new(|v| char::from_digit(v as u32, 10).unwrap());
+        gd.set_missing(' ');
+        gd.draw(&sparse);
+        assert_eq!(gd.frames()[0].extents(), ([-1, 0], [1, 1]));
+        gd.assert_last("1  \n  2");
+    }
+
+    #[test]
+    fn test_grid_diff() {
+        assert_eq!(grid_diff("  ab\n  cd\n", "ab\ncd"), None);
+        // Shifted by a column
+        let diff = grid_diff("  ab\n  cd\n", " ab\n cd").unwrap();
+        assert!(diff.contains("  > ab       |  ab   row 0 col 0"));
+        assert!(grid_diff("ab\ncd", "ab \ncd").is_some());
+        let diff = grid_diff("ab\ncd", "ab\ncx\nef").unwrap();
+        assert_eq!(
+            diff,
+            "    expected | actual\n    ab       | ab\n  > cd       | cx   row 1 col 1\n  >          | ef   row 2 col 0\nexpected 2 rows, got 3\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "frame 0 differs")]
+    fn test_assert_frame_fails() {
+        let mut gd = RecordingGridDrawer::new(|c| c);
+        gd.draw(&make_grid());
+        gd.assert_frame(0, "+");
+    }
+}
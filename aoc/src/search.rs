@@ -0,0 +1,250 @@
+//! Observing the path finding functions while they run
+//!
+//! The *_observed versions of astar_grid, dijkstra_grid and astar_graph call
+//! a SearchObserver as nodes are pushed and expanded. SearchVisualizer is an
+//! observer drawing the frontier, the visited nodes and the final path over
+//! the grid with any GridDrawer.
+
+use crate::{FxHashMap, Grid, GridDrawer, LayeredGridDrawer, Point};
+
+pub trait SearchObserver {
+    // p was added to the frontier with the given priority (cost so far plus
+    // heuristic for A*)
+    fn pushed(&mut self, _p: Point, _priority: i64) {}
+    // p was taken from the frontier, cost is the cost of reaching it
+    fn expanded(&mut self, _p: Point, _cost: i64) {}
+    // The goal was reached, path goes in the same direction as the one
+    // returned by the search
+    fn path_found(&mut self, _cost: i64, _path: &[Point]) {}
+}
+
+pub struct NopSearchObserver {}
+
+impl SearchObserver for NopSearchObserver {}
+
+pub struct SearchVisualizer<'a, D, G, T>
+where
+    D: GridDrawer<FxHashMap<Point, T>, T>,
+    G: Grid<T>,
+    T: PartialEq + Copy + Default,
+{
+    drawer: LayeredGridDrawer<D, T>,
+    grid: &'a G,
+    visited: usize,
+    frontier: usize,
+    path: usize,
+    markers: (T, T, T),
+    every: usize,
+    expansions: usize,
+}
+
+impl<'a, D, G, T> SearchVisualizer<'a, D, G, T>
+where
+    D: GridDrawer<FxHashMap<Point, T>, T>,
+    G: Grid<T>,
+    T: PartialEq + Copy + Default,
+{
+    // The markers are the values drawn for frontier, visited and path nodes
+    pub fn new(
+        drawer: D,
+        grid: &'a G,
+        frontier: T,
+        visited: T,
+        path: T,
+    ) -> SearchVisualizer<'a, D, G, T> {
+        let mut drawer = LayeredGridDrawer::new(drawer);
+        let visited_layer = drawer.add_layer();
+        let frontier_layer = drawer.add_layer();
+        let path_layer = drawer.add_layer();
+        SearchVisualizer {
+            drawer,
+            grid,
+            visited: visited_layer,
+            frontier: frontier_layer,
+            path: path_layer,
+            markers: (frontier, visited, path),
+            every: 1,
+            expansions: 0,
+        }
+    }
+
+    // Only draw every nth expansion, the final path is always drawn
+    pub fn set_every(&mut self, n: usize) {
+        self.every = n.max(1);
+    }
+
+    pub fn expansions(&self) -> usize {
+        self.expansions
+    }
+
+    pub fn drawer(&mut self) -> &mut D {
+        self.drawer.drawer()
+    }
+}
+
+impl<D, G, T> SearchObserver for SearchVisualizer<'_, D, G, T>
+where
+    D: GridDrawer<FxHashMap<Point, T>, T>,
+    G: Grid<T>,
+    T: PartialEq + Copy + Default,
+{
+    fn pushed(&mut self, p: Point, _priority: i64) {
+        self.drawer.layer(self.frontier).insert(p, self.markers.0);
+    }
+
+    fn expanded(&mut self, p: Point, _cost: i64) {
+        self.drawer.layer(self.frontier).remove(&p);
+        self.drawer.layer(self.visited).insert(p, self.markers.1);
+        self.expansions += 1;
+        if self.expansions.is_multiple_of(self.every) {
+            self.drawer.draw(self.grid);
+        }
+    }
+
+    fn path_found(&mut self, _cost: i64, path: &[Point]) {
+        let marker = self.markers.2;
+        self.drawer
+            .add_points(self.path, path.iter().copied(), marker);
+        self.drawer.draw(self.grid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        astar_graph_observed, astar_grid_observed, dijkstra_grid, dijkstra_grid_observed,
+        grid_to_directed_graph, grid_to_undirected_graph, parse_grid, FxHashSet,
+        RecordingGridDrawer,
+    };
+
+    fn make_grid() -> Vec<Vec<char>> {
+        parse_grid(&[
+            "#########",
+            "#.......#",
+            "#####...#",
+            "#.......#",
+            "#..######",
+            "##......#",
+            "#####...#",
+            "#...#...#",
+            "#.......#",
+            "#########",
+        ])
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        pushed: usize,
+        expanded: usize,
+        cost: Option<i64>,
+    }
+
+    impl SearchObserver for Counter {
+        fn pushed(&mut self, _p: Point, _priority: i64) {
+            self.pushed += 1;
+        }
+        fn expanded(&mut self, _p: Point, _cost: i64) {
+            self.expanded += 1;
+        }
+        fn path_found(&mut self, cost: i64, _path: &[Point]) {
+            self.cost = Some(cost);
+        }
+    }
+
+    #[test]
+    fn test_observers() {
+        let grid = make_grid();
+        let is_node = |_: &Point, c: &char| *c == '.';
+        let cost = |_: &Point, _: &char, _: &Point, _: &char| Some(1);
+        let (a, f) = ([1, 1], [1, 7]);
+        let mut dijkstra = Counter::default();
+        let res = dijkstra_grid_observed(&grid, is_node, cost, a, f, &mut dijkstra);
+        assert_eq!(res, dijkstra_grid(&grid, is_node, cost, a, f));
+        assert_eq!(dijkstra.cost, Some(22));
+        let mut astar = Counter::default();
+        let (c, _) = astar_grid_observed(&grid, is_node, cost, a, f, &mut astar).unwrap();
+        assert_eq!((c, astar.cost), (22, Some(22)));
+        assert!(astar.pushed > 0 && astar.expanded > 0);
+        let graph = grid_to_undirected_graph(&grid, |_, c| *c == '.', |_, _, _, _| Some(1), 4);
+        let mut graph_obs = Counter::default();
+        let (c, path) = astar_graph_observed(&graph, a, f, &mut graph_obs).unwrap();
+        assert_eq!((c, path.len()), (22, 23));
+        assert_eq!((path[0], path[22]), (a, f));
+        assert_eq!(graph_obs.cost, Some(22));
+    }
+
+    #[derive(Default)]
+    struct Expanded(Vec<Point>);
+
+    impl SearchObserver for Expanded {
+        fn expanded(&mut self, p: Point, _cost: i64) {
+            self.0.push(p);
+        }
+    }
+
+    #[test]
+    fn test_astar_stale() {
+        // [3, 1] is pushed from [3, 2] and then more cheaply from [2, 1],
+        // the old entry must not be expanded again
+        let grid = parse_grid(&[
+            "######", //
+            "#1232#", //
+            "#1119#", //
+            "#32#1#", //
+            "######",
+        ]);
+        let is_node = |_: &Point, c: &char| *c != '#';
+        let cost = |_: &Point, _: &char, _: &Point, c: &char| c.to_digit(10).map(|d| d as i64);
+        let (a, f) = ([1, 1], [4, 3]);
+        let mut grid_obs = Expanded::default();
+        let (c, _) = astar_grid_observed(&grid, is_node, cost, a, f, &mut grid_obs).unwrap();
+        assert_eq!(c, 13);
+        let unique: FxHashSet<Point> = grid_obs.0.iter().copied().collect();
+        assert_eq!(unique.len(), grid_obs.0.len());
+        let graph = grid_to_directed_graph(
+            &grid,
+            |_, c| *c != '#',
+            |_, _, _, c| c.to_digit(10).map(|d| d as i64),
+            4,
+        );
+        let mut graph_obs = Expanded::default();
+        let (c, _) = astar_graph_observed(&graph, a, f, &mut graph_obs).unwrap();
+        assert_eq!(c, 13);
+        let unique: FxHashSet<Point> = graph_obs.0.iter().copied().collect();
+        assert_eq!(unique.len(), graph_obs.0.len());
+    }
+
+    #[test]
+    fn test_visualizer() {
+        let grid = make_grid();
+        let mut vis = SearchVisualizer::new(RecordingGridDrawer::new(|c| c), &grid, '?', 'o', '*');
+        vis.set_every(1000);
+        let res = dijkstra_grid_observed(
+            &grid,
+            |_, c| *c == '.',
+            |_, _, _, _| Some(1),
+            [1, 1],
+            [7, 1],
+            &mut vis,
+        );
+        assert_eq!(res.unwrap().0, 6);
+        // Only the final frame, with visited nodes and the path
+        let rec = vis.drawer();
+        assert_eq!(rec.len(), 1);
+        rec.assert_last(
+            "
+            #########
+            #*******#
+            #####oo?#
+            #...?o?.#
+            #..######
+            ##......#
+            #####...#
+            #...#...#
+            #.......#
+            #########
+            ",
+        );
+    }
+}
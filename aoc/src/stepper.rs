@@ -0,0 +1,371 @@
+//! Interactive stepping through a simulation drawn by any GridDrawer
+//!
+//! SteppingGridDrawer wraps a drawer and keeps the last frames in a ring
+//! buffer. Between frames it reads commands, so a simulation can be paused,
+//! stepped forwards and backwards, sped up, slowed down or fast forwarded.
+
+use crate::{Grid, GridDrawer};
+use std::collections::VecDeque;
+use std::io::BufRead;
+use std::marker::PhantomData;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepCommand {
+    Pause,
+    Resume,
+    TogglePause,
+    // Next frame, from the history if stepped back
+    Step,
+    Back,
+    Faster,
+    Slower,
+    // Frames are numbered from 1 in the order they were drawn
+    Jump(usize),
+    Quit,
+}
+
+impl StepCommand {
+    // The line based syntax used by StdinCommands:
+    // p pause/resume, empty line or n step, b back, + faster, - slower,
+    // g N jump to frame N, r resume, q quit
+    pub fn parse(s: &str) -> Option<StepCommand> {
+        let s = s.trim();
+        match s {
+            "" | "n" => Some(StepCommand::Step),
+            "p" | " " => Some(StepCommand::TogglePause),
+            "r" => Some(StepCommand::Resume),
+            "b" => Some(StepCommand::Back),
+            "+" => Some(StepCommand::Faster),
+            "-" => Some(StepCommand::Slower),
+            "q" => Some(StepCommand::Quit),
+            _ => {
+                let n = s.strip_prefix('g')?.trim().parse().ok()?;
+                Some(StepCommand::Jump(n))
+            }
+        }
+    }
+}
+
+pub trait CommandSource {
+    // A command if one is available, doesn't block
+    fn poll(&mut self) -> Option<StepCommand>;
+    // Blocks until there is a command
+    fn wait(&mut self) -> StepCommand;
+}
+
+// Reads commands from stdin, one per line, on a background thread
+pub struct StdinCommands {
+    rx: Receiver<StepCommand>,
+}
+
+impl StdinCommands {
+    pub fn new() -> StdinCommands {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                match StepCommand::parse(&line) {
+                    Some(cmd) => {
+                        if tx.send(cmd).is_err() {
+                            break;
+                        }
+                    }
+                    None => eprintln!("unknown command {:?}", line),
+                }
+            }
+        });
+        StdinCommands { rx }
+    }
+}
+
+impl Default for StdinCommands {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandSource for StdinCommands {
+    fn poll(&mut self) -> Option<StepCommand> {
+        self.rx.try_recv().ok()
+    }
+
+    fn wait(&mut self) -> StepCommand {
+        // If stdin is closed nobody can unpause us, so just run
+        self.rx.recv().unwrap_or(StepCommand::Resume)
+    }
+}
+
+// Commands given up front, e.g. for tests. When they run out the simulation
+// just runs.
+pub struct ScriptedCommands {
+    commands: VecDeque<StepCommand>,
+}
+
+impl ScriptedCommands {
+    pub fn new(commands: &[StepCommand]) -> ScriptedCommands {
+        ScriptedCommands {
+            commands: commands.iter().copied().collect(),
+        }
+    }
+}
+
+impl CommandSource for ScriptedCommands {
+    fn poll(&mut self) -> Option<StepCommand> {
+        self.commands.pop_front()
+    }
+
+    fn wait(&mut self) -> StepCommand {
+        self.commands.pop_front().unwrap_or(StepCommand::Resume)
+    }
+}
+
+pub struct SteppingGridDrawer<D, G, T>
+where
+    D: GridDrawer<G, T>,
+    G: Grid<T> + Clone,
+    T: PartialEq + Copy,
+{
+    drawer: D,
+    commands: Box<dyn CommandSource>,
+    // Frame number and grid, oldest first
+    history: VecDeque<(usize, G)>,
+    capacity: usize,
+    frame: usize,
+    paused: bool,
+    delay_ms: u64,
+    jump_to: Option<usize>,
+    quit: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<D, G, T> SteppingGridDrawer<D, G, T>
+where
+    D: GridDrawer<G, T>,
+    G: Grid<T> + Clone,
+    T: PartialEq + Copy,
+{
+    // Reads commands from stdin
+    pub fn new(drawer: D) -> SteppingGridDrawer<D, G, T> {
+        SteppingGridDrawer::with_commands(drawer, Box::new(StdinCommands::new()))
+    }
+
+    pub fn with_commands(
+        drawer: D,
+        commands: Box<dyn CommandSource>,
+    ) -> SteppingGridDrawer<D, G, T> {
+        SteppingGridDrawer {
+            drawer,
+            commands,
+            history: VecDeque::new(),
+            capacity: 100,
+            frame: 0,
+            paused: false,
+            delay_ms: 100,
+            jump_to: None,
+            quit: false,
+            phantom: PhantomData,
+        }
+    }
+
+    // How many frames can be stepped back through, default is 100
+    pub fn set_history(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.history.len() > self.capacity {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    // Pause between frames when running, default is 100 ms
+    pub fn set_delay(&mut self, delay_ms: u64) {
+        self.delay_ms = delay_ms;
+    }
+
+    pub fn delay(&self) -> u64 {
+        self.delay_ms
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Number of frames drawn so far
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    // Set when the user asked to quit, the simulation should check this and
+    // stop. Nothing is drawn after that.
+    pub fn quit_requested(&self) -> bool {
+        self.quit
+    }
+
+    pub fn drawer(&mut self) -> &mut D {
+        &mut self.drawer
+    }
+
+    // Index of frame n in history
+    fn history_ix(&self, n: usize) -> Option<usize> {
+        self.history.iter().position(|(f, _)| *f == n)
+    }
+
+    // Handles commands until the simulation should produce the next frame
+    fn interact(&mut self) {
+        // Index in history of the shown frame
+        let mut shown = self.history.len() - 1;
+        loop {
+            let cmd = if self.paused || shown + 1 < self.history.len() {
+                Some(self.commands.wait())
+            } else {
+                self.commands.poll()
+            };
+            let Some(cmd) = cmd else {
+                break;
+            };
+            match cmd {
+                StepCommand::Pause => self.paused = true,
+                StepCommand::Resume => self.paused = false,
+                StepCommand::TogglePause => self.paused = !self.paused,
+                StepCommand::Faster => self.delay_ms /= 2,
+                StepCommand::Slower => self.delay_ms = (self.delay_ms * 2).max(1),
+                StepCommand::Quit => {
+                    self.quit = true;
+                    return;
+                }
+                StepCommand::Step => {
+                    if shown + 1 < self.history.len() {
+                        shown += 1;
+                        self.drawer.draw(&self.history[shown].1);
+                    } else {
+                        return;
+                    }
+                }
+                StepCommand::Back => {
+                    if shown > 0 {
+                        shown -= 1;
+                        self.drawer.draw(&self.history[shown].1);
+                    }
+                }
+                StepCommand::Jump(n) => {
+                    if let Some(ix) = self.history_ix(n) {
+                        shown = ix;
+                        self.paused = true;
+                        self.drawer.draw(&self.history[shown].1);
+                    } else if n > self.frame {
+                        self.jump_to = Some(n);
+                        return;
+                    }
+                }
+            }
+            // Back to the latest frame when resuming after stepping back
+            if !self.paused && shown + 1 < self.history.len() {
+                shown = self.history.len() - 1;
+                self.drawer.draw(&self.history[shown].1);
+            }
+        }
+        if !self.paused && self.delay_ms > 0 {
+            std::thread::sleep(Duration::from_millis(self.delay_ms));
+        }
+    }
+}
+
+impl<D, G, T> GridDrawer<G, T> for SteppingGridDrawer<D, G, T>
+where
+    D: GridDrawer<G, T>,
+    G: Grid<T> + Clone,
+    T: PartialEq + Copy,
+{
+    fn draw(&mut self, area: &G) {
+        if self.quit {
+            return;
+        }
+        self.frame += 1;
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back((self.frame, area.clone()));
+        if let Some(n) = self.jump_to {
+            if self.frame < n {
+                return;
+            }
+            self.jump_to = None;
+            self.paused = true;
+        }
+        self.drawer.draw(area);
+        self.interact();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Remembers the first value of each drawn frame
+    struct LogDrawer {
+        log: Rc<RefCell<Vec<i64>>>,
+    }
+
+    impl GridDrawer<Vec<Vec<i64>>, i64> for LogDrawer {
+        fn draw(&mut self, area: &Vec<Vec<i64>>) {
+            self.log.borrow_mut().push(area[0][0]);
+        }
+    }
+
+    fn run(commands: &[StepCommand], frames: i64) -> (Vec<i64>, bool) {
+        let log = Rc::new(RefCell::new(vec![]));
+        let drawer = LogDrawer { log: log.clone() };
+        let mut stepper =
+            SteppingGridDrawer::with_commands(drawer, Box::new(ScriptedCommands::new(commands)));
+        stepper.set_delay(0);
+        stepper.set_history(3);
+        for i in 1..=frames {
+            stepper.draw(&vec![vec![i]]);
+            if stepper.quit_requested() {
+                break;
+            }
+        }
+        let drawn = log.borrow().clone();
+        (drawn, stepper.quit_requested())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(StepCommand::parse(""), Some(StepCommand::Step));
+        assert_eq!(StepCommand::parse("g 42"), Some(StepCommand::Jump(42)));
+        assert_eq!(StepCommand::parse("g42"), Some(StepCommand::Jump(42)));
+        assert_eq!(StepCommand::parse("x"), None);
+    }
+
+    #[test]
+    fn test_stepping() {
+        use StepCommand::*;
+        // Pause at 1, step to 2 and 3, go back twice (only 3 frames are
+        // kept), forward through the history, then resume
+        let cmds = [
+            Pause, Step, Step, Back, Back, Back, Step, Step, Step, Resume,
+        ];
+        let (drawn, quit) = run(&cmds, 6);
+        assert_eq!(drawn, vec![1, 2, 3, 2, 1, 2, 3, 4, 5, 6]);
+        assert!(!quit);
+    }
+
+    #[test]
+    fn test_jump_and_quit() {
+        use StepCommand::*;
+        // Fast forward to 5 without drawing 2-4, jump back to 4, resume
+        // which shows 5 again, then quit
+        let cmds = [Jump(5), Jump(4), Resume, Quit];
+        let (drawn, quit) = run(&cmds, 10);
+        assert_eq!(drawn, vec![1, 5, 4, 5]);
+        assert!(quit);
+    }
+}
@@ -0,0 +1,424 @@
+//! Drawers that write SVG instead of bitmaps
+//!
+//! Cells are drawn as squares (or pointy top hexagons in odd-r layout) with a
+//! fill, stroke and label per value. Paths, e.g. from astar_grid or
+//! dijkstra_grid, can be drawn on top as polylines.
+
+use crate::{cube_to_oddr, FrameSink, Grid, GridDrawer, HexGrid, HexGridDrawer, Point, Vec3};
+use rustc_hash::FxHashMap;
+use std::fmt::Write;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SvgStyle {
+    pub fill: Option<[u8; 3]>,
+    pub stroke: Option<[u8; 3]>,
+    pub label: Option<String>,
+}
+
+impl SvgStyle {
+    pub fn fill(rgb: [u8; 3]) -> SvgStyle {
+        SvgStyle {
+            fill: Some(rgb),
+            ..Default::default()
+        }
+    }
+
+    // Nothing is drawn for the cell
+    pub fn none() -> SvgStyle {
+        SvgStyle::default()
+    }
+}
+
+fn color(rgb: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn header(width: f64, height: f64, bg: [u8; 3]) -> String {
+    let mut s = String::new();
+    writeln!(
+        s,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width, height, width, height
+    )
+    .unwrap();
+    writeln!(
+        s,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        color(bg)
+    )
+    .unwrap();
+    s
+}
+
+fn paint(style: &SvgStyle) -> String {
+    let fill = style.fill.map(color).unwrap_or_else(|| "none".into());
+    match style.stroke {
+        Some(stroke) => format!(r#"fill="{}" stroke="{}""#, fill, color(stroke)),
+        None => format!(r#"fill="{}""#, fill),
+    }
+}
+
+fn label(s: &mut String, (x, y): (f64, f64), size: f64, text: &str) {
+    writeln!(
+        s,
+        r#"<text x="{}" y="{}" font-size="{}" font-family="monospace" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+        x,
+        y,
+        size,
+        escape(text)
+    )
+    .unwrap();
+}
+
+fn polyline(s: &mut String, points: &[(f64, f64)], rgb: [u8; 3], width: f64) {
+    let points: Vec<String> = points.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+    writeln!(
+        s,
+        r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+        points.join(" "),
+        color(rgb),
+        width
+    )
+    .unwrap();
+}
+
+pub struct SvgGridDrawer<F, G, T>
+where
+    F: Fn(T) -> SvgStyle,
+    G: Grid<T>,
+    T: PartialEq + Copy,
+{
+    to_style: F,
+    frames: FrameSink,
+    rect: Option<(Point, Point)>,
+    bg: [u8; 3],
+    cell_size: f64,
+    paths: Vec<(Vec<Point>, [u8; 3])>,
+    svg: Option<String>,
+    phantom: PhantomData<T>,
+    phantom_g: PhantomData<G>,
+}
+
+// Frames are saved as basename_000001.svg etc, like the bitmap drawers
+impl<F, G, T> SvgGridDrawer<F, G, T>
+where
+    F: Fn(T) -> SvgStyle,
+    G: Grid<T>,
+    T: PartialEq + Copy,
+{
+    pub fn new(to_style: F, basename: &str) -> SvgGridDrawer<F, G, T> {
+        SvgGridDrawer {
+            to_style,
+            frames: FrameSink::with_extension(basename, "svg"),
+            rect: None,
+            bg: [255, 255, 255],
+            cell_size: 10.0,
+            paths: vec![],
+            svg: None,
+            phantom: PhantomData,
+            phantom_g: PhantomData,
+        }
+    }
+
+    pub fn set_rect(&mut self, r: (Point, Point)) {
+        self.rect = Some(r);
+    }
+
+    pub fn set_bg(&mut self, bg: [u8; 3]) {
+        self.bg = bg;
+    }
+
+    // Side of a cell in SVG units, default is 10
+    pub fn set_cell_size(&mut self, size: f64) {
+        self.cell_size = size;
+    }
+
+    // Drawn through the cell centers on top of the grid, in every frame
+    // until cleared
+    pub fn add_path(&mut self, path: &[Point], rgb: [u8; 3]) {
+        self.paths.push((path.to_vec(), rgb));
+    }
+
+    pub fn clear_paths(&mut self) {
+        self.paths.clear();
+    }
+
+    // The last drawn frame
+    pub fn svg(&self) -> Option<&str> {
+        self.svg.as_deref()
+    }
+
+    pub fn save_image(&self) {
+        if let Some(svg) = &self.svg {
+            self.frames.save_data(svg.as_bytes());
+        }
+    }
+
+    pub fn draw_grid(&mut self, area: &G) {
+        self.frames.next_frame();
+        let ([min_x, min_y], [max_x, max_y]) = self.rect.unwrap_or_else(|| area.extents());
+        let cs = self.cell_size;
+        let width = (max_x - min_x + 1) as f64 * cs;
+        let height = (max_y - min_y + 1) as f64 * cs;
+        let mut s = header(width, height, self.bg);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if let Some(value) = area.get_value([x, y]) {
+                    let style = (self.to_style)(value);
+                    let px = (x - min_x) as f64 * cs;
+                    let py = (y - min_y) as f64 * cs;
+                    if style.fill.is_some() || style.stroke.is_some() {
+                        writeln!(
+                            s,
+                            r#"<rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                            px,
+                            py,
+                            cs,
+                            cs,
+                            paint(&style)
+                        )
+                        .unwrap();
+                    }
+                    if let Some(text) = &style.label {
+                        label(&mut s, (px + cs / 2.0, py + cs / 2.0), cs * 0.8, text);
+                    }
+                }
+            }
+        }
+        for (path, rgb) in &self.paths {
+            let points: Vec<(f64, f64)> = path
+                .iter()
+                .map(|p| {
+                    (
+                        (p[0] - min_x) as f64 * cs + cs / 2.0,
+                        (p[1] - min_y) as f64 * cs + cs / 2.0,
+                    )
+                })
+                .collect();
+            polyline(&mut s, &points, *rgb, cs / 4.0);
+        }
+        s.push_str("</svg>\n");
+        self.svg = Some(s);
+    }
+}
+
+impl<F, G, T> GridDrawer<G, T> for SvgGridDrawer<F, G, T>
+where
+    F: Fn(T) -> SvgStyle,
+    G: Grid<T>,
+    T: PartialEq + Copy,
+{
+    fn draw(&mut self, area: &G) {
+        self.draw_grid(area);
+        self.save_image();
+    }
+}
+
+pub struct SvgHexGridDrawer<F, G, T>
+where
+    F: Fn(T) -> SvgStyle,
+    G: HexGrid<T>,
+    T: PartialEq + Copy,
+{
+    to_style: F,
+    frames: FrameSink,
+    bg: [u8; 3],
+    // Distance from the center to a corner
+    hex_size: f64,
+    paths: Vec<(Vec<Vec3>, [u8; 3])>,
+    svg: Option<String>,
+    phantom: PhantomData<T>,
+    phantom_g: PhantomData<G>,
+}
+
+impl<F, G, T> SvgHexGridDrawer<F, G, T>
+where
+    F: Fn(T) -> SvgStyle,
+    G: HexGrid<T>,
+    T: PartialEq + Copy + Default,
+{
+    pub fn new(to_style: F, basename: &str) -> SvgHexGridDrawer<F, G, T> {
+        SvgHexGridDrawer {
+            to_style,
+            frames: FrameSink::with_extension(basename, "svg"),
+            bg: [255, 255, 255],
+            hex_size: 10.0,
+            paths: vec![],
+            svg: None,
+            phantom: PhantomData,
+            phantom_g: PhantomData,
+        }
+    }
+
+    pub fn set_bg(&mut self, bg: [u8; 3]) {
+        self.bg = bg;
+    }
+
+    // Center to corner distance in SVG units, default is 10
+    pub fn set_hex_size(&mut self, size: f64) {
+        self.hex_size = size;
+    }
+
+    // A path in cube coordinates, drawn through the hexagon centers
+    pub fn add_path(&mut self, path: &[Vec3], rgb: [u8; 3]) {
+        self.paths.push((path.to_vec(), rgb));
+    }
+
+    pub fn clear_paths(&mut self) {
+        self.paths.clear();
+    }
+
+    pub fn svg(&self) -> Option<&str> {
+        self.svg.as_deref()
+    }
+
+    pub fn save_image(&self) {
+        if let Some(svg) = &self.svg {
+            self.frames.save_data(svg.as_bytes());
+        }
+    }
+
+    // Center of an odd-r cell, relative to the top left one
+    fn center(&self, [x, y]: Point, [min_x, min_y]: Point) -> (f64, f64) {
+        let w = 3f64.sqrt() * self.hex_size;
+        let xoffs = if y.rem_euclid(2) != 0 { w / 2.0 } else { 0.0 };
+        (
+            (x - min_x) as f64 * w + xoffs + w / 2.0,
+            (y - min_y) as f64 * 1.5 * self.hex_size + self.hex_size,
+        )
+    }
+
+    pub fn draw_grid(&mut self, area: &G) {
+        self.frames.next_frame();
+        let g: FxHashMap<Point, T> = self.convert(area);
+        let ([min_x, min_y], [max_x, max_y]) = g.extents();
+        let size = self.hex_size;
+        let w = 3f64.sqrt() * size;
+        let width = (max_x - min_x + 1) as f64 * w + w / 2.0;
+        let height = (max_y - min_y) as f64 * 1.5 * size + 2.0 * size;
+        let mut s = header(width, height, self.bg);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                if let Some(value) = g.get(&[x, y]) {
+                    let style = (self.to_style)(*value);
+                    let (cx, cy) = self.center([x, y], [min_x, min_y]);
+                    if style.fill.is_some() || style.stroke.is_some() {
+                        let corners: Vec<String> = (0..6)
+                            .map(|i| {
+                                let a = (60.0 * i as f64 - 30.0).to_radians();
+                                format!("{},{}", cx + size * a.cos(), cy + size * a.sin())
+                            })
+                            .collect();
+                        writeln!(
+                            s,
+                            r#"<polygon points="{}" {}/>"#,
+                            corners.join(" "),
+                            paint(&style)
+                        )
+                        .unwrap();
+                    }
+                    if let Some(text) = &style.label {
+                        label(&mut s, (cx, cy), size, text);
+                    }
+                }
+            }
+        }
+        for (path, rgb) in &self.paths {
+            let points: Vec<(f64, f64)> = path
+                .iter()
+                .map(|p| self.center(cube_to_oddr(*p), [min_x, min_y]))
+                .collect();
+            polyline(&mut s, &points, *rgb, size / 3.0);
+        }
+        s.push_str("</svg>\n");
+        self.svg = Some(s);
+    }
+}
+
+impl<F, G, T> HexGridDrawer<G, T> for SvgHexGridDrawer<F, G, T>
+where
+    F: Fn(T) -> SvgStyle,
+    G: HexGrid<T>,
+    T: PartialEq + Copy + Default,
+{
+    fn draw(&mut self, area: &G) {
+        self.draw_grid(area);
+        self.save_image();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{astar_grid, parse_grid};
+
+    #[test]
+    fn test_svg_grid() {
+        let lines = ["#....", "#.##.", "..#<."];
+        let grid = parse_grid(&lines);
+        let mut gd = SvgGridDrawer::new(
+            |c| match c {
+                '#' => SvgStyle::fill([0, 0, 0]),
+                '.' => SvgStyle::none(),
+                c => SvgStyle {
+                    stroke: Some([255, 0, 0]),
+                    label: Some(c.to_string()),
+                    ..Default::default()
+                },
+            },
+            "svgtest",
+        );
+        gd.set_cell_size(4.0);
+        let (_, path) = astar_grid(
+            &grid,
+            |_, c| *c != '#',
+            |_, _, _, _| Some(1),
+            [1, 0],
+            [4, 2],
+        )
+        .unwrap();
+        gd.add_path(&path, [0, 0, 255]);
+        gd.draw_grid(&grid);
+        let svg = gd.svg().unwrap();
+        assert!(
+            svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="12""#)
+        );
+        assert_eq!(svg.matches(r##"fill="#000000"/>"##).count(), 5);
+        assert!(svg.contains(
+            r##"<rect x="12" y="8" width="4" height="4" fill="none" stroke="#ff0000"/>"##
+        ));
+        assert!(svg.contains(">&lt;</text>"));
+        assert!(svg.contains(r#"<polyline points="18,10 18,6 18,2 14,2 10,2 6,2""#));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_svg_hex_grid() {
+        let mut grid: FxHashMap<Vec3, char> = FxHashMap::default();
+        grid.set_value([0, 0, 0], 'a');
+        grid.set_value([1, -1, 0], 'b');
+        grid.set_value([0, -1, 1], 'c');
+        let mut gd = SvgHexGridDrawer::new(
+            |c: char| SvgStyle {
+                fill: Some([200, 200, 200]),
+                stroke: Some([0, 0, 0]),
+                label: Some(c.to_string()),
+            },
+            "svgtest",
+        );
+        gd.add_path(&[[0, 0, 0], [0, -1, 1]], [255, 0, 0]);
+        gd.draw_grid(&grid);
+        let svg = gd.svg().unwrap();
+        assert_eq!(svg.matches("<polygon").count(), 3);
+        assert_eq!(svg.matches("<text").count(), 3);
+        assert!(svg.contains("<polyline"));
+    }
+}
@@ -0,0 +1,397 @@
+//! Viewing sets of voxels, as used by the 3D cube puzzles
+//!
+//! A VoxelScene is a set of colored unit cubes. It can be rendered without a
+//! display to images of a camera orbiting the scene, saved as numbered PNGs
+//! like the bitmap drawers or into an Animation. With the vis3d feature it
+//! can also be shown in a kiss3d window, where the camera can be orbited
+//! with the mouse. A sequence of scenes, like the steps of a simulation, can
+//! be shown or saved the same way with show_scenes and save_scenes.
+
+use crate::{Animation, FrameSink, FxHashMap, Vec3};
+use image::{Rgb, RgbImage};
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+
+const FACES: [Vec3; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
+];
+
+#[derive(Debug, Clone)]
+pub struct VoxelScene {
+    voxels: FxHashMap<Vec3, [u8; 3]>,
+    size: (u32, u32),
+    bg: [u8; 3],
+    pitch: f64,
+}
+
+impl Default for VoxelScene {
+    fn default() -> Self {
+        VoxelScene {
+            voxels: FxHashMap::default(),
+            size: (400, 400),
+            bg: [255, 255, 255],
+            pitch: 0.5,
+        }
+    }
+}
+
+impl VoxelScene {
+    pub fn new() -> VoxelScene {
+        Self::default()
+    }
+
+    pub fn from_set<S: BuildHasher>(set: &HashSet<Vec3, S>, color: [u8; 3]) -> VoxelScene {
+        let mut scene = VoxelScene::new();
+        for p in set {
+            scene.insert(*p, color);
+        }
+        scene
+    }
+
+    pub fn from_map<T, F, S>(map: &HashMap<Vec3, T, S>, to_color: F) -> VoxelScene
+    where
+        T: Copy,
+        F: Fn(T) -> [u8; 3],
+        S: BuildHasher,
+    {
+        let mut scene = VoxelScene::new();
+        for (p, v) in map {
+            scene.insert(*p, to_color(*v));
+        }
+        scene
+    }
+
+    pub fn insert(&mut self, p: Vec3, color: [u8; 3]) {
+        self.voxels.insert(p, color);
+    }
+
+    pub fn remove(&mut self, p: Vec3) {
+        self.voxels.remove(&p);
+    }
+
+    pub fn len(&self) -> usize {
+        self.voxels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voxels.is_empty()
+    }
+
+    // Image size in pixels, default is 400x400
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.size = (width, height);
+    }
+
+    pub fn set_bg(&mut self, bg: [u8; 3]) {
+        self.bg = bg;
+    }
+
+    // Camera angle above the horizontal plane in radians, default is 0.5
+    pub fn set_pitch(&mut self, pitch: f64) {
+        self.pitch = pitch;
+    }
+
+    fn extents(&self) -> (Vec3, Vec3) {
+        extents(std::slice::from_ref(self))
+    }
+
+    // Renders the scene seen from the camera rotated yaw radians around the
+    // y axis, with orthographic projection and simple shading
+    pub fn render(&self, yaw: f64) -> RgbImage {
+        self.render_within(yaw, self.extents())
+    }
+
+    // Like render, with the view fitted to the given extents instead of the
+    // scene's own, so a sequence of scenes doesn't jump around
+    fn render_within(&self, yaw: f64, (min, max): (Vec3, Vec3)) -> RgbImage {
+        let (w, h) = self.size;
+        let mut image = RgbImage::from_pixel(w, h, Rgb(self.bg));
+        if self.voxels.is_empty() {
+            return image;
+        }
+        let center: [f64; 3] = std::array::from_fn(|i| (min[i] + max[i]) as f64 / 2.0);
+        let radius = (0..3)
+            .map(|i| ((max[i] - min[i] + 1) as f64 / 2.0).powi(2))
+            .sum::<f64>()
+            .sqrt();
+        let scale = 0.95 * w.min(h) as f64 / (2.0 * radius);
+        let (sy, cy) = yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+        let rotate = |v: [f64; 3]| {
+            let x = v[0] * cy - v[2] * sy;
+            let z = v[0] * sy + v[2] * cy;
+            [x, v[1] * cp - z * sp, v[1] * sp + z * cp]
+        };
+        let project = |v: [f64; 3]| {
+            let r = rotate([v[0] - center[0], v[1] - center[1], v[2] - center[2]]);
+            (
+                w as f64 / 2.0 + r[0] * scale,
+                h as f64 / 2.0 - r[1] * scale,
+                r[2],
+            )
+        };
+        // Visible faces as (depth, corners, color), painted back to front
+        let mut faces = vec![];
+        for (p, color) in &self.voxels {
+            for n in FACES {
+                if self.voxels.contains_key(&crate::vec_add(*p, n)) {
+                    continue;
+                }
+                let nf = n.map(|x| x as f64);
+                let facing = rotate(nf)[2];
+                if facing <= 0.0 {
+                    continue;
+                }
+                // The two axes spanning the face
+                let axis = n.iter().position(|x| *x != 0).unwrap();
+                let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+                let corners: Vec<(f64, f64, f64)> =
+                    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                        .iter()
+                        .map(|(da, db)| {
+                            let mut v: [f64; 3] =
+                                std::array::from_fn(|i| p[i] as f64 + nf[i] / 2.0);
+                            v[a] += da / 2.0;
+                            v[b] += db / 2.0;
+                            project(v)
+                        })
+                        .collect();
+                let depth = corners.iter().map(|c| c.2).sum::<f64>() / 4.0;
+                let shade = 0.35 + 0.65 * facing;
+                let rgb = color.map(|c| (c as f64 * shade).round() as u8);
+                faces.push((depth, corners, rgb));
+            }
+        }
+        faces.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, corners, rgb) in faces {
+            fill_quad(&mut image, &corners, rgb);
+        }
+        image
+    }
+
+    // A full turn of the camera in the given number of frames
+    pub fn orbit(&self, frames: usize) -> impl Iterator<Item = RgbImage> + '_ {
+        (0..frames).map(move |i| self.render(std::f64::consts::TAU * i as f64 / frames as f64))
+    }
+
+    // Saves an orbit as basename_000001.png etc, like the bitmap drawers
+    pub fn save_orbit(&self, basename: &str, frames: usize) {
+        let mut sink = FrameSink::new(basename);
+        for image in self.orbit(frames) {
+            sink.next_frame();
+            sink.save_image(&image);
+        }
+    }
+
+    pub fn add_orbit(&self, animation: &mut Animation, frames: usize) {
+        for image in self.orbit(frames) {
+            animation.add_frame(&image);
+        }
+    }
+
+    // Opens a window with the scene slowly turning, drag to orbit. Returns
+    // when the window is closed.
+    #[cfg(feature = "vis3d")]
+    pub fn show(&self, title: &str) {
+        show_scenes(std::slice::from_ref(self), title, 1);
+    }
+}
+
+fn extents(scenes: &[VoxelScene]) -> (Vec3, Vec3) {
+    let mut min = [i64::MAX; 3];
+    let mut max = [i64::MIN; 3];
+    for p in scenes.iter().flat_map(|s| s.voxels.keys()) {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+    (min, max)
+}
+
+// Renders each scene once, the camera turning yaw_step radians between them,
+// and saves them as basename_000001.png etc. The view fits all the scenes.
+pub fn save_scenes(scenes: &[VoxelScene], basename: &str, yaw_step: f64) {
+    let bounds = extents(scenes);
+    let mut sink = FrameSink::new(basename);
+    for (i, scene) in scenes.iter().enumerate() {
+        sink.next_frame();
+        sink.save_image(&scene.render_within(yaw_step * i as f64, bounds));
+    }
+}
+
+// Shows the scenes one after another in a window, each for the given number
+// of window frames, with the camera slowly turning. The size, pitch and
+// background are the first scene's. Stays on the last scene until the window
+// is closed.
+#[cfg(feature = "vis3d")]
+pub fn show_scenes(scenes: &[VoxelScene], title: &str, frames: usize) {
+    use kiss3d::camera::ArcBall;
+    use kiss3d::light::Light;
+    use kiss3d::nalgebra::{Point3, Translation3};
+    use kiss3d::window::Window;
+
+    let Some(first) = scenes.first() else {
+        return;
+    };
+    let (w, h) = first.size;
+    let mut window = Window::new_with_size(title, w, h);
+    window.set_light(Light::StickToCamera);
+    let [r, g, b] = first.bg;
+    window.set_background_color(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    if scenes.iter().all(|s| s.is_empty()) {
+        while window.render() {}
+        return;
+    }
+    let (min, max) = extents(scenes);
+    let center: [f32; 3] = std::array::from_fn(|i| (min[i] + max[i]) as f32 / 2.0);
+    let radius = (0..3)
+        .map(|i| ((max[i] - min[i] + 1) as f32).powi(2))
+        .sum::<f32>()
+        .sqrt();
+    let eye = Point3::new(0.0, radius * first.pitch.sin() as f32, radius * 1.5);
+    let mut camera = ArcBall::new(eye, Point3::origin());
+    let mut cubes = vec![];
+    let mut frame = 0;
+    while window.render_with_camera(&mut camera) {
+        let ix = frame / frames.max(1);
+        if ix < scenes.len() && frame % frames.max(1) == 0 {
+            for mut c in cubes.drain(..) {
+                window.remove_node(&mut c);
+            }
+            for (p, [r, g, b]) in &scenes[ix].voxels {
+                let mut c = window.add_cube(1.0, 1.0, 1.0);
+                c.set_color(*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0);
+                c.append_translation(&Translation3::new(
+                    p[0] as f32 - center[0],
+                    p[1] as f32 - center[1],
+                    p[2] as f32 - center[2],
+                ));
+                cubes.push(c);
+            }
+        }
+        camera.set_yaw(camera.yaw() + 0.005);
+        frame += 1;
+    }
+}
+
+// Fills a convex quadrilateral, the corners in order around it
+fn fill_quad(image: &mut RgbImage, corners: &[(f64, f64, f64)], rgb: [u8; 3]) {
+    let (w, h) = image.dimensions();
+    let min_x = corners
+        .iter()
+        .map(|c| c.0)
+        .fold(f64::MAX, f64::min)
+        .floor()
+        .max(0.0) as u32;
+    let max_x = corners
+        .iter()
+        .map(|c| c.0)
+        .fold(f64::MIN, f64::max)
+        .ceil()
+        .min(w as f64) as u32;
+    let min_y = corners
+        .iter()
+        .map(|c| c.1)
+        .fold(f64::MAX, f64::min)
+        .floor()
+        .max(0.0) as u32;
+    let max_y = corners
+        .iter()
+        .map(|c| c.1)
+        .fold(f64::MIN, f64::max)
+        .ceil()
+        .min(h as f64) as u32;
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+            let sides: Vec<f64> = (0..corners.len())
+                .map(|i| {
+                    let a = corners[i];
+                    let b = corners[(i + 1) % corners.len()];
+                    (b.0 - a.0) * (py - a.1) - (b.1 - a.1) * (px - a.0)
+                })
+                .collect();
+            if sides.iter().all(|s| *s >= 0.0) || sides.iter().all(|s| *s <= 0.0) {
+                image.put_pixel(x, y, Rgb(rgb));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FxHashSet;
+
+    #[test]
+    fn test_render() {
+        let set: FxHashSet<Vec3> = [[0, 0, 0], [1, 0, 0], [0, 1, 0]].into_iter().collect();
+        let mut scene = VoxelScene::from_set(&set, [255, 0, 0]);
+        scene.set_size(60, 40);
+        let image = scene.render(0.3);
+        assert_eq!(image.dimensions(), (60, 40));
+        // Background in the corner, shaded red in the middle
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255]);
+        let mid = image.get_pixel(30, 20).0;
+        assert!(mid[0] > 80 && mid[1] == 0 && mid[2] == 0);
+        assert_eq!(
+            VoxelScene::new().render(0.0).get_pixel(200, 200).0,
+            [255; 3]
+        );
+    }
+
+    #[test]
+    fn test_save_orbit() {
+        let dir = std::env::temp_dir().join(format!("aoc_voxel_{}", std::process::id()));
+        let set: FxHashSet<Vec3> = [[0, 0, 0]].into_iter().collect();
+        let mut scene = VoxelScene::from_set(&set, [255, 0, 0]);
+        scene.set_size(10, 10);
+        scene.save_orbit(dir.join("orbit").to_str().unwrap(), 2);
+        assert!(dir.join("orbit_000001.png").exists());
+        assert!(dir.join("orbit_000002.png").exists());
+        assert!(!dir.join("orbit_000000.png").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_save_scenes() {
+        let dir = std::env::temp_dir().join(format!("aoc_voxel_scenes_{}", std::process::id()));
+        let mut small = VoxelScene::new();
+        small.set_size(20, 20);
+        small.insert([0, 0, 0], [255, 0, 0]);
+        let mut large = small.clone();
+        large.insert([3, 0, 0], [255, 0, 0]);
+        save_scenes(
+            &[small.clone(), large],
+            dir.join("step").to_str().unwrap(),
+            0.0,
+        );
+        // Both are fitted to the larger extents, so the first stays small
+        let first = image::open(dir.join("step_000001.png")).unwrap().to_rgb8();
+        assert_ne!(first, small.render(0.0));
+        assert!(dir.join("step_000002.png").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_colors() {
+        let mut map: FxHashMap<Vec3, bool> = FxHashMap::default();
+        map.insert([0, 0, 0], true);
+        map.insert([0, 0, 1], false);
+        let mut scene = VoxelScene::from_map(&map, |v| if v { [0, 0, 255] } else { [0, 255, 0] });
+        scene.set_pitch(0.0);
+        scene.set_size(50, 50);
+        // Looking down the z axis the nearer z=1 voxel hides the other
+        let front = scene.render(0.0);
+        assert!(front.get_pixel(25, 25).0[1] > 0);
+        let back = scene.render(std::f64::consts::PI);
+        assert!(back.get_pixel(25, 25).0[2] > 0);
+        assert_eq!(scene.orbit(4).count(), 4);
+    }
+}